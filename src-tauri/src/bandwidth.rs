@@ -0,0 +1,339 @@
+// src/bandwidth.rs
+// 全域頻寬排程：網站、直鏈、BT 共用一個總量 token bucket，另有各引擎的份額上限。
+// 網站與直鏈每收一個 chunk 先向這裡要 token；BT 由 librqbit 自己限速（上限 = 份額），
+// 實際流量每秒記回總量 bucket，其他引擎就只分到剩下的。設定存檔時即時套用。
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// 可累積的突發量（秒數 × 速率）
+const BURST_SECS: f64 = 0.5;
+/// 欠量上限（秒數 × 速率）：調降限速或 BT 突發後，不讓其他引擎被餓太久
+const MAX_DEBT_SECS: f64 = 5.0;
+/// 等待時的輪詢間隔，期間可回應取消與限速調整
+const WAIT_STEP: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    Site,
+    Http,
+    Bt,
+}
+
+/// 各引擎可用的總頻寬比例（%）。總量不限時不作用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BandwidthShares {
+    pub site: u8,
+    pub http: u8,
+    pub bt: u8,
+}
+
+impl Default for BandwidthShares {
+    fn default() -> Self {
+        Self {
+            site: 100,
+            http: 100,
+            bt: 100,
+        }
+    }
+}
+
+impl BandwidthShares {
+    fn of(&self, engine: Engine) -> u8 {
+        match engine {
+            Engine::Site => self.site,
+            Engine::Http => self.http,
+            Engine::Bt => self.bt,
+        }
+    }
+}
+
+struct Bucket {
+    /// bytes/sec，0 = 不限
+    rate: u64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl Bucket {
+    fn new() -> Self {
+        Self {
+            rate: 0,
+            tokens: 0.0,
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last).as_secs_f64();
+        self.last = now;
+        if self.rate == 0 {
+            self.tokens = 0.0;
+            return;
+        }
+        let rate = self.rate as f64;
+        self.tokens = (self.tokens + elapsed * rate).min(rate * BURST_SECS);
+    }
+
+    fn set_rate(&mut self, rate: u64, now: Instant) {
+        self.refill(now);
+        self.rate = rate;
+        self.tokens = self
+            .tokens
+            .clamp(-(rate as f64) * MAX_DEBT_SECS, rate as f64 * BURST_SECS);
+    }
+
+    /// 扣 token（可欠）
+    fn take(&mut self, bytes: u64, now: Instant) {
+        self.refill(now);
+        if self.rate > 0 {
+            self.tokens = (self.tokens - bytes as f64).max(-(self.rate as f64) * MAX_DEBT_SECS);
+        }
+    }
+
+    /// 還清欠量所需時間
+    fn wait_time(&mut self, now: Instant) -> Duration {
+        self.refill(now);
+        if self.rate == 0 || self.tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(-self.tokens / self.rate as f64)
+    }
+}
+
+struct Buckets {
+    total: Bucket,
+    site: Bucket,
+    http: Bucket,
+    shares: BandwidthShares,
+}
+
+impl Buckets {
+    fn engine(&mut self, engine: Engine) -> Option<&mut Bucket> {
+        match engine {
+            Engine::Site => Some(&mut self.site),
+            Engine::Http => Some(&mut self.http),
+            Engine::Bt => None,
+        }
+    }
+}
+
+pub struct BandwidthLimiter {
+    inner: Mutex<Buckets>,
+}
+
+impl Default for BandwidthLimiter {
+    fn default() -> Self {
+        Self {
+            inner: Mutex::new(Buckets {
+                total: Bucket::new(),
+                site: Bucket::new(),
+                http: Bucket::new(),
+                shares: BandwidthShares::default(),
+            }),
+        }
+    }
+}
+
+/// 總量 × 比例；總量不限時回 0（不限）
+fn share_of(total_bps: u64, percent: u8) -> u64 {
+    if total_bps == 0 {
+        return 0;
+    }
+    (total_bps * u64::from(percent.clamp(1, 100)) / 100).max(1)
+}
+
+impl BandwidthLimiter {
+    /// 套用總量上限（bytes/sec，0 = 不限）與各引擎比例
+    pub fn configure(&self, total_bps: u64, shares: BandwidthShares) {
+        let now = Instant::now();
+        let mut b = self.inner.lock().unwrap();
+        b.shares = shares;
+        b.total.set_rate(total_bps, now);
+        b.site.set_rate(share_of(total_bps, shares.site), now);
+        b.http.set_rate(share_of(total_bps, shares.http), now);
+    }
+
+    /// 該引擎目前的上限 bytes/sec，0 = 不限
+    pub fn engine_cap(&self, engine: Engine) -> u64 {
+        let b = self.inner.lock().unwrap();
+        share_of(b.total.rate, b.shares.of(engine))
+    }
+
+    /// 記下已用掉的流量，不等待（BT 由 librqbit 自己限速，只把實際流量記回總量）
+    pub fn charge(&self, engine: Engine, bytes: u64) {
+        let now = Instant::now();
+        let mut b = self.inner.lock().unwrap();
+        b.total.take(bytes, now);
+        if let Some(bucket) = b.engine(engine) {
+            bucket.take(bytes, now);
+        }
+    }
+
+    /// 還清總量與該引擎欠量所需時間
+    fn wait_time(&self, engine: Engine) -> Duration {
+        let now = Instant::now();
+        let mut b = self.inner.lock().unwrap();
+        let engine_wait = b
+            .engine(engine)
+            .map_or(Duration::ZERO, |e| e.wait_time(now));
+        b.total.wait_time(now).max(engine_wait)
+    }
+
+    /// 綁定引擎的限速把手，傳給下載迴圈
+    pub fn throttle(self: &Arc<Self>, engine: Engine) -> Throttle {
+        Throttle {
+            limiter: self.clone(),
+            engine,
+            task: None,
+        }
+    }
+}
+
+/// 單一任務的速率上限（bytes/sec，0 = 不限），可在下載中調整
+pub struct RateLimit {
+    bucket: Mutex<Bucket>,
+}
+
+impl RateLimit {
+    pub fn new(bps: u64) -> Self {
+        let limit = Self {
+            bucket: Mutex::new(Bucket::new()),
+        };
+        limit.set(bps);
+        limit
+    }
+
+    pub fn set(&self, bps: u64) {
+        self.bucket.lock().unwrap().set_rate(bps, Instant::now());
+    }
+
+    pub fn get(&self) -> u64 {
+        self.bucket.lock().unwrap().rate
+    }
+}
+
+#[derive(Clone)]
+pub struct Throttle {
+    limiter: Arc<BandwidthLimiter>,
+    engine: Engine,
+    task: Option<Arc<RateLimit>>,
+}
+
+impl Throttle {
+    /// 再疊一層任務自己的上限
+    pub fn with_task_limit(mut self, limit: Arc<RateLimit>) -> Self {
+        self.task = Some(limit);
+        self
+    }
+
+    /// 記下剛收到的 bytes，超出額度就等到還清；stop 被設起時提早返回 false
+    pub async fn acquire(&self, bytes: u64, stop: &AtomicBool) -> bool {
+        self.wait_for(bytes, || stop.load(Ordering::Relaxed)).await
+    }
+
+    /// 同 acquire，多一個停止旗標（分段下載的 abort）
+    pub async fn acquire_either(&self, bytes: u64, stop: &AtomicBool, abort: &AtomicBool) -> bool {
+        self.wait_for(bytes, || {
+            stop.load(Ordering::Relaxed) || abort.load(Ordering::Relaxed)
+        })
+        .await
+    }
+
+    async fn wait_for(&self, bytes: u64, stopped: impl Fn() -> bool) -> bool {
+        self.limiter.charge(self.engine, bytes);
+        if let Some(task) = &self.task {
+            task.bucket.lock().unwrap().take(bytes, Instant::now());
+        }
+        loop {
+            let task_wait = self.task.as_ref().map_or(Duration::ZERO, |t| {
+                t.bucket.lock().unwrap().wait_time(Instant::now())
+            });
+            let wait = self.limiter.wait_time(self.engine).max(task_wait);
+            if wait.is_zero() {
+                return true;
+            }
+            if stopped() {
+                return false;
+            }
+            tokio::time::sleep(wait.min(WAIT_STEP)).await;
+        }
+    }
+
+    /// 目前上限 bytes/sec，0 = 不限
+    pub fn cap(&self) -> u64 {
+        let engine = self.limiter.engine_cap(self.engine);
+        match self.task.as_ref().map_or(0, |t| t.get()) {
+            0 => engine,
+            task if engine == 0 => task,
+            task => task.min(engine),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shares_scale_total() {
+        assert_eq!(share_of(0, 50), 0);
+        assert_eq!(share_of(1000, 50), 500);
+        assert_eq!(share_of(1000, 0), 10);
+        assert_eq!(share_of(1000, 200), 1000);
+    }
+
+    #[test]
+    fn bucket_charges_debt_and_refills() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new();
+        bucket.last = start;
+        bucket.set_rate(1000, start);
+        bucket.take(1500, start);
+        assert_eq!(bucket.wait_time(start), Duration::from_millis(1500));
+        assert_eq!(
+            bucket.wait_time(start + Duration::from_secs(2)),
+            Duration::ZERO
+        );
+        // 調成不限：欠量一筆勾銷
+        bucket.take(5000, start + Duration::from_secs(2));
+        bucket.set_rate(0, start + Duration::from_secs(2));
+        assert_eq!(
+            bucket.wait_time(start + Duration::from_secs(2)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn engine_bucket_is_tighter_than_total() {
+        let limiter = BandwidthLimiter::default();
+        limiter.configure(
+            1000,
+            BandwidthShares {
+                site: 50,
+                ..Default::default()
+            },
+        );
+        assert_eq!(limiter.engine_cap(Engine::Site), 500);
+        assert_eq!(limiter.engine_cap(Engine::Http), 1000);
+        limiter.charge(Engine::Site, 1000);
+        let now = Instant::now();
+        let mut b = limiter.inner.lock().unwrap();
+        assert!(b.site.wait_time(now) > b.total.wait_time(now));
+    }
+
+    #[test]
+    fn task_limit_tightens_cap() {
+        let limiter = Arc::new(BandwidthLimiter::default());
+        let throttle = limiter.throttle(Engine::Http);
+        assert_eq!(throttle.cap(), 0);
+        let throttle = throttle.with_task_limit(Arc::new(RateLimit::new(300)));
+        assert_eq!(throttle.cap(), 300);
+        limiter.configure(200, BandwidthShares::default());
+        assert_eq!(throttle.cap(), 200);
+    }
+}
@@ -1,50 +1,65 @@
 // src/commands/common.rs
 
+use crate::cookies::CookieDomain;
 use crate::db;
-use crate::providers::{ClipboardPayload, Site};
+use crate::error::DownloadError;
+use crate::http_dl::manager::HttpManager;
+use crate::library;
+use crate::providers::{self, rules::RulesState, ClipboardPayload};
+use crate::queue;
+use crate::schedule::{self, ActiveProfile, ScheduleState};
 use crate::settings::{AppSettings, SettingsState};
 use crate::state::AppState;
+use crate::torrent;
 
 use clipboard::{ClipboardContext, ClipboardProvider};
+use serde_json::json;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use tauri::command;
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Manager, State};
 
 /// 讀取剪貼簿內容
 #[command]
-pub fn read_clipboard() -> Result<String, String> {
+pub fn read_clipboard() -> Result<String, DownloadError> {
     let mut ctx: ClipboardContext =
         ClipboardProvider::new().map_err(|e| format!("Error creating clipboard context: {}", e))?;
 
     ctx.get_contents()
-        .map_err(|e| format!("Error reading clipboard: {}", e))
+        .map_err(|e| DownloadError::Other(format!("Error reading clipboard: {}", e)))
 }
 
 /// 取得所有任務列表
 #[tauri::command]
-pub fn load_all_tasks(app_handle: tauri::AppHandle) -> Result<Vec<ClipboardPayload>, String> {
-    db::get_all_tasks(&app_handle).map_err(|e| format!("讀取資料庫失敗: {:?}", e))
+pub fn load_all_tasks(
+    app_handle: tauri::AppHandle,
+) -> Result<Vec<ClipboardPayload>, DownloadError> {
+    db::get_all_tasks(&app_handle)
+        .map_err(|e| DownloadError::Other(format!("讀取資料庫失敗: {:?}", e)))
 }
 
+/// 刪除任務；下載中的先取消，串流才不會在任務移除後繼續寫檔
 #[tauri::command]
-pub fn remove_task(app_handle: AppHandle, url: String) -> Result<(), String> {
-    db::delete_task_by_url(&app_handle, &url).map_err(|e| format!("刪除任務失敗: {:?}", e))
+pub fn remove_task(app_handle: AppHandle, url: String) -> Result<(), DownloadError> {
+    queue::cancel_task(&app_handle, &url)?;
+    db::delete_task_by_url(&app_handle, &url)
+        .map_err(|e| DownloadError::Other(format!("刪除任務失敗: {:?}", e)))
 }
 
+/// 刪除全部任務；下載中的先取消，同 remove_task
 #[tauri::command]
-pub fn remove_all_tasks(app_handle: AppHandle) -> Result<(), String> {
-    db::clear_all_tasks(&app_handle).map_err(|e| format!("刪除全部任務失敗: {:?}", e))
+pub fn remove_all_tasks(app_handle: AppHandle) -> Result<(), DownloadError> {
+    Ok(queue::clear(&app_handle)?)
 }
 
 #[tauri::command]
-pub fn update_task_status(app_handle: AppHandle, url: String, status: String) -> Result<(), String> {
+pub fn update_task_status(
+    app_handle: AppHandle,
+    url: String,
+    status: String,
+) -> Result<(), DownloadError> {
     db::update_task_status(&app_handle, &url, &status)
-        .map_err(|e| format!("更新狀態失敗: {:?}", e))
-}
-
-#[tauri::command]
-pub fn cancel_download(state: State<'_, AppState>) {
-    state.download_cancelled.store(true, Ordering::Relaxed);
+        .map_err(|e| DownloadError::Other(format!("更新狀態失敗: {:?}", e)))
 }
 
 #[tauri::command]
@@ -52,44 +67,113 @@ pub fn get_app_settings(settings: State<'_, SettingsState>) -> AppSettings {
     settings.get()
 }
 
-/// 存 app 設定並即時套用 runtime 旗標（頻寬限制、監控開關）。
-/// BT port/限速仍是重啟生效（session 建立時讀取）。
+/// 存 app 設定並即時套用 runtime 旗標（頻寬限制、每主機連線限制、header 設定檔、代理、監控開關、同時下載數、重試策略）。
+/// 頻寬依目前時段套用，BT 限速同時套用到執行中的 session；BT 代理變更時重建 session；
+/// BT port 仍是重啟生效（session 建立時讀取）。
 #[tauri::command]
 pub fn save_app_settings(
+    app_handle: AppHandle,
     state: State<'_, AppState>,
     settings_state: State<'_, SettingsState>,
     settings: AppSettings,
-) -> Result<(), String> {
+) -> Result<(), DownloadError> {
+    for profile in &settings.header_profiles {
+        profile.set.validate()?;
+    }
+    settings.proxy.validate()?;
     settings_state
         .save(settings.clone())
         .map_err(|e| format!("儲存設定失敗: {:?}", e))?;
-    state
-        .bandwidth_limit_bps
-        .store(settings.bandwidth_limit_kbps * 1024, Ordering::Relaxed);
+    schedule::apply_limits(&app_handle, &settings);
+    // 改了時段規則時 UI 立即換上新時段；下一輪排程也不會把這當成進入時段而執行動作
+    let _ = schedule::refresh(&app_handle, &settings);
+    state.hosts.configure(settings.host_limits.clone());
+    state.proxy.configure(settings.proxy.clone());
+    torrent::state::restart(app_handle.clone());
     state
         .monitor_paused
         .store(!settings.monitor_clipboard, Ordering::Relaxed);
+    if let Some(http) = app_handle.try_state::<Arc<HttpManager>>() {
+        http.set_retry_policy(settings.retry.clone());
+        http.set_default_segments(settings.download_segments);
+        http.set_header_profiles(settings.header_profiles.clone());
+    }
+    // 同時下載數調高時立即補滿空槽
+    queue::pump(&app_handle);
     Ok(())
 }
 
+/// 目前生效的時段設定檔（排程尚未跑第一輪時為 None）
 #[tauri::command]
-pub fn reorder_tasks(app_handle: AppHandle, urls: Vec<String>) -> Result<(), String> {
-    db::reorder_tasks(&app_handle, &urls).map_err(|e| format!("排序失敗: {:?}", e))
+pub fn get_schedule_profile(schedule: State<'_, ScheduleState>) -> Option<ActiveProfile> {
+    schedule.get()
+}
+
+#[tauri::command]
+pub fn reorder_tasks(app_handle: AppHandle, urls: Vec<String>) -> Result<(), DownloadError> {
+    db::reorder_tasks(&app_handle, &urls)
+        .map_err(|e| DownloadError::Other(format!("排序失敗: {:?}", e)))
 }
 
 /// 手動新增任務（拖曳連結觸發）：複用剪貼簿同一條 pipeline
-/// （辨識站台 → 驗證 → 抓元資料 → 寫 DB），回傳 payload 讓前端直接 addTask。
+/// （辨識站台 → 驗證 → 抓元資料 → 寫 DB），回傳 `{ payload }` 讓前端直接 addTask；
+/// 已下載過且檔案還在則不加入，回傳 `{ already_downloaded: entry }`（與直鏈、BT 相同）。
 /// 不 emit 事件，避免與剪貼簿監控的 listener double-add；前端 addTask 與 DB UNIQUE 各自去重。
 #[tauri::command]
 pub async fn add_url_manually(
     app_handle: AppHandle,
     url: String,
-) -> Result<ClipboardPayload, String> {
+) -> Result<serde_json::Value, DownloadError> {
     let url = url.trim().to_string();
-    let site = Site::from_url(&url)?;
-    let normalized = site.validate(&url)?;
+    let (site, normalized) = providers::normalize_url(&app_handle, &url)?;
+    if let Some(entry) = library::find_site(&app_handle, &normalized) {
+        return Ok(json!({ "already_downloaded": entry }));
+    }
     let payload = site.fetch_details(&app_handle, &normalized).await?;
-    // 重複 url 回 Ok(false)，仍回傳 payload（前端去重）；手動加不做檔案已存在檢查（使用者明示意圖）
+    // 重複 url 回 Ok(false)，仍回傳 payload（前端去重）
     db::insert_task(&app_handle, &payload).map_err(|e| format!("寫入資料庫失敗: {:?}", e))?;
-    Ok(payload)
+    Ok(json!({ "payload": payload }))
+}
+
+/// 目前生效的爬取規則（內建 + scrape_rules.json 覆寫）與規則檔路徑
+#[tauri::command]
+pub fn get_scrape_rules(rules: State<'_, RulesState>) -> serde_json::Value {
+    json!({ "path": rules.path(), "rules": rules.all() })
+}
+
+/// 匯入瀏覽器匯出的 Netscape 格式 cookies.txt 到共用 cookie jar，回傳匯入幾筆。
+/// 網站與直鏈下載之後的請求依網域自動帶上。
+#[tauri::command]
+pub fn import_cookies(state: State<'_, AppState>, path: String) -> Result<usize, DownloadError> {
+    let text = std::fs::read_to_string(&path)
+        .map_err(|e| DownloadError::Other(format!("讀取 cookies.txt 失敗: {}", e)))?;
+    state.cookies.import_netscape(&text)
+}
+
+/// 依網域列出已存的 cookie（只有名稱，值不送前端）
+#[tauri::command]
+pub fn list_cookies(state: State<'_, AppState>) -> Vec<CookieDomain> {
+    state.cookies.domains()
+}
+
+/// 清除某個網域的 cookie，domain 為空 = 全部清除；回傳清掉幾筆
+#[tauri::command]
+pub fn clear_cookies(state: State<'_, AppState>, domain: Option<String>) -> usize {
+    state
+        .cookies
+        .clear(domain.as_deref().filter(|d| !d.trim().is_empty()))
+}
+
+/// 重新讀取規則檔並對 url 試跑辨識 → 驗證 → 抓元資料（含檔案連結），不寫 DB。
+/// 規則檔有誤時回傳錯誤、沿用先前規則。
+#[tauri::command]
+pub async fn test_scrape_rules(
+    app_handle: AppHandle,
+    rules: State<'_, RulesState>,
+    url: String,
+) -> Result<serde_json::Value, DownloadError> {
+    rules.reload()?;
+    let (site, normalized) = providers::normalize_url(&app_handle, url.trim())?;
+    let payload = site.fetch_details(&app_handle, &normalized).await?;
+    Ok(json!({ "site": site.name(), "normalized_url": normalized, "payload": payload }))
 }
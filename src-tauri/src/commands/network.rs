@@ -1,35 +1,33 @@
-use crate::{error::DownloadError, providers::Site, state::AppState, utils};
-use std::sync::atomic::Ordering;
-use tauri::{AppHandle, Manager, State};
+use crate::error::DownloadError;
+use crate::queue;
+use tauri::AppHandle;
 
+/// 把任務（tasks.url）排進網站下載佇列，後端依設定的同時下載數自行開跑
 #[tauri::command]
-pub async fn download_with_progress(
-    url: String,
-    title: String,
-    file_url: String,
-    state: State<'_, AppState>,
-    app_handle: AppHandle,
-) -> Result<String, DownloadError> {
-    state.download_cancelled.store(false, Ordering::Relaxed);
+pub fn enqueue_site_tasks(app_handle: AppHandle, urls: Vec<String>) -> Result<(), DownloadError> {
+    Ok(queue::enqueue(&app_handle, &urls)?)
+}
 
-    let download_dir = app_handle
-        .path()
-        .download_dir()
-        .map_err(|e| DownloadError::Other(e.to_string()))?;
-    std::fs::create_dir_all(&download_dir)?;
-    let save_path = utils::fs::get_unique_save_path(download_dir, &title);
+/// 暫停佇列：下載中的取消、排隊中的改回暫停
+#[tauri::command]
+pub fn pause_site_queue(app_handle: AppHandle) -> Result<(), DownloadError> {
+    Ok(queue::pause(&app_handle)?)
+}
 
-    let site = Site::from_url(&url).map_err(DownloadError::Other)?;
-    site.download(
-        &state.client,
-        &app_handle,
-        url,
-        file_url,
-        save_path.clone(),
-        state.download_cancelled.clone(),
-        state.bandwidth_limit_bps.clone(),
-    )
-    .await?;
+/// 暫停單一任務（下載中的停下、排隊中的移出佇列），不影響其他任務
+#[tauri::command]
+pub fn pause_download(app_handle: AppHandle, url: String) -> Result<(), DownloadError> {
+    Ok(queue::pause_task(&app_handle, &url)?)
+}
 
-    Ok(save_path.to_string_lossy().to_string())
+/// 取消單一任務，回到待下載狀態
+#[tauri::command]
+pub fn cancel_download(app_handle: AppHandle, url: String) -> Result<(), DownloadError> {
+    Ok(queue::cancel_task(&app_handle, &url)?)
+}
+
+/// 目前佇列狀態（webview 重載後同步用），格式同 "site-queue-state" 事件
+#[tauri::command]
+pub fn get_site_queue_state(app_handle: AppHandle) -> serde_json::Value {
+    queue::snapshot(&app_handle)
 }
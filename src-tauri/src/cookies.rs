@@ -0,0 +1,334 @@
+// src/cookies.rs
+// 登入 session 用的 cookie jar：匯入瀏覽器匯出的 Netscape 格式 cookies.txt，網站下載（AppState.client）
+// 與直鏈（HttpManager.client）共用同一個 jar，依網域、路徑比對後自動帶上；伺服器回的 Set-Cookie
+// 也記下來（session 續期）。存 app_data_dir/cookies.json，值只存檔不進 log，列表只給名稱。
+// 直鏈任務或 header 設定檔自己帶了 Cookie 時，該請求以那個為準，jar 不再附加。
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use reqwest::cookie::CookieStore;
+use reqwest::header::HeaderValue;
+use serde::{Deserialize, Serialize};
+
+use crate::error::DownloadError;
+
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredCookie {
+    /// 不含開頭的點，小寫
+    pub domain: String,
+    /// 子網域也帶（cookies.txt 第二欄 TRUE、Set-Cookie 有 Domain 屬性）
+    pub include_subdomains: bool,
+    pub path: String,
+    /// 只在 https 帶
+    pub secure: bool,
+    /// 到期的 Unix 秒數，0 = session cookie（匯入後一直保留到使用者清除）
+    pub expires: u64,
+    pub name: String,
+    pub value: String,
+}
+
+impl StoredCookie {
+    fn same_slot(&self, other: &StoredCookie) -> bool {
+        self.domain == other.domain && self.path == other.path && self.name == other.name
+    }
+
+    fn expired(&self, now: u64) -> bool {
+        self.expires != 0 && self.expires <= now
+    }
+
+    fn matches(&self, url: &url::Url, now: u64) -> bool {
+        let Some(host) = url.host_str().map(|h| h.to_ascii_lowercase()) else {
+            return false;
+        };
+        let domain_ok = host == self.domain
+            || (self.include_subdomains && host.ends_with(&format!(".{}", self.domain)));
+        let path = url.path();
+        let path_ok = path == self.path
+            || (path.starts_with(&self.path)
+                && (self.path.ends_with('/') || path[self.path.len()..].starts_with('/')));
+        domain_ok && path_ok && (!self.secure || url.scheme() == "https") && !self.expired(now)
+    }
+}
+
+/// 列表用：某個網域存了哪些 cookie（只有名稱）
+#[derive(Debug, Clone, Serialize)]
+pub struct CookieDomain {
+    pub domain: String,
+    pub names: Vec<String>,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Netscape cookies.txt：tab 分隔 7 欄（domain、子網域、path、secure、到期、name、value）。
+/// `#HttpOnly_` 開頭的是 HttpOnly cookie 不是註解；欄位不足的行略過。
+pub fn parse_netscape(text: &str) -> Vec<StoredCookie> {
+    text.lines()
+        .filter_map(|line| {
+            let line = line.trim_end_matches('\r');
+            let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+            if line.starts_with('#') || line.trim().is_empty() {
+                return None;
+            }
+            let fields: Vec<&str> = line.split('\t').collect();
+            let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+                return None;
+            };
+            let domain = domain.trim().trim_start_matches('.').to_ascii_lowercase();
+            if domain.is_empty() || name.is_empty() {
+                return None;
+            }
+            Some(StoredCookie {
+                domain,
+                include_subdomains: subdomains.eq_ignore_ascii_case("TRUE"),
+                path: if path.is_empty() { "/" } else { path }.to_string(),
+                secure: secure.eq_ignore_ascii_case("TRUE"),
+                // 有些匯出工具寫小數或負數，解析不了當 session cookie
+                expires: expires
+                    .trim()
+                    .split('.')
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(0),
+                name: name.to_string(),
+                value: value.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// 解析一個 Set-Cookie。Domain 不屬於請求主機的拒收；Max-Age 優先於 Expires
+fn parse_set_cookie(header: &str, url: &url::Url, now: u64) -> Option<StoredCookie> {
+    let host = url.host_str()?.to_ascii_lowercase();
+    let mut parts = header.split(';');
+    let (name, value) = parts.next()?.split_once('=')?;
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    let mut cookie = StoredCookie {
+        domain: host.clone(),
+        include_subdomains: false,
+        // 預設路徑:請求路徑到最後一個 / 為止
+        path: match url.path().rfind('/') {
+            Some(0) | None => "/".to_string(),
+            Some(i) => url.path()[..i].to_string(),
+        },
+        secure: false,
+        expires: 0,
+        name: name.to_string(),
+        value: value.trim().trim_matches('"').to_string(),
+    };
+    let mut max_age = None;
+    for attr in parts {
+        let (key, val) = attr.split_once('=').unwrap_or((attr, ""));
+        let val = val.trim();
+        match key.trim().to_ascii_lowercase().as_str() {
+            "domain" if !val.is_empty() => {
+                let domain = val.trim_start_matches('.').to_ascii_lowercase();
+                if host != domain && !host.ends_with(&format!(".{domain}")) {
+                    return None;
+                }
+                cookie.domain = domain;
+                cookie.include_subdomains = true;
+            }
+            "path" if val.starts_with('/') => cookie.path = val.to_string(),
+            "secure" => cookie.secure = true,
+            "max-age" => max_age = val.parse::<i64>().ok(),
+            "expires" if max_age.is_none() => {
+                if let Ok(t) = httpdate::parse_http_date(val) {
+                    cookie.expires = t
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_secs().max(1))
+                        .unwrap_or(1);
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(secs) = max_age {
+        // 0 或負數 = 立即刪除
+        cookie.expires = if secs <= 0 { 1 } else { now + secs as u64 };
+    }
+    Some(cookie)
+}
+
+/// 網站與直鏈的 reqwest client 共用（`cookie_provider`）
+pub struct CookieJar {
+    path: PathBuf,
+    cookies: Mutex<Vec<StoredCookie>>,
+}
+
+impl CookieJar {
+    pub fn load(path: PathBuf) -> Self {
+        let now = now_secs();
+        let mut cookies: Vec<StoredCookie> = std::fs::read(&path)
+            .ok()
+            .and_then(|b| serde_json::from_slice(&b).ok())
+            .unwrap_or_default();
+        cookies.retain(|c| !c.expired(now));
+        CookieJar {
+            path,
+            cookies: Mutex::new(cookies),
+        }
+    }
+
+    fn persist(&self, cookies: &[StoredCookie]) {
+        if let Ok(json) = serde_json::to_string_pretty(cookies) {
+            let _ = std::fs::write(&self.path, json);
+        }
+    }
+
+    /// 新的覆蓋同網域、同路徑、同名的舊 cookie；已過期的等於刪除
+    fn upsert(&self, incoming: Vec<StoredCookie>, now: u64) {
+        let mut cookies = self.cookies.lock().unwrap();
+        for cookie in incoming {
+            cookies.retain(|c| !c.same_slot(&cookie));
+            if !cookie.expired(now) {
+                cookies.push(cookie);
+            }
+        }
+        self.persist(&cookies);
+    }
+
+    /// 匯入 cookies.txt 內容，回傳匯入幾筆（已過期的不算）
+    pub fn import_netscape(&self, text: &str) -> Result<usize, DownloadError> {
+        let parsed = parse_netscape(text);
+        if parsed.is_empty() {
+            return Err(DownloadError::Other(
+                "找不到 cookie：請用瀏覽器擴充功能匯出 Netscape 格式的 cookies.txt".to_string(),
+            ));
+        }
+        let now = now_secs();
+        let count = parsed.iter().filter(|c| !c.expired(now)).count();
+        self.upsert(parsed, now);
+        Ok(count)
+    }
+
+    /// 依網域列出已存的 cookie 名稱
+    pub fn domains(&self) -> Vec<CookieDomain> {
+        let now = now_secs();
+        let mut by_domain: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for c in self
+            .cookies
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| !c.expired(now))
+        {
+            by_domain
+                .entry(c.domain.clone())
+                .or_default()
+                .push(c.name.clone());
+        }
+        by_domain
+            .into_iter()
+            .map(|(domain, names)| CookieDomain { domain, names })
+            .collect()
+    }
+
+    /// 清除某個網域的 cookie（None = 全部），回傳清掉幾筆
+    pub fn clear(&self, domain: Option<&str>) -> usize {
+        let mut cookies = self.cookies.lock().unwrap();
+        let before = cookies.len();
+        match domain {
+            Some(d) => {
+                let d = d.trim().trim_start_matches('.').to_ascii_lowercase();
+                cookies.retain(|c| c.domain != d);
+            }
+            None => cookies.clear(),
+        }
+        self.persist(&cookies);
+        before - cookies.len()
+    }
+}
+
+impl CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &url::Url) {
+        let now = now_secs();
+        let incoming: Vec<StoredCookie> = cookie_headers
+            .filter_map(|v| v.to_str().ok())
+            .filter_map(|h| parse_set_cookie(h, url, now))
+            .collect();
+        if !incoming.is_empty() {
+            self.upsert(incoming, now);
+        }
+    }
+
+    fn cookies(&self, url: &url::Url) -> Option<HeaderValue> {
+        let now = now_secs();
+        let cookies = self.cookies.lock().unwrap();
+        let mut matched: Vec<&StoredCookie> =
+            cookies.iter().filter(|c| c.matches(url, now)).collect();
+        if matched.is_empty() {
+            return None;
+        }
+        // 路徑較長的先送（RFC 6265）
+        matched.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        let header = matched
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOKIES_TXT: &str = "# Netscape HTTP Cookie File\n\
+        .example.com\tTRUE\t/\tFALSE\t0\tsid\tabc\n\
+        #HttpOnly_files.host.net\tFALSE\t/dl\tTRUE\t4102444800\ttoken\txyz\n\
+        old.net\tFALSE\t/\tFALSE\t1\tgone\t1\n\
+        broken line\n";
+
+    fn url(s: &str) -> url::Url {
+        url::Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn parses_netscape_and_matches_by_domain_path_and_scheme() {
+        let cookies = parse_netscape(COOKIES_TXT);
+        assert_eq!(cookies.len(), 3);
+        let now = now_secs();
+        let (sid, token) = (&cookies[0], &cookies[1]);
+        assert!(sid.matches(&url("http://cdn.example.com/a"), now));
+        assert!(sid.matches(&url("http://example.com/"), now));
+        assert!(!sid.matches(&url("http://badexample.com/"), now));
+        assert!(token.matches(&url("https://files.host.net/dl/a.zip"), now));
+        assert!(!token.matches(&url("http://files.host.net/dl/a.zip"), now));
+        assert!(!token.matches(&url("https://files.host.net/dlx"), now));
+        assert!(!token.matches(&url("https://cdn.files.host.net/dl/a"), now));
+        assert!(cookies[2].expired(now));
+    }
+
+    #[test]
+    fn set_cookie_respects_domain_and_max_age() {
+        let page = url("https://www.example.com/gallery/1");
+        let c = parse_set_cookie(
+            "sid=new; Domain=.example.com; Path=/; Max-Age=60",
+            &page,
+            100,
+        )
+        .unwrap();
+        assert!(c.include_subdomains && c.domain == "example.com" && c.expires == 160);
+        assert!(parse_set_cookie("sid=x; Domain=other.com", &page, 100).is_none());
+        let host_only = parse_set_cookie("a=1", &page, 100).unwrap();
+        assert_eq!(
+            (host_only.domain.as_str(), host_only.path.as_str()),
+            ("www.example.com", "/gallery")
+        );
+        assert!(parse_set_cookie("a=1; Max-Age=0", &page, 100)
+            .unwrap()
+            .expired(100));
+    }
+}
@@ -1,6 +1,11 @@
-use crate::{providers::ClipboardPayload, state::AppState};
+use crate::{
+    library::LibraryEntry,
+    providers::ClipboardPayload,
+    retry::{self, Attempt},
+    state::AppState,
+};
 
-use rusqlite::{params, Connection, Result};
+use rusqlite::{params, Connection, OptionalExtension, Result};
 use tauri::{AppHandle, Manager};
 
 /// 建立並初始化資料庫
@@ -21,17 +26,122 @@ pub fn init_db(app_handle: &AppHandle) -> Result<Connection, Box<dyn std::error:
             file_size INTEGER DEFAULT -1,
             created_at INTEGER DEFAULT 0,
             db_status TEXT NOT NULL DEFAULT 'idle',
-            sort_order INTEGER DEFAULT 0
+            sort_order INTEGER DEFAULT 0,
+            save_path TEXT NOT NULL DEFAULT '',
+            downloaded_bytes INTEGER NOT NULL DEFAULT 0,
+            segments TEXT NOT NULL DEFAULT '',
+            page_count INTEGER NOT NULL DEFAULT 0,
+            tags TEXT NOT NULL DEFAULT '',
+            rebuilt INTEGER NOT NULL DEFAULT 0,
+            attempts TEXT NOT NULL DEFAULT ''
         )",
         [],
     )?;
 
-    conn.execute("ALTER TABLE tasks ADD COLUMN created_at INTEGER DEFAULT 0", []).ok();
-    conn.execute("ALTER TABLE tasks ADD COLUMN db_status TEXT NOT NULL DEFAULT 'idle'", []).ok();
-    conn.execute("ALTER TABLE tasks ADD COLUMN sort_order INTEGER DEFAULT 0", []).ok();
-    conn.execute("ALTER TABLE tasks ADD COLUMN file_url TEXT NOT NULL DEFAULT ''", []).ok();
-    conn.execute("ALTER TABLE tasks ADD COLUMN file_size INTEGER DEFAULT -1", []).ok();
-    conn.execute("UPDATE tasks SET sort_order = id WHERE sort_order = 0", []).ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN created_at INTEGER DEFAULT 0",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN db_status TEXT NOT NULL DEFAULT 'idle'",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN sort_order INTEGER DEFAULT 0",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN file_url TEXT NOT NULL DEFAULT ''",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN file_size INTEGER DEFAULT -1",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN save_path TEXT NOT NULL DEFAULT ''",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN downloaded_bytes INTEGER NOT NULL DEFAULT 0",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN segments TEXT NOT NULL DEFAULT ''",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN page_count INTEGER NOT NULL DEFAULT 0",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN tags TEXT NOT NULL DEFAULT ''",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN rebuilt INTEGER NOT NULL DEFAULT 0",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "ALTER TABLE tasks ADD COLUMN attempts TEXT NOT NULL DEFAULT ''",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS library (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            kind TEXT NOT NULL,
+            source_url TEXT NOT NULL DEFAULT '',
+            site TEXT NOT NULL DEFAULT '',
+            gallery_id TEXT NOT NULL DEFAULT '',
+            title TEXT NOT NULL DEFAULT '',
+            file_path TEXT NOT NULL,
+            size INTEGER NOT NULL DEFAULT 0,
+            sha256 TEXT NOT NULL DEFAULT '',
+            completed_at INTEGER NOT NULL DEFAULT 0
+        )",
+        [],
+    )?;
+    conn.execute(
+        "ALTER TABLE library ADD COLUMN etag TEXT NOT NULL DEFAULT ''",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS library_source ON library (source_url)",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS library_gallery ON library (site, gallery_id)",
+        [],
+    )
+    .ok();
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS library_hash ON library (sha256)",
+        [],
+    )
+    .ok();
+
+    conn.execute("UPDATE tasks SET sort_order = id WHERE sort_order = 0", [])
+        .ok();
+    // 上次關閉時還在下載的任務放回佇列，由 SiteQueue 接手重跑
+    conn.execute(
+        "UPDATE tasks SET db_status = 'queued' WHERE db_status = 'downloading'",
+        [],
+    )
+    .ok();
 
     Ok(conn)
 }
@@ -42,8 +152,8 @@ pub fn insert_task(app_handle: &AppHandle, payload: &ClipboardPayload) -> Result
     let conn = state.db.lock().unwrap();
 
     let affected = conn.execute(
-        "INSERT OR IGNORE INTO tasks (url, title, image, download_page_href, file_url, file_size, created_at, db_status, sort_order)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM tasks))",
+        "INSERT OR IGNORE INTO tasks (url, title, image, download_page_href, file_url, file_size, created_at, db_status, page_count, tags, rebuilt, sort_order)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM tasks))",
         params![
             payload.url,
             payload.title,
@@ -53,33 +163,56 @@ pub fn insert_task(app_handle: &AppHandle, payload: &ClipboardPayload) -> Result
             payload.file_size,
             payload.created_at,
             payload.db_status,
+            payload.page_count,
+            serde_json::to_string(&payload.tags).unwrap_or_default(),
+            payload.rebuilt,
         ],
     )?;
 
     Ok(affected > 0)
 }
 
+const TASK_COLUMNS: &str =
+    "url, title, image, download_page_href, file_url, file_size, created_at, db_status, save_path, downloaded_bytes, segments, page_count, tags, rebuilt, attempts";
+
+fn row_to_payload(row: &rusqlite::Row<'_>) -> Result<ClipboardPayload> {
+    Ok(ClipboardPayload {
+        url: row.get(0)?,
+        title: row.get(1)?,
+        image: row.get(2)?,
+        download_page_href: row.get(3)?,
+        file_url: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
+        file_size: row.get::<_, Option<i64>>(5)?.unwrap_or(-1),
+        created_at: row.get::<_, Option<i64>>(6)?.unwrap_or(0),
+        db_status: row
+            .get::<_, Option<String>>(7)?
+            .unwrap_or_else(|| "idle".to_string()),
+        save_path: row.get::<_, Option<String>>(8)?.unwrap_or_default(),
+        downloaded_bytes: row.get::<_, Option<i64>>(9)?.unwrap_or(0),
+        segments: row.get::<_, Option<String>>(10)?.unwrap_or_default(),
+        page_count: row.get::<_, Option<i64>>(11)?.unwrap_or(0),
+        tags: row
+            .get::<_, Option<String>>(12)?
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+        rebuilt: row.get::<_, Option<bool>>(13)?.unwrap_or(false),
+        attempts: row
+            .get::<_, Option<String>>(14)?
+            .and_then(|s| serde_json::from_str(&s).ok())
+            .unwrap_or_default(),
+    })
+}
+
 /// 取得所有任務資料
 pub fn get_all_tasks(app_handle: &AppHandle) -> Result<Vec<ClipboardPayload>> {
     let state = app_handle.state::<AppState>();
     let conn = state.db.lock().unwrap();
 
-    let mut stmt = conn.prepare(
-        "SELECT url, title, image, download_page_href, file_url, file_size, created_at, db_status FROM tasks ORDER BY sort_order ASC",
-    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {TASK_COLUMNS} FROM tasks ORDER BY sort_order ASC"
+    ))?;
 
-    let task_iter = stmt.query_map([], |row| {
-        Ok(ClipboardPayload {
-            url: row.get(0)?,
-            title: row.get(1)?,
-            image: row.get(2)?,
-            download_page_href: row.get(3)?,
-            file_url: row.get::<_, Option<String>>(4)?.unwrap_or_default(),
-            file_size: row.get::<_, Option<i64>>(5)?.unwrap_or(-1),
-            created_at: row.get::<_, Option<i64>>(6)?.unwrap_or(0),
-            db_status: row.get::<_, Option<String>>(7)?.unwrap_or_else(|| "idle".to_string()),
-        })
-    })?;
+    let task_iter = stmt.query_map([], row_to_payload)?;
 
     let mut tasks = Vec::new();
     for task in task_iter {
@@ -89,6 +222,163 @@ pub fn get_all_tasks(app_handle: &AppHandle) -> Result<Vec<ClipboardPayload>> {
     Ok(tasks)
 }
 
+/// 將任務排入下載佇列（下載中的不動），回傳實際排入的 URL
+pub fn enqueue_tasks(app_handle: &AppHandle, urls: &[String]) -> Result<Vec<String>> {
+    let state = app_handle.state::<AppState>();
+    let mut conn = state.db.lock().unwrap();
+    let tx = conn.transaction()?;
+    let mut queued = Vec::new();
+    for url in urls {
+        let affected = tx.execute(
+            "UPDATE tasks SET db_status = 'queued' WHERE url = ?1 AND db_status != 'downloading'",
+            params![url],
+        )?;
+        if affected > 0 {
+            queued.push(url.clone());
+        }
+    }
+    tx.commit()?;
+    Ok(queued)
+}
+
+/// 佇列中尚未開跑的任務全部改回 paused，回傳受影響的 URL
+pub fn unqueue_all(app_handle: &AppHandle) -> Result<Vec<String>> {
+    let state = app_handle.state::<AppState>();
+    let mut conn = state.db.lock().unwrap();
+    let tx = conn.transaction()?;
+    let urls = {
+        let mut stmt = tx.prepare("SELECT url FROM tasks WHERE db_status = 'queued'")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        rows.collect::<Result<Vec<_>>>()?
+    };
+    tx.execute(
+        "UPDATE tasks SET db_status = 'paused' WHERE db_status = 'queued'",
+        [],
+    )?;
+    tx.commit()?;
+    Ok(urls)
+}
+
+/// 暫停中的任務 URL（依佇列排序）
+pub fn paused_urls(app_handle: &AppHandle) -> Result<Vec<String>> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    let mut stmt =
+        conn.prepare("SELECT url FROM tasks WHERE db_status = 'paused' ORDER BY sort_order ASC")?;
+    let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+    rows.collect()
+}
+
+/// 還在排隊（未開跑）的單一任務改為指定狀態，回傳是否有改到
+pub fn dequeue_task(app_handle: &AppHandle, url: &str, status: &str) -> Result<bool> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    let affected = conn.execute(
+        "UPDATE tasks SET db_status = ?1 WHERE url = ?2 AND db_status = 'queued'",
+        params![status, url],
+    )?;
+    Ok(affected > 0)
+}
+
+/// 取出排序最前的 queued 任務並標為 downloading（同一把鎖內完成，不會被兩個 worker 搶到）
+pub fn claim_next_queued(app_handle: &AppHandle) -> Result<Option<ClipboardPayload>> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    let task = conn
+        .query_row(
+            &format!(
+                "SELECT {TASK_COLUMNS} FROM tasks WHERE db_status = 'queued' ORDER BY sort_order ASC LIMIT 1"
+            ),
+            [],
+            row_to_payload,
+        )
+        .optional()?;
+    if let Some(task) = &task {
+        conn.execute(
+            "UPDATE tasks SET db_status = 'downloading' WHERE url = ?1",
+            params![task.url],
+        )?;
+    }
+    Ok(task)
+}
+
+/// 佇列中等待開跑的任務數
+pub fn count_queued(app_handle: &AppHandle) -> Result<usize> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    conn.query_row(
+        "SELECT COUNT(*) FROM tasks WHERE db_status = 'queued'",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n as usize)
+}
+
+/// 開跑時記錄目標路徑（.part 在其旁）
+pub fn set_download_target(app_handle: &AppHandle, url: &str, save_path: &str) -> Result<()> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    conn.execute(
+        "UPDATE tasks SET save_path = ?1 WHERE url = ?2",
+        params![save_path, url],
+    )?;
+    Ok(())
+}
+
+/// 落地 .part 的分段配置（JSON）與已寫入總數，app 重開後從這裡續傳
+pub fn update_part_progress(
+    app_handle: &AppHandle,
+    url: &str,
+    downloaded_bytes: u64,
+    segments: &str,
+) -> Result<()> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    conn.execute(
+        "UPDATE tasks SET downloaded_bytes = ?1, segments = ?2 WHERE url = ?3",
+        params![downloaded_bytes as i64, segments, url],
+    )?;
+    Ok(())
+}
+
+/// 下載完成：標 done 並記錄存檔路徑與是否由圖片重建
+pub fn finish_task(
+    app_handle: &AppHandle,
+    url: &str,
+    save_path: &str,
+    rebuilt: bool,
+) -> Result<()> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    conn.execute(
+        "UPDATE tasks SET db_status = 'done', save_path = ?1, downloaded_bytes = 0, segments = '', rebuilt = ?2 WHERE url = ?3",
+        params![save_path, rebuilt, url],
+    )?;
+    Ok(())
+}
+
+/// 追加一筆下載失敗紀錄（只留最近 retry::MAX_HISTORY 筆）
+pub fn push_attempt(app_handle: &AppHandle, url: &str, attempt: Attempt) -> Result<()> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    let current: Option<String> = conn
+        .query_row(
+            "SELECT attempts FROM tasks WHERE url = ?1",
+            params![url],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let mut history: Vec<Attempt> = current
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    retry::push_history(&mut history, attempt);
+    conn.execute(
+        "UPDATE tasks SET attempts = ?1 WHERE url = ?2",
+        params![serde_json::to_string(&history).unwrap_or_default(), url],
+    )?;
+    Ok(())
+}
+
 /// 刪除指定 URL 的任務
 pub fn delete_task_by_url(app_handle: &AppHandle, url: &str) -> Result<()> {
     let state = app_handle.state::<AppState>();
@@ -101,7 +391,10 @@ pub fn delete_task_by_url(app_handle: &AppHandle, url: &str) -> Result<()> {
 pub fn update_task_status(app_handle: &AppHandle, url: &str, status: &str) -> Result<()> {
     let state = app_handle.state::<AppState>();
     let conn = state.db.lock().unwrap();
-    conn.execute("UPDATE tasks SET db_status = ?1 WHERE url = ?2", params![status, url])?;
+    conn.execute(
+        "UPDATE tasks SET db_status = ?1 WHERE url = ?2",
+        params![status, url],
+    )?;
     Ok(())
 }
 
@@ -127,3 +420,113 @@ pub fn clear_all_tasks(app_handle: &AppHandle) -> Result<()> {
     conn.execute("DELETE FROM tasks", [])?;
     Ok(())
 }
+
+const LIBRARY_COLUMNS: &str =
+    "id, kind, source_url, site, gallery_id, title, file_path, size, sha256, completed_at, etag";
+
+fn row_to_library_entry(row: &rusqlite::Row<'_>) -> Result<LibraryEntry> {
+    Ok(LibraryEntry {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        source_url: row.get(2)?,
+        site: row.get(3)?,
+        gallery_id: row.get(4)?,
+        title: row.get(5)?,
+        file_path: row.get(6)?,
+        size: row.get(7)?,
+        sha256: row.get(8)?,
+        completed_at: row.get(9)?,
+        etag: row.get(10)?,
+    })
+}
+
+/// 寫入完成紀錄；同一路徑的舊紀錄（檔案被覆寫）先刪
+pub fn library_insert(app_handle: &AppHandle, entry: &LibraryEntry) -> Result<()> {
+    let state = app_handle.state::<AppState>();
+    let mut conn = state.db.lock().unwrap();
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM library WHERE file_path = ?1",
+        params![entry.file_path],
+    )?;
+    tx.execute(
+        "INSERT INTO library (kind, source_url, site, gallery_id, title, file_path, size, sha256, completed_at, etag)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+        params![
+            entry.kind,
+            entry.source_url,
+            entry.site,
+            entry.gallery_id,
+            entry.title,
+            entry.file_path,
+            entry.size,
+            entry.sha256,
+            entry.completed_at,
+            entry.etag,
+        ],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// 來源網址相同，或同站同作品 ID 的紀錄（新的在前）
+pub fn library_find(
+    app_handle: &AppHandle,
+    source_url: &str,
+    site: &str,
+    gallery_id: &str,
+) -> Result<Vec<LibraryEntry>> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {LIBRARY_COLUMNS} FROM library
+         WHERE (?1 != '' AND source_url = ?1) OR (?3 != '' AND site = ?2 AND gallery_id = ?3)
+         ORDER BY completed_at DESC"
+    ))?;
+    let rows = stmt.query_map(params![source_url, site, gallery_id], row_to_library_entry)?;
+    rows.collect()
+}
+
+/// 內容雜湊與大小相同的紀錄
+pub fn library_find_by_hash(
+    app_handle: &AppHandle,
+    sha256: &str,
+    size: i64,
+) -> Result<Vec<LibraryEntry>> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {LIBRARY_COLUMNS} FROM library WHERE sha256 = ?1 AND size = ?2"
+    ))?;
+    let rows = stmt.query_map(params![sha256, size], row_to_library_entry)?;
+    rows.collect()
+}
+
+/// 大小相同，且 SHA-256 或 ETag 相同的紀錄（新的在前）
+pub fn library_find_content(
+    app_handle: &AppHandle,
+    size: i64,
+    sha256: &str,
+    etag: &str,
+) -> Result<Vec<LibraryEntry>> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {LIBRARY_COLUMNS} FROM library
+         WHERE size = ?1 AND ((?2 != '' AND sha256 = ?2) OR (?3 != '' AND etag = ?3))
+         ORDER BY completed_at DESC"
+    ))?;
+    let rows = stmt.query_map(params![size, sha256, etag], row_to_library_entry)?;
+    rows.collect()
+}
+
+/// 檔案被搬走/改名後重新找到時更新路徑
+pub fn library_update_path(app_handle: &AppHandle, id: i64, file_path: &str) -> Result<()> {
+    let state = app_handle.state::<AppState>();
+    let conn = state.db.lock().unwrap();
+    conn.execute(
+        "UPDATE library SET file_path = ?2 WHERE id = ?1",
+        params![id, file_path],
+    )?;
+    Ok(())
+}
@@ -26,21 +26,30 @@ pub enum DownloadState {
 pub struct DownloadManager {
     // 追蹤下載的起始時間，用於計算速度和 ETR
     start_time: Option<Instant>,
-    // 這裡可以存放更多下載相關的狀態或配置
+    // 續傳時本次啟動前已存在的位元組，不計入速度
+    resumed_bytes: u64,
 }
 
 impl DownloadManager {
     pub fn new() -> Self {
-        DownloadManager { start_time: None }
+        DownloadManager {
+            start_time: None,
+            resumed_bytes: 0,
+        }
     }
 
-    /// 初始化並啟動下載（模擬邏輯）
-    pub fn start_download(&mut self, total_size: u64) {
+    /// 初始化並啟動下載（模擬邏輯），resumed_bytes 為續傳起點
+    pub fn start_download(&mut self, total_size: u64, resumed_bytes: u64) {
         // 真正開始下載時，記錄時間
         self.start_time = Some(Instant::now());
+        self.resumed_bytes = resumed_bytes;
 
         // 實際的下載啟動邏輯 (例如：建立網路連線)
-        tracing::debug!("Core: 下載啟動，總大小: {} Bytes", total_size);
+        tracing::debug!(
+            "Core: 下載啟動，總大小: {} Bytes，續傳自: {} Bytes",
+            total_size,
+            resumed_bytes
+        );
     }
 
     /// 根據當前數據和時間計算最新的 ProgressMetrics
@@ -60,7 +69,7 @@ impl DownloadManager {
         let elapsed_secs = elapsed.as_secs_f64();
 
         let speed = if elapsed_secs > 0.0 {
-            downloaded as f64 / elapsed_secs
+            downloaded.saturating_sub(self.resumed_bytes) as f64 / elapsed_secs
         } else {
             0.0
         };
@@ -1,17 +1,48 @@
 // src/error.rs
 
-use serde::ser::{Serialize, SerializeStruct, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 use std::fmt;
+use std::time::{Duration, SystemTime};
 
-/// 下載流程的結構化錯誤。取代散落各處的魔字串（"NOT_FOUND"、"下載已取消"），
-/// 經 IPC 序列化為 `{ code, message }`，前端比對 code 而非錯誤訊息子字串。
-#[derive(Debug)]
+use reqwest::header::RETRY_AFTER;
+use reqwest::StatusCode;
+
+use crate::retry::RetryClass;
+
+/// 網站、直鏈、BT 與所有 command 共用的結構化錯誤。取代散落各處的魔字串，
+/// 經 IPC 序列化為 `{ code, message, retryable }`，前端依 code 反應而非比對訊息子字串。
+/// 訊息一律不含 URL（直鏈 token 在 query string 裡，不可外洩到 log 或畫面）。
+#[derive(Debug, Clone)]
 pub enum DownloadError {
     /// HTTP 404/410 — 永久性失效，不可重試
     NotFound,
     /// 使用者取消下載
     Cancelled,
-    /// 其他暫時性錯誤（網路、IO、解析），可重試
+    /// HTTP 401 — 連結 token 或登入已過期，需重新取得連結
+    AuthExpired,
+    /// HTTP 403 — 權限不足
+    Forbidden,
+    /// HTTP 429 — 請求過於頻繁；retry_after 為伺服器要求的等待秒數
+    RateLimited { retry_after: Option<u64> },
+    /// HTTP 5xx，可重試
+    Server(String),
+    /// 連線中斷、串流提前結束，可重試
+    Network(String),
+    /// 連線或讀取逾時，可重試
+    Timeout(String),
+    /// 磁碟空間不足，需使用者清出空間
+    DiskFull(String),
+    /// 沒有寫入權限，需使用者換目錄或調整權限
+    PermissionDenied(String),
+    /// 伺服器不支援（或不再支援）Range 續傳，續傳資料不可信
+    RangeNotSupported,
+    /// 下載完成但驗證不過（ZIP 損毀、大小不符），檔案已丟棄，可重試
+    Integrity(String),
+    /// 續傳時遠端檔案已變更（ETag / Last-Modified 不符），舊進度已丟棄，重試會從頭下載
+    RemoteChanged,
+    /// 檔案雜湊與預期（使用者指定、伺服器宣告或 .sha256 檔）不符；檔案保留供檢查，不自動重試
+    ChecksumMismatch(String),
+    /// 其他錯誤（輸入無效、解析、資料庫），不自動重試；暫時性的錯誤應在來源轉成 Network / Timeout
     Other(String),
 }
 
@@ -20,9 +51,89 @@ impl DownloadError {
         match self {
             DownloadError::NotFound => "NOT_FOUND",
             DownloadError::Cancelled => "CANCELLED",
+            DownloadError::AuthExpired => "AUTH_EXPIRED",
+            DownloadError::Forbidden => "FORBIDDEN",
+            DownloadError::RateLimited { .. } => "RATE_LIMITED",
+            DownloadError::Server(_) => "SERVER_ERROR",
+            DownloadError::Network(_) => "NETWORK",
+            DownloadError::Timeout(_) => "TIMEOUT",
+            DownloadError::DiskFull(_) => "DISK_FULL",
+            DownloadError::PermissionDenied(_) => "PERMISSION_DENIED",
+            DownloadError::RangeNotSupported => "RANGE_NOT_SUPPORTED",
+            DownloadError::Integrity(_) => "INTEGRITY",
+            DownloadError::RemoteChanged => "REMOTE_CHANGED",
+            DownloadError::ChecksumMismatch(_) => "CHECKSUM_MISMATCH",
             DownloadError::Other(_) => "OTHER",
         }
     }
+
+    /// 自動重試用的錯誤類別（None = 不重試）
+    pub fn retry_class(&self) -> Option<RetryClass> {
+        match self {
+            DownloadError::NotFound
+            | DownloadError::Cancelled
+            | DownloadError::AuthExpired
+            | DownloadError::Forbidden
+            | DownloadError::DiskFull(_)
+            | DownloadError::PermissionDenied(_)
+            | DownloadError::RangeNotSupported
+            | DownloadError::ChecksumMismatch(_)
+            | DownloadError::Other(_) => None,
+            DownloadError::RateLimited { .. } => Some(RetryClass::RateLimited),
+            DownloadError::Server(_) => Some(RetryClass::Server),
+            DownloadError::Integrity(_) | DownloadError::RemoteChanged => {
+                Some(RetryClass::Integrity)
+            }
+            DownloadError::Network(_) | DownloadError::Timeout(_) => Some(RetryClass::Network),
+        }
+    }
+
+    /// 原樣再試一次就可能成功（不需使用者換連結、清空間、改權限）
+    pub fn retryable(&self) -> bool {
+        self.retry_class().is_some()
+    }
+
+    /// 伺服器要求的最短等待（429 Retry-After）
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            DownloadError::RateLimited { retry_after } => retry_after.map(Duration::from_secs),
+            _ => None,
+        }
+    }
+
+    /// 非 2xx 狀態碼對應的錯誤（5xx 與未分類的狀態碼帶 detail）
+    pub fn from_status(status: StatusCode, retry_after: Option<u64>, detail: &str) -> Self {
+        match status.as_u16() {
+            401 => DownloadError::AuthExpired,
+            403 => DownloadError::Forbidden,
+            404 | 410 => DownloadError::NotFound,
+            416 => DownloadError::RangeNotSupported,
+            429 => DownloadError::RateLimited { retry_after },
+            code if status.is_server_error() => {
+                DownloadError::Server(format!("HTTP {code}{detail}"))
+            }
+            code => DownloadError::Other(format!("HTTP {code}{detail}")),
+        }
+    }
+
+    /// 由失敗的回應建立（讀 Retry-After，不讀 body）
+    pub fn from_response(resp: &reqwest::Response) -> Self {
+        Self::from_status(resp.status(), retry_after_secs(resp.headers()), "")
+    }
+}
+
+/// Retry-After：秒數或 HTTP-date 兩種格式皆可
+pub fn retry_after_secs(headers: &reqwest::header::HeaderMap) -> Option<u64> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(secs);
+    }
+    let at = httpdate::parse_http_date(value).ok()?;
+    Some(
+        at.duration_since(SystemTime::now())
+            .map(|d| d.as_secs_f64().ceil() as u64)
+            .unwrap_or(0),
+    )
 }
 
 impl fmt::Display for DownloadError {
@@ -30,6 +141,29 @@ impl fmt::Display for DownloadError {
         match self {
             DownloadError::NotFound => write!(f, "找不到檔案 (404/410)"),
             DownloadError::Cancelled => write!(f, "下載已取消"),
+            DownloadError::AuthExpired => write!(f, "連結已過期,請重新複製下載連結 (401)"),
+            DownloadError::Forbidden => write!(f, "權限不足,請重新複製下載連結 (403)"),
+            DownloadError::RateLimited {
+                retry_after: Some(secs),
+            } => write!(f, "請求過於頻繁,{} 秒後可再試 (429)", secs),
+            DownloadError::RateLimited { retry_after: None } => {
+                write!(f, "請求過於頻繁,請稍後再試 (429)")
+            }
+            DownloadError::Server(msg) => write!(f, "伺服器錯誤: {}", msg),
+            DownloadError::Network(msg) => write!(f, "網路中斷: {}", msg),
+            DownloadError::Timeout(msg) => write!(f, "連線逾時: {}", msg),
+            DownloadError::DiskFull(msg) => write!(f, "磁碟空間不足: {}", msg),
+            DownloadError::PermissionDenied(msg) => write!(f, "沒有寫入權限: {}", msg),
+            DownloadError::RangeNotSupported => {
+                write!(f, "伺服器不支援續傳,請刪除任務後重新加入")
+            }
+            DownloadError::Integrity(msg) => write!(f, "檔案驗證失敗: {}", msg),
+            DownloadError::RemoteChanged => {
+                write!(f, "遠端檔案已變更,已捨棄先前的下載進度")
+            }
+            DownloadError::ChecksumMismatch(msg) => {
+                write!(f, "檔案雜湊不符,已保留檔案供檢查: {}", msg)
+            }
             DownloadError::Other(msg) => write!(f, "{}", msg),
         }
     }
@@ -39,21 +173,147 @@ impl std::error::Error for DownloadError {}
 
 impl From<reqwest::Error> for DownloadError {
     fn from(e: reqwest::Error) -> Self {
-        DownloadError::Other(e.to_string())
+        // without_url:reqwest 錯誤字串預設帶完整 URL(含 token),必須剝掉。
+        if let Some(status) = e.status() {
+            return DownloadError::from_status(status, None, "");
+        }
+        let timeout = e.is_timeout();
+        let msg = e.without_url().to_string();
+        if timeout {
+            DownloadError::Timeout(msg)
+        } else {
+            DownloadError::Network(msg)
+        }
     }
 }
 
 impl From<std::io::Error> for DownloadError {
     fn from(e: std::io::Error) -> Self {
-        DownloadError::Other(e.to_string())
+        use std::io::ErrorKind;
+        match e.kind() {
+            ErrorKind::StorageFull | ErrorKind::QuotaExceeded => {
+                DownloadError::DiskFull(e.to_string())
+            }
+            ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem => {
+                DownloadError::PermissionDenied(e.to_string())
+            }
+            ErrorKind::TimedOut => DownloadError::Timeout(e.to_string()),
+            // 串流讀到一半斷線（body 讀取常以 io::Error 回報）
+            ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::BrokenPipe
+            | ErrorKind::UnexpectedEof => DownloadError::Network(e.to_string()),
+            _ => DownloadError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<zip::result::ZipError> for DownloadError {
+    fn from(e: zip::result::ZipError) -> Self {
+        match e {
+            zip::result::ZipError::Io(e) => e.into(),
+            e => DownloadError::Other(e.to_string()),
+        }
+    }
+}
+
+/// 既有回傳 `Result<_, String>` 的輔助函式（設定、佇列、BT 引擎）以 `?` 接進 command
+impl From<String> for DownloadError {
+    fn from(msg: String) -> Self {
+        DownloadError::Other(msg)
+    }
+}
+
+impl From<&str> for DownloadError {
+    fn from(msg: &str) -> Self {
+        DownloadError::Other(msg.to_string())
+    }
+}
+
+/// DownloadError 序列化後的形狀；要保存或之後再推給前端時用（如直鏈任務的最後錯誤）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorInfo {
+    pub code: String,
+    pub message: String,
+    pub retryable: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+impl From<&DownloadError> for ErrorInfo {
+    fn from(e: &DownloadError) -> Self {
+        ErrorInfo {
+            code: e.code().to_string(),
+            message: e.to_string(),
+            retryable: e.retryable(),
+            retry_after_secs: e.retry_after().map(|d| d.as_secs()),
+        }
     }
 }
 
 impl Serialize for DownloadError {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
-        let mut st = serializer.serialize_struct("DownloadError", 2)?;
-        st.serialize_field("code", self.code())?;
-        st.serialize_field("message", &self.to_string())?;
-        st.end()
+        ErrorInfo::from(self).serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderMap, HeaderValue};
+
+    #[test]
+    fn status_codes_map_to_codes() {
+        let code =
+            |s: u16| DownloadError::from_status(StatusCode::from_u16(s).unwrap(), None, "").code();
+        assert_eq!(code(401), "AUTH_EXPIRED");
+        assert_eq!(code(403), "FORBIDDEN");
+        assert_eq!(code(410), "NOT_FOUND");
+        assert_eq!(code(416), "RANGE_NOT_SUPPORTED");
+        assert_eq!(code(429), "RATE_LIMITED");
+        assert_eq!(code(503), "SERVER_ERROR");
+        assert_eq!(code(418), "OTHER");
+    }
+
+    #[test]
+    fn io_errors_are_classified() {
+        let disk = DownloadError::from(std::io::Error::from(std::io::ErrorKind::StorageFull));
+        assert_eq!(disk.code(), "DISK_FULL");
+        assert!(!disk.retryable());
+        let denied =
+            DownloadError::from(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert_eq!(denied.code(), "PERMISSION_DENIED");
+        let reset = DownloadError::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset));
+        assert!(reset.retryable());
+        assert!(!DownloadError::Other("無效的下載連結".to_string()).retryable());
+    }
+
+    #[test]
+    fn serializes_code_message_and_retryable() {
+        let e = DownloadError::RateLimited {
+            retry_after: Some(30),
+        };
+        let v = serde_json::to_value(&e).unwrap();
+        assert_eq!(v["code"], "RATE_LIMITED");
+        assert_eq!(v["retryable"], true);
+        assert_eq!(v["retry_after_secs"], 30);
+        assert!(v["message"].as_str().unwrap().contains("30"));
+        let v = serde_json::to_value(DownloadError::Forbidden).unwrap();
+        assert_eq!(v["retryable"], false);
+        assert!(v.get("retry_after_secs").is_none());
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_dates() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+        assert_eq!(retry_after_secs(&headers), Some(120));
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(retry_after_secs(&headers), Some(0));
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(retry_after_secs(&headers), None);
     }
 }
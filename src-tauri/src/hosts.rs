@@ -0,0 +1,247 @@
+// src/hosts.rs
+// 每個主機的連線禮節（AppSettings.host_limits）：同時連線數上限、兩次請求的最短間隔，
+// 以及 429（或帶 Retry-After 的 503）之後暫停該主機。直鏈與網站整包下載的分段共用，
+// 一個主機被暫停時只有連到它的分段在等，其他主機照跑。設定存檔時即時套用。
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::error;
+
+/// 等待時的輪詢間隔，期間可回應取消
+const WAIT_STEP: Duration = Duration::from_millis(250);
+
+/// 單一主機樣式的限制
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostRule {
+    /// 主機名（example.com）或萬用字元（*.example.com，含 example.com 本身）
+    pub pattern: String,
+    /// 同時連線數上限，0 = 不限
+    pub max_connections: usize,
+    /// 兩次請求至少間隔毫秒
+    pub min_interval_ms: u64,
+}
+
+impl HostRule {
+    fn matches(&self, host: &str) -> bool {
+        host_matches(&self.pattern, host)
+    }
+}
+
+/// 主機名（小寫）是否符合樣式：完全相同，或 *.domain 比對 domain 本身與其子網域
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host == domain || host.ends_with(&format!(".{domain}")),
+        None => !pattern.is_empty() && host == pattern,
+    }
+}
+
+/// 規則依序比對，第一個符合的生效；都不符合時用預設值
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HostLimits {
+    /// 預設每個主機的同時連線數上限，0 = 不限
+    pub max_connections: usize,
+    /// 預設兩次請求至少間隔毫秒
+    pub min_interval_ms: u64,
+    /// 429/503 沒帶 Retry-After 時暫停該主機的秒數
+    pub backoff_secs: u64,
+    pub rules: Vec<HostRule>,
+}
+
+impl Default for HostLimits {
+    fn default() -> Self {
+        Self {
+            max_connections: 8,
+            min_interval_ms: 0,
+            backoff_secs: 30,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl HostLimits {
+    /// (同時連線數上限, 請求間隔)
+    fn limits_for(&self, host: &str) -> (usize, Duration) {
+        let (max, interval) = self
+            .rules
+            .iter()
+            .find(|r| r.matches(host))
+            .map_or((self.max_connections, self.min_interval_ms), |r| {
+                (r.max_connections, r.min_interval_ms)
+            });
+        (max, Duration::from_millis(interval))
+    }
+}
+
+#[derive(Default)]
+struct HostSlot {
+    active: usize,
+    next_request: Option<Instant>,
+    paused_until: Option<Instant>,
+}
+
+#[derive(Default)]
+struct Inner {
+    limits: HostLimits,
+    hosts: HashMap<String, HostSlot>,
+}
+
+#[derive(Default)]
+pub struct HostLimiter {
+    inner: Mutex<Inner>,
+}
+
+/// URL 的主機名（小寫），解析不了時為空字串（共用一個槽）
+pub fn host_of(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(|h| h.to_ascii_lowercase()))
+        .unwrap_or_default()
+}
+
+impl HostLimiter {
+    pub fn configure(&self, limits: HostLimits) {
+        self.inner.lock().unwrap().limits = limits;
+    }
+
+    /// 佔一條連線；可以發請求時回 Ok，否則回還要等多久
+    fn try_acquire(&self, host: &str, now: Instant) -> Result<(), Duration> {
+        let mut inner = self.inner.lock().unwrap();
+        let (max, interval) = inner.limits.limits_for(host);
+        let slot = inner.hosts.entry(host.to_string()).or_default();
+        let ready_at = slot.next_request.max(slot.paused_until);
+        if let Some(wait) = ready_at.map(|t| t.saturating_duration_since(now)) {
+            if !wait.is_zero() {
+                return Err(wait);
+            }
+        }
+        if max > 0 && slot.active >= max {
+            return Err(WAIT_STEP);
+        }
+        slot.active += 1;
+        slot.next_request = Some(now + interval);
+        Ok(())
+    }
+
+    fn release(&self, host: &str) {
+        let mut inner = self.inner.lock().unwrap();
+        if let Some(slot) = inner.hosts.get_mut(host) {
+            slot.active = slot.active.saturating_sub(1);
+            // 沒有連線、也沒有待守的間隔或暫停時丟掉，避免表越長越大
+            let now = Instant::now();
+            let pending = slot.next_request.max(slot.paused_until);
+            if slot.active == 0 && pending.is_none_or(|t| t <= now) {
+                inner.hosts.remove(host);
+            }
+        }
+    }
+
+    /// 等到該主機有空位、離上次請求夠久且沒被暫停；stopped 回 true 時提早返回 None。
+    /// 拿到的 permit 存活期間算一條連線
+    pub async fn acquire(
+        self: &Arc<Self>,
+        host: &str,
+        stopped: impl Fn() -> bool,
+    ) -> Option<HostPermit> {
+        loop {
+            match self.try_acquire(host, Instant::now()) {
+                Ok(()) => {
+                    return Some(HostPermit {
+                        limiter: self.clone(),
+                        host: host.to_string(),
+                    })
+                }
+                Err(wait) => {
+                    if stopped() {
+                        return None;
+                    }
+                    tokio::time::sleep(wait.min(WAIT_STEP)).await;
+                }
+            }
+        }
+    }
+
+    /// 回應要求放慢時（429，或帶 Retry-After 的 503）暫停該主機，回傳暫停多久
+    pub fn back_off(&self, host: &str, resp: &reqwest::Response) -> Option<Duration> {
+        let retry_after = error::retry_after_secs(resp.headers());
+        let status = resp.status();
+        if status != StatusCode::TOO_MANY_REQUESTS
+            && !(status == StatusCode::SERVICE_UNAVAILABLE && retry_after.is_some())
+        {
+            return None;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        let wait = Duration::from_secs(retry_after.unwrap_or(inner.limits.backoff_secs));
+        let until = Instant::now() + wait;
+        let slot = inner.hosts.entry(host.to_string()).or_default();
+        slot.paused_until = slot.paused_until.max(Some(until));
+        Some(wait)
+    }
+}
+
+/// 佔用中的主機連線，drop 時歸還
+pub struct HostPermit {
+    limiter: Arc<HostLimiter>,
+    host: String,
+}
+
+impl Drop for HostPermit {
+    fn drop(&mut self) {
+        self.limiter.release(&self.host);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_match_hosts_and_wildcards() {
+        let rule = |p: &str| HostRule {
+            pattern: p.to_string(),
+            ..Default::default()
+        };
+        assert!(rule("*.example.com").matches("cdn.example.com"));
+        assert!(rule("*.example.com").matches("example.com"));
+        assert!(!rule("*.example.com").matches("badexample.com"));
+        assert!(rule("Files.Host.net").matches("files.host.net"));
+        assert!(!rule("").matches(""));
+        assert_eq!(
+            host_of("https://CDN.Example.com:8443/a?t=1"),
+            "cdn.example.com"
+        );
+    }
+
+    #[test]
+    fn connection_cap_and_spacing() {
+        let limiter = HostLimiter::default();
+        limiter.configure(HostLimits {
+            rules: vec![HostRule {
+                pattern: "slow.net".to_string(),
+                max_connections: 1,
+                min_interval_ms: 1000,
+            }],
+            ..Default::default()
+        });
+        let now = Instant::now();
+        assert!(limiter.try_acquire("slow.net", now).is_ok());
+        // 間隔未到
+        let wait = limiter.try_acquire("slow.net", now).unwrap_err();
+        assert_eq!(wait, Duration::from_secs(1));
+        // 間隔到了但連線數已滿
+        let later = now + Duration::from_secs(2);
+        assert_eq!(limiter.try_acquire("slow.net", later), Err(WAIT_STEP));
+        limiter.release("slow.net");
+        assert!(limiter.try_acquire("slow.net", later).is_ok());
+        // 其他主機用預設值，不受影響
+        assert!(limiter.try_acquire("fast.net", now).is_ok());
+        assert!(limiter.try_acquire("fast.net", now).is_ok());
+    }
+}
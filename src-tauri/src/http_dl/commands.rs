@@ -2,9 +2,21 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use serde_json::{json, Value};
-use tauri::State;
+use tauri::{AppHandle, State};
 
-use super::manager::{filename_from_url, HttpManager, HttpStatus};
+use super::headers::HeaderSet;
+use super::manager::{filename_from_url, HttpManager, HttpStatus, TaskOptions};
+use crate::error::DownloadError;
+use crate::library;
+use crate::utils::checksum::Checksum;
+
+/// 只收 http(s) 連結。
+fn parse_http_url(url: &str) -> Result<reqwest::Url, DownloadError> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(DownloadError::Other("無效的下載連結".to_string()));
+    }
+    reqwest::Url::parse(url).map_err(|_| DownloadError::Other("無效的下載連結".to_string()))
+}
 
 /// 極端情況的後備檔名時間戳(URL 取不出檔名時)。
 fn fallback_timestamp() -> u64 {
@@ -16,22 +28,55 @@ fn fallback_timestamp() -> u64 {
 
 /// 新增 HTTP 直鏈下載並立即開跑。檔名先取 URL path 最後一段,首次回應的
 /// Content-Disposition 會再覆蓋。out_dir 空 = 系統下載資料夾。
+/// speed_limit_bps 為該任務自己的速率上限(bytes/sec),空或 0 = 只受全域頻寬限制。
+/// segments 為並行連線數,空或 0 = 跟隨全域設定。
+/// mirrors 為同一檔案的其他下載連結,開跑前確認大小(與 ETag)相符後分段輪流使用。
+/// checksum 為預期雜湊("sha256:…"、"md5:…" 或只給 hex),完成後驗證;
+/// 未指定時改用伺服器宣告(Digest / Content-MD5)或同目錄的 .sha256 檔。
+/// headers 為自訂 header 與 cookie(Referer、登入 cookie…),探測與每個分段請求都帶;
+/// 主機符合的 header 設定檔也會套用,同名時以任務的為準。
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 pub fn add_http_download(
+    app_handle: AppHandle,
     state: State<'_, Arc<HttpManager>>,
     url: String,
     out_dir: Option<String>,
-) -> Result<Value, String> {
+    speed_limit_bps: Option<u64>,
+    segments: Option<usize>,
+    mirrors: Option<Vec<String>>,
+    checksum: Option<String>,
+    headers: Option<HeaderSet>,
+) -> Result<Value, DownloadError> {
     let url = url.trim().to_string();
-    if !url.starts_with("http://") && !url.starts_with("https://") {
-        return Err("無效的下載連結".to_string());
+    let parsed = parse_http_url(&url)?;
+    let mut mirror_urls: Vec<String> = Vec::new();
+    for m in mirrors.unwrap_or_default() {
+        let m = m.trim().to_string();
+        if m.is_empty() || m == url || mirror_urls.contains(&m) {
+            continue;
+        }
+        parse_http_url(&m)?;
+        mirror_urls.push(m);
     }
-    let parsed = reqwest::Url::parse(&url).map_err(|_| "無效的下載連結".to_string())?;
+    let checksum = match checksum.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        Some(s) => {
+            Some(Checksum::parse(s).ok_or("雜湊格式不正確(SHA-256 為 64 碼、MD5 為 32 碼 hex)")?)
+        }
+        None => None,
+    };
+    let headers = headers.unwrap_or_default();
+    headers.validate()?;
 
     // 同一條 URL 還在跑/暫停/失敗中 → 不重複加。
     if let Some(existing) = state.find_active_by_url(&url) {
         return Ok(json!({ "already_exists": true, "id": existing.id }));
     }
+    // 同一條 URL 已下載完成過且檔案還在 → 回報既有檔案。
+    // 換了連結的同一檔案要等探測拿到大小與 ETag / 雜湊,由 HttpManager 再查一次(推 "library-hit")。
+    if let Some(entry) = library::find(&app_handle, &url, "", "") {
+        return Ok(json!({ "already_downloaded": entry }));
+    }
 
     let dest_dir = out_dir
         .filter(|s| !s.trim().is_empty())
@@ -41,21 +86,51 @@ pub fn add_http_download(
     let file_name =
         filename_from_url(&parsed).unwrap_or_else(|| format!("download-{}", fallback_timestamp()));
 
-    let task = state.add(url, dest_dir, file_name);
+    let task = state.add(
+        url,
+        dest_dir,
+        file_name,
+        TaskOptions {
+            speed_limit_bps: speed_limit_bps.unwrap_or(0),
+            segment_count: segments.unwrap_or(0),
+            mirrors: mirror_urls,
+            checksum,
+            headers,
+        },
+    );
     let id = task.id;
     state.spawn_run(task);
     Ok(json!({ "id": id }))
 }
 
 #[tauri::command]
-pub fn pause_http_download(state: State<'_, Arc<HttpManager>>, id: u64) -> Result<(), String> {
+pub fn pause_http_download(
+    state: State<'_, Arc<HttpManager>>,
+    id: u64,
+) -> Result<(), DownloadError> {
     state.pause(id);
     Ok(())
 }
 
+/// 調整單一任務的速率上限(bytes/sec,0 = 不限),執行中即時生效。
+#[tauri::command]
+pub fn set_http_speed_limit(
+    state: State<'_, Arc<HttpManager>>,
+    id: u64,
+    speed_limit_bps: u64,
+) -> Result<(), DownloadError> {
+    if !state.set_speed_limit(id, speed_limit_bps) {
+        return Err("任務不存在".into());
+    }
+    Ok(())
+}
+
 /// 續跑暫停或失敗的任務(連結未過期時 Range 續傳)。
 #[tauri::command]
-pub fn resume_http_download(state: State<'_, Arc<HttpManager>>, id: u64) -> Result<(), String> {
+pub fn resume_http_download(
+    state: State<'_, Arc<HttpManager>>,
+    id: u64,
+) -> Result<(), DownloadError> {
     let task = state.find(id).ok_or("任務不存在")?;
     if task.status() == HttpStatus::Running {
         return Ok(());
@@ -65,17 +140,15 @@ pub fn resume_http_download(state: State<'_, Arc<HttpManager>>, id: u64) -> Resu
 }
 
 /// token 過期後使用者貼新連結(指向同一檔案),換掉 URL 後接著續傳。
+/// 續傳請求帶原本的 If-Range,新連結的檔案若已不同,會捨棄舊進度從頭下載。
 #[tauri::command]
 pub fn update_http_url(
     state: State<'_, Arc<HttpManager>>,
     id: u64,
     url: String,
-) -> Result<(), String> {
+) -> Result<(), DownloadError> {
     let url = url.trim().to_string();
-    if !url.starts_with("http://") && !url.starts_with("https://") {
-        return Err("無效的下載連結".to_string());
-    }
-    reqwest::Url::parse(&url).map_err(|_| "無效的下載連結".to_string())?;
+    parse_http_url(&url)?;
     let task = state.find(id).ok_or("任務不存在")?;
     *task.url.lock().unwrap() = url;
     if task.status() != HttpStatus::Running {
@@ -84,12 +157,41 @@ pub fn update_http_url(
     Ok(())
 }
 
+/// 加一個同一檔案的鏡像連結;執行中的任務下一輪開跑時才會用到。
+#[tauri::command]
+pub fn add_http_mirror(
+    state: State<'_, Arc<HttpManager>>,
+    id: u64,
+    url: String,
+) -> Result<(), DownloadError> {
+    let url = url.trim().to_string();
+    parse_http_url(&url)?;
+    if !state.add_mirror(id, url) {
+        return Err("任務不存在".into());
+    }
+    Ok(())
+}
+
+/// 換掉任務的自訂 header 與 cookie(例如 cookie 過期後貼新的);執行中的任務下一輪開跑時才會用到。
+#[tauri::command]
+pub fn set_http_headers(
+    state: State<'_, Arc<HttpManager>>,
+    id: u64,
+    headers: HeaderSet,
+) -> Result<(), DownloadError> {
+    headers.validate()?;
+    if !state.set_headers(id, headers) {
+        return Err("任務不存在".into());
+    }
+    Ok(())
+}
+
 #[tauri::command]
 pub fn delete_http_download(
     state: State<'_, Arc<HttpManager>>,
     id: u64,
     delete_files: bool,
-) -> Result<(), String> {
+) -> Result<(), DownloadError> {
     state.remove(id, delete_files);
     Ok(())
 }
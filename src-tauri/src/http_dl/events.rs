@@ -7,6 +7,8 @@ use serde_json::json;
 use tauri::{AppHandle, Emitter, Manager};
 
 use super::manager::{HttpManager, HttpStatus};
+use crate::hosts;
+use crate::library::{self, LibraryEntry};
 
 /// 每秒收集直鏈任務狀態推 "http-stats" event;速度 = 兩次 tick 的
 /// downloaded 差值。finished 轉換時推 "http-finished"(首 tick 不發,
@@ -48,6 +50,24 @@ pub fn spawn_http_stats_task(app: AppHandle) {
                 if !first_tick && !prev_fin && finished {
                     let name = t.file_name.lock().unwrap().clone();
                     let _ = app.emit("http-finished", json!({ "id": t.id, "name": name }));
+                    library::spawn_record(
+                        app.clone(),
+                        LibraryEntry {
+                            kind: "http".to_string(),
+                            source_url: t.url.lock().unwrap().clone(),
+                            title: name.clone(),
+                            file_path: t.dest_dir.join(&name).to_string_lossy().into_owned(),
+                            // 弱 ETag 不代表內容相同,不記
+                            etag: t
+                                .etag
+                                .lock()
+                                .unwrap()
+                                .clone()
+                                .filter(|e| !e.starts_with("W/"))
+                                .unwrap_or_default(),
+                            ..Default::default()
+                        },
+                    );
                 }
 
                 let progress = if total > 0 {
@@ -70,8 +90,20 @@ pub fn spawn_http_stats_task(app: AppHandle) {
                     "downloaded_bytes": downloaded,
                     "total_bytes": total,
                     "down_speed_bps": bps,
+                    "speed_limit_bps": t.speed_limit.get(),
+                    "checksum": t.checksum.lock().unwrap().clone(),
+                    // 只送名稱:值可能是 cookie / token
+                    "header_names": t.headers.lock().unwrap().names(),
+                    // 只送主機名:完整連結含 token
+                    "mirror_hosts": t
+                        .mirrors
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .map(|m| hosts::host_of(m))
+                        .collect::<Vec<_>>(),
                     "error": t.error.lock().unwrap().clone(),
-                    "retryable": t.retryable.load(Ordering::Relaxed),
+                    "attempts": t.attempts.lock().unwrap().clone(),
                 }));
             }
             prev_bytes.retain(|k, _| tasks.iter().any(|t| t.id == *k));
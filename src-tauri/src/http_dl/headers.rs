@@ -0,0 +1,159 @@
+// 直鏈任務的自訂請求 header 與 cookie(Referer、session cookie、Bearer token…)。
+// 值多半就是授權本體:存 http_tasks.json / app_settings.json 但一律不進 log,
+// Debug 只印名稱,推給前端的事件也只有名稱。
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, COOKIE};
+use serde::{Deserialize, Serialize};
+
+use crate::error::DownloadError;
+use crate::hosts;
+
+/// 一組自訂 header 與 cookie;header 名稱不分大小寫,後設定的覆蓋先前的
+#[derive(Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeaderSet {
+    pub headers: BTreeMap<String, String>,
+    /// 合併成一個 `Cookie: a=1; b=2`
+    pub cookies: BTreeMap<String, String>,
+}
+
+impl fmt::Debug for HeaderSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HeaderSet")
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .field("cookies", &self.cookies.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl HeaderSet {
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty() && self.cookies.is_empty()
+    }
+
+    /// 有設定的名稱(給前端顯示,不含值)
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.headers.keys().cloned().collect();
+        if !self.cookies.is_empty() {
+            names.push(format!("Cookie({})", self.cookies.len()));
+        }
+        names
+    }
+
+    /// other 疊在上面:同名 header(不分大小寫)與同名 cookie 以 other 為準
+    pub fn merge(&mut self, other: &HeaderSet) {
+        for (name, value) in &other.headers {
+            self.headers.retain(|k, _| !k.eq_ignore_ascii_case(name));
+            self.headers.insert(name.clone(), value.clone());
+        }
+        self.cookies.extend(other.cookies.clone());
+    }
+
+    /// 檢查名稱與值都是合法 header(新增任務、存設定時先擋掉)
+    pub fn validate(&self) -> Result<(), DownloadError> {
+        self.to_header_map().map(|_| ())
+    }
+
+    pub fn to_header_map(&self) -> Result<HeaderMap, DownloadError> {
+        let mut map = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| DownloadError::Other(format!("無效的 header 名稱:{}", name)))?;
+            // 錯誤訊息只帶名稱,值可能是 token
+            let value = HeaderValue::from_str(value.trim())
+                .map_err(|_| DownloadError::Other(format!("header {} 的值含無效字元", name)))?;
+            map.insert(header, value);
+        }
+        if !self.cookies.is_empty() {
+            let cookie = self
+                .cookies
+                .iter()
+                .map(|(k, v)| format!("{}={}", k.trim(), v.trim()))
+                .collect::<Vec<_>>()
+                .join("; ");
+            let value = HeaderValue::from_str(&cookie)
+                .map_err(|_| DownloadError::Other("cookie 含無效字元".to_string()))?;
+            map.insert(COOKIE, value);
+        }
+        Ok(map)
+    }
+}
+
+/// 具名 header 設定檔:套用到主機符合的所有直鏈請求
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HeaderProfile {
+    pub name: String,
+    /// 主機名或 *.example.com(含 example.com 本身)
+    pub pattern: String,
+    #[serde(flatten)]
+    pub set: HeaderSet,
+}
+
+/// 某個 URL 實際要帶的 header:符合主機的設定檔依序疊上,最後疊任務自己的
+pub fn resolve(profiles: &[HeaderProfile], task: &HeaderSet, url: &str) -> HeaderSet {
+    let host = hosts::host_of(url);
+    let mut set = HeaderSet::default();
+    for profile in profiles
+        .iter()
+        .filter(|p| hosts::host_matches(&p.pattern, &host))
+    {
+        set.merge(&profile.set);
+    }
+    set.merge(task);
+    set
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(headers: &[(&str, &str)], cookies: &[(&str, &str)]) -> HeaderSet {
+        HeaderSet {
+            headers: headers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            cookies: cookies
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn task_headers_override_matching_profiles() {
+        let profiles = vec![
+            HeaderProfile {
+                name: "cdn".to_string(),
+                pattern: "*.example.com".to_string(),
+                set: set(&[("Referer", "https://example.com/")], &[("sid", "p")]),
+            },
+            HeaderProfile {
+                name: "other".to_string(),
+                pattern: "other.net".to_string(),
+                set: set(&[("X-Other", "1")], &[]),
+            },
+        ];
+        let task = set(&[("referer", "https://example.com/page")], &[("sid", "t")]);
+        let resolved = resolve(&profiles, &task, "https://dl.example.com/a.zip");
+        let map = resolved.to_header_map().unwrap();
+        assert_eq!(map["referer"], "https://example.com/page");
+        assert_eq!(map["cookie"], "sid=t");
+        assert!(map.get("x-other").is_none());
+    }
+
+    #[test]
+    fn debug_and_errors_hide_values() {
+        let secret = set(&[("Authorization", "Bearer s3cret")], &[("sid", "s3cret")]);
+        assert!(!format!("{:?}", secret).contains("s3cret"));
+        assert_eq!(secret.names(), vec!["Authorization", "Cookie(1)"]);
+
+        let bad = set(&[("Authorization", "s3\ncret")], &[]);
+        let msg = bad.validate().unwrap_err().to_string();
+        assert!(msg.contains("Authorization") && !msg.contains("cret"));
+    }
+}
@@ -1,23 +1,43 @@
+use std::collections::VecDeque;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
 
 use futures_util::StreamExt;
 use percent_encoding::percent_decode_str;
-use reqwest::header::{CONTENT_DISPOSITION, CONTENT_RANGE, RANGE};
+use reqwest::header::{
+    HeaderMap, CONTENT_DISPOSITION, CONTENT_RANGE, ETAG, IF_RANGE, LAST_MODIFIED, RANGE,
+};
 use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
 use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 use tokio::task::JoinSet;
 
+use super::headers::{self, HeaderProfile, HeaderSet};
+use crate::bandwidth::{BandwidthLimiter, Engine, RateLimit, Throttle};
+use crate::cookies::CookieJar;
+use crate::error::{self, DownloadError, ErrorInfo};
+use crate::hosts::{self, HostLimiter};
+use crate::library::{self, LibraryEntry};
+use crate::proxy::ProxyRouter;
+use crate::retry::{self, Attempt, RetryPolicy};
 use crate::torrent::commands::sanitize_folder_name;
+use crate::utils::checksum::{self, Checksum, HashAlgo};
+use crate::utils::verify;
 
-/// 分段並行數(伺服器無連線數限制時 4 段)。
-const SEGMENT_COUNT: u64 = 4;
-/// 小於此大小不分段,單一連線下載即可。
-const MIN_SPLIT_BYTES: u64 = 8 * 1024 * 1024;
+/// 每個任務預設的並行連線數(AppSettings.download_segments)。
+pub const DEFAULT_SEGMENTS: usize = 4;
+/// 並行連線數上限。
+pub const MAX_SEGMENTS: usize = 16;
+/// 初始切段時每段至少這麼大;檔案小於兩段時單一連線下載即可。
+pub(crate) const MIN_SEGMENT_BYTES: u64 = 2 * 1024 * 1024;
+/// 工作竊取時切出的後半段至少這麼大,太小不值得多開一條連線。
+const MIN_STEAL_BYTES: u64 = 1024 * 1024;
+/// 單一分段被主機要求放慢(429)後最多再等幾輪,之後交給任務層的重試策略。
+const MAX_HOST_BACKOFFS: u32 = 3;
 /// Content-Length 未知時 segment.end 的哨兵值。
-const UNBOUNDED: u64 = u64::MAX;
+pub(crate) const UNBOUNDED: u64 = u64::MAX;
 
 #[derive(Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Debug)]
 #[serde(rename_all = "snake_case")]
@@ -29,23 +49,107 @@ pub enum HttpStatus {
 }
 
 /// 半開區間 [start, end)。written 為已寫入 bytes,由 worker 累加。
+/// clone 共用同一顆 written 與 end;工作竊取時 end 會被調小,worker 寫到新的 end 為止。
+#[derive(Clone)]
 pub struct Segment {
     pub start: u64,
-    pub end: u64,
+    pub end: Arc<AtomicU64>,
     pub written: Arc<AtomicU64>,
 }
 
+impl Segment {
+    pub fn new(start: u64, end: u64, written: u64) -> Self {
+        Segment {
+            start,
+            end: Arc::new(AtomicU64::new(end)),
+            written: Arc::new(AtomicU64::new(written)),
+        }
+    }
+
+    pub fn end(&self) -> u64 {
+        self.end.load(Ordering::Relaxed)
+    }
+
+    /// 下一個要寫的偏移
+    fn pos(&self) -> u64 {
+        self.start + self.written.load(Ordering::Relaxed)
+    }
+
+    /// 記下從 pos 寫入的 len bytes,回傳實際算進進度的量。寫檔期間可能被
+    /// 工作竊取把 end 往前切,超過新 end 的部分歸切出去的分段,不算在這段
+    fn commit(&self, pos: u64, len: usize) -> u64 {
+        let counted = match self.end() {
+            UNBOUNDED => len as u64,
+            end => (len as u64).min(end.saturating_sub(pos)),
+        };
+        self.written.fetch_add(counted, Ordering::Relaxed);
+        counted
+    }
+
+    /// 尚未寫入的 bytes(end 未知時為 None)
+    fn remaining(&self) -> Option<u64> {
+        match self.end() {
+            UNBOUNDED => None,
+            end => Some(end.saturating_sub(self.pos())),
+        }
+    }
+}
+
+/// 新增任務時的選填設定
+#[derive(Default)]
+pub struct TaskOptions {
+    /// bytes/sec,0 = 只受全域頻寬限制
+    pub speed_limit_bps: u64,
+    /// 並行連線數,0 = 跟隨全域設定
+    pub segment_count: usize,
+    pub mirrors: Vec<String>,
+    pub checksum: Option<Checksum>,
+    pub headers: HeaderSet,
+}
+
+/// 分段下載的一個來源:連結與這個來源要帶的 header(Referer、Cookie…)
+#[derive(Clone)]
+pub(crate) struct Source {
+    pub url: String,
+    pub headers: HeaderMap,
+}
+
+impl Source {
+    pub fn new(url: String) -> Self {
+        Source {
+            url,
+            headers: HeaderMap::new(),
+        }
+    }
+}
+
 pub struct HttpTask {
     pub id: u64,
     pub url: Mutex<String>,
+    /// 同一檔案的其他來源;分段輪流分派,失敗的鏡像會被移除
+    pub mirrors: Mutex<Vec<String>>,
     pub file_name: Mutex<String>,
     pub dest_dir: PathBuf,
     pub total_bytes: AtomicU64, // 0 = 未知
     pub range_supported: AtomicBool,
+    /// 探測時主連結回的 ETag:確認鏡像是同一個檔案,續傳時帶 If-Range
+    pub etag: Mutex<Option<String>>,
+    /// 探測時主連結回的 Last-Modified;沒有強 ETag 時續傳改帶它
+    pub last_modified: Mutex<Option<String>>,
+    /// 預期的檔案雜湊:使用者指定,否則取伺服器宣告或同目錄的 .sha256 檔
+    pub checksum: Mutex<Option<Checksum>>,
+    /// 自訂 header 與 cookie:探測與每個分段請求都帶,值只存檔不進 log
+    pub headers: Mutex<HeaderSet>,
     pub segments: Mutex<Vec<Segment>>,
     pub status: Mutex<HttpStatus>,
-    pub error: Mutex<Option<String>>,
-    pub retryable: AtomicBool,
+    /// 最後一次失敗(retryable = 原樣續跑即可;否則需貼新連結或處理磁碟/權限)
+    pub error: Mutex<Option<ErrorInfo>>,
+    /// 每次失敗的紀錄（含自動重試）
+    pub attempts: Mutex<Vec<Attempt>>,
+    /// 任務自己的速率上限(所有分段共用),下載中可調
+    pub speed_limit: Arc<RateLimit>,
+    /// 並行連線數,0 = 跟隨全域設定
+    pub segment_count: AtomicUsize,
     /// 本輪執行的停止旗標;暫停/刪除設為 true,resume 換新的一顆。
     stop: Mutex<Arc<AtomicBool>>,
 }
@@ -78,82 +182,119 @@ impl HttpTask {
     fn signal_stop(&self) {
         self.stop.lock().unwrap().store(true, Ordering::Relaxed);
     }
-}
 
-/// 下載失敗原因。message 給 UI 直接顯示;retryable = 網路類問題,重試即可
-/// (token 過期/權限/404 則需要使用者處理)。錯誤訊息一律不含 URL(token 在
-/// query string 裡,不可外洩到 log 或畫面)。
-struct TaskError {
-    message: String,
-    retryable: bool,
-}
-
-impl TaskError {
-    fn net(e: reqwest::Error) -> Self {
-        // without_url:reqwest 錯誤字串預設帶完整 URL(含 token),必須剝掉。
-        TaskError {
-            message: format!("網路中斷,可重試({})", e.without_url()),
-            retryable: true,
-        }
+    fn if_range(&self) -> Option<String> {
+        if_range_validator(
+            self.etag.lock().unwrap().clone(),
+            self.last_modified.lock().unwrap().clone(),
+        )
     }
+}
 
-    fn io(e: std::io::Error) -> Self {
-        TaskError {
-            message: format!("寫檔失敗:{e}"),
-            retryable: true,
-        }
-    }
+/// 續傳請求帶的 If-Range:強 ETag 優先,弱 ETag 不能用在 If-Range,改用 Last-Modified。
+fn if_range_validator(etag: Option<String>, last_modified: Option<String>) -> Option<String> {
+    etag.filter(|e| !e.starts_with("W/")).or(last_modified)
 }
 
-async fn map_status_error(status: StatusCode, resp: reqwest::Response) -> TaskError {
-    let canned = match status.as_u16() {
-        401 => Some("連結已過期,請重新複製下載連結"),
-        403 => Some("權限不足,請重新複製下載連結"),
-        404 => Some("檔案已不存在"),
-        _ => None,
-    };
-    if let Some(msg) = canned {
-        return TaskError {
-            message: msg.to_string(),
-            retryable: false,
-        };
-    }
-    // 其他狀態碼:body 可能是 {code,message} JSON,也可能是 nginx HTML,別假設。
-    let detail = resp
-        .text()
-        .await
-        .ok()
-        .and_then(|body| serde_json::from_str::<serde_json::Value>(&body).ok())
-        .and_then(|v| v["message"].as_str().map(|s| format!(":{s}")))
-        .unwrap_or_default();
-    TaskError {
-        message: format!("HTTP {}{detail}", status.as_u16()),
-        retryable: status.is_server_error(),
+/// 非 2xx 回應轉錯誤。5xx 與未分類的狀態碼附上 body 的 message:
+/// body 可能是 {code,message} JSON,也可能是 nginx HTML,別假設。
+async fn map_status_error(resp: reqwest::Response) -> DownloadError {
+    let status = resp.status();
+    let retry_after = error::retry_after_secs(resp.headers());
+    match DownloadError::from_status(status, retry_after, "") {
+        DownloadError::Server(_) | DownloadError::Other(_) => {
+            let detail = resp
+                .text()
+                .await
+                .ok()
+                .and_then(|body| serde_json::from_str::<serde_json::Value>(&body).ok())
+                .and_then(|v| v["message"].as_str().map(|s| format!(":{s}")))
+                .unwrap_or_default();
+            DownloadError::from_status(status, retry_after, &detail)
+        }
+        e => e,
     }
 }
 
 // ---- 持久化(app_data_dir/http_tasks.json) ----
 
 #[derive(Serialize, Deserialize)]
-struct PersistedSegment {
+pub(crate) struct PersistedSegment {
     start: u64,
     end: u64,
     written: u64,
 }
 
+impl PersistedSegment {
+    pub(crate) fn from_segment(s: &Segment) -> Self {
+        PersistedSegment {
+            start: s.start,
+            end: s.end(),
+            written: s.written.load(Ordering::Relaxed),
+        }
+    }
+
+    pub(crate) fn into_segment(self) -> Segment {
+        Segment::new(self.start, self.end, self.written)
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 struct PersistedTask {
     id: u64,
     // token 即授權本體,但效期內續傳必須留著 URL;只存檔不進 log。
     url: String,
+    #[serde(default)]
+    mirrors: Vec<String>,
     file_name: String,
     dest_dir: String,
     total_bytes: u64,
     range_supported: bool,
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+    #[serde(default)]
+    checksum: Option<Checksum>,
+    // 同 url:cookie / Authorization 的值只存檔不進 log
+    #[serde(default)]
+    headers: HeaderSet,
     segments: Vec<PersistedSegment>,
     status: HttpStatus,
-    error: Option<String>,
+    error: Option<StoredError>,
+    /// 舊版檔案才有;新版併入 error
+    #[serde(default, skip_serializing)]
     retryable: bool,
+    #[serde(default)]
+    attempts: Vec<Attempt>,
+    /// bytes/sec,0 = 不限
+    #[serde(default)]
+    speed_limit_bps: u64,
+    /// 0 = 跟隨全域設定
+    #[serde(default)]
+    segment_count: usize,
+}
+
+/// 舊版 http_tasks.json 的 error 只是訊息字串(另存 retryable)
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredError {
+    Info(ErrorInfo),
+    Message(String),
+}
+
+impl StoredError {
+    fn into_info(self, retryable: bool) -> ErrorInfo {
+        match self {
+            StoredError::Info(info) => info,
+            StoredError::Message(message) => ErrorInfo {
+                code: if retryable { "NETWORK" } else { "OTHER" }.to_string(),
+                message,
+                retryable,
+                retry_after_secs: None,
+            },
+        }
+    }
 }
 
 pub struct HttpManager {
@@ -161,10 +302,28 @@ pub struct HttpManager {
     next_id: AtomicU64,
     state_path: PathBuf,
     client: reqwest::Client,
+    /// 自動重試策略(AppSettings.retry,存設定時即時更新)
+    retry: Mutex<RetryPolicy>,
+    /// 全域頻寬排程(與網站、BT 共用)
+    bandwidth: Arc<BandwidthLimiter>,
+    /// 每主機的連線數與請求間隔(與網站下載共用)
+    hosts: Arc<HostLimiter>,
+    /// 任務未指定時的並行連線數(AppSettings.download_segments,即時生效)
+    default_segments: AtomicUsize,
+    /// 依主機自動套用的 header 設定檔(AppSettings.header_profiles,即時生效)
+    header_profiles: Mutex<Vec<HeaderProfile>>,
+    /// 探測後查已下載索引、推 "library-hit" 用;未設定時不查
+    app: OnceLock<AppHandle>,
 }
 
 impl HttpManager {
-    pub fn load(state_path: PathBuf) -> Arc<Self> {
+    pub fn load(
+        state_path: PathBuf,
+        bandwidth: Arc<BandwidthLimiter>,
+        hosts: Arc<HostLimiter>,
+        cookies: Arc<CookieJar>,
+        proxy: Arc<ProxyRouter>,
+    ) -> Arc<Self> {
         let persisted: Vec<PersistedTask> = std::fs::read(&state_path)
             .ok()
             .and_then(|b| serde_json::from_slice(&b).ok())
@@ -177,23 +336,26 @@ impl HttpManager {
                 Arc::new(HttpTask {
                     id: p.id,
                     url: Mutex::new(p.url),
+                    mirrors: Mutex::new(p.mirrors),
                     file_name: Mutex::new(p.file_name),
                     dest_dir: PathBuf::from(p.dest_dir),
                     total_bytes: AtomicU64::new(p.total_bytes),
                     range_supported: AtomicBool::new(p.range_supported),
+                    etag: Mutex::new(p.etag),
+                    last_modified: Mutex::new(p.last_modified),
+                    checksum: Mutex::new(p.checksum),
+                    headers: Mutex::new(p.headers),
                     segments: Mutex::new(
                         p.segments
                             .into_iter()
-                            .map(|s| Segment {
-                                start: s.start,
-                                end: s.end,
-                                written: Arc::new(AtomicU64::new(s.written)),
-                            })
+                            .map(PersistedSegment::into_segment)
                             .collect(),
                     ),
                     status: Mutex::new(p.status),
-                    error: Mutex::new(p.error),
-                    retryable: AtomicBool::new(p.retryable),
+                    error: Mutex::new(p.error.map(|e| e.into_info(p.retryable))),
+                    attempts: Mutex::new(p.attempts),
+                    speed_limit: Arc::new(RateLimit::new(p.speed_limit_bps)),
+                    segment_count: AtomicUsize::new(p.segment_count),
                     stop: Mutex::new(Arc::new(AtomicBool::new(false))),
                 })
             })
@@ -215,11 +377,58 @@ impl HttpManager {
             state_path,
             client: reqwest::Client::builder()
                 .connect_timeout(std::time::Duration::from_secs(30))
+                // 與網站下載共用登入 cookie
+                .cookie_provider(cookies)
+                .proxy(proxy.reqwest_proxy(Engine::Http))
                 .build()
                 .expect("reqwest client"),
+            retry: Mutex::new(RetryPolicy::default()),
+            bandwidth,
+            hosts,
+            default_segments: AtomicUsize::new(DEFAULT_SEGMENTS),
+            header_profiles: Mutex::new(Vec::new()),
+            app: OnceLock::new(),
         })
     }
 
+    pub fn set_app_handle(&self, app: AppHandle) {
+        let _ = self.app.set(app);
+    }
+
+    pub fn set_retry_policy(&self, policy: RetryPolicy) {
+        *self.retry.lock().unwrap() = policy;
+    }
+
+    pub fn set_default_segments(&self, n: usize) {
+        self.default_segments.store(n, Ordering::Relaxed);
+    }
+
+    pub fn set_header_profiles(&self, profiles: Vec<HeaderProfile>) {
+        *self.header_profiles.lock().unwrap() = profiles;
+    }
+
+    /// 對 url 發請求時要帶的 header:主機符合的設定檔,再疊任務自己的。
+    /// 任務 header 只送往主連結的主機,其他主機的鏡像不帶(cookie 不外流)。
+    fn headers_for(&self, task: &HttpTask, url: &str) -> Result<HeaderMap, DownloadError> {
+        let primary = hosts::host_of(&task.url.lock().unwrap());
+        let own = if hosts::host_of(url) == primary {
+            task.headers.lock().unwrap().clone()
+        } else {
+            HeaderSet::default()
+        };
+        let profiles = self.header_profiles.lock().unwrap();
+        headers::resolve(&profiles, &own, url).to_header_map()
+    }
+
+    /// 任務實際使用的並行連線數
+    fn segments_for(&self, task: &HttpTask) -> usize {
+        match task.segment_count.load(Ordering::Relaxed) {
+            0 => self.default_segments.load(Ordering::Relaxed),
+            n => n,
+        }
+        .clamp(1, MAX_SEGMENTS)
+    }
+
     /// app 啟動時把上次仍在跑的任務接回去(中途殺 app 重開要續傳)。
     pub fn resume_interrupted(self: &Arc<Self>) {
         let running: Vec<_> = self
@@ -236,7 +445,12 @@ impl HttpManager {
     }
 
     pub fn find(&self, id: u64) -> Option<Arc<HttpTask>> {
-        self.tasks.lock().unwrap().iter().find(|t| t.id == id).cloned()
+        self.tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|t| t.id == id)
+            .cloned()
     }
 
     pub fn find_active_by_url(&self, url: &str) -> Option<Arc<HttpTask>> {
@@ -248,18 +462,31 @@ impl HttpManager {
             .cloned()
     }
 
-    pub fn add(&self, url: String, dest_dir: PathBuf, file_name: String) -> Arc<HttpTask> {
+    pub fn add(
+        &self,
+        url: String,
+        dest_dir: PathBuf,
+        file_name: String,
+        options: TaskOptions,
+    ) -> Arc<HttpTask> {
         let task = Arc::new(HttpTask {
             id: self.next_id.fetch_add(1, Ordering::Relaxed),
             url: Mutex::new(url),
+            mirrors: Mutex::new(options.mirrors),
             file_name: Mutex::new(file_name),
             dest_dir,
             total_bytes: AtomicU64::new(0),
             range_supported: AtomicBool::new(false),
+            etag: Mutex::new(None),
+            last_modified: Mutex::new(None),
+            checksum: Mutex::new(options.checksum),
+            headers: Mutex::new(options.headers),
             segments: Mutex::new(Vec::new()),
             status: Mutex::new(HttpStatus::Paused),
             error: Mutex::new(None),
-            retryable: AtomicBool::new(false),
+            attempts: Mutex::new(Vec::new()),
+            speed_limit: Arc::new(RateLimit::new(options.speed_limit_bps)),
+            segment_count: AtomicUsize::new(options.segment_count.min(MAX_SEGMENTS)),
             stop: Mutex::new(Arc::new(AtomicBool::new(false))),
         });
         self.tasks.lock().unwrap().push(task.clone());
@@ -277,6 +504,71 @@ impl HttpManager {
         }
     }
 
+    /// 加一個鏡像來源;下一輪開跑時先驗證是同一個檔案
+    pub fn add_mirror(&self, id: u64, url: String) -> bool {
+        let Some(task) = self.find(id) else {
+            return false;
+        };
+        {
+            let mut mirrors = task.mirrors.lock().unwrap();
+            if *task.url.lock().unwrap() != url && !mirrors.contains(&url) {
+                mirrors.push(url);
+            }
+        }
+        self.persist();
+        true
+    }
+
+    /// 換掉任務的自訂 header 與 cookie,下一輪開跑起生效
+    pub fn set_headers(&self, id: u64, headers: HeaderSet) -> bool {
+        let Some(task) = self.find(id) else {
+            return false;
+        };
+        *task.headers.lock().unwrap() = headers;
+        self.persist();
+        true
+    }
+
+    /// 調整任務速率上限(bytes/sec,0 = 不限),執行中的分段下一個 chunk 起生效
+    pub fn set_speed_limit(&self, id: u64, bps: u64) -> bool {
+        let Some(task) = self.find(id) else {
+            return false;
+        };
+        task.speed_limit.set(bps);
+        self.persist();
+        true
+    }
+
+    /// 排程用:暫停所有執行中(含等待重試)的任務
+    pub fn pause_all(&self) {
+        let ids: Vec<u64> = self
+            .tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|t| t.status() == HttpStatus::Running)
+            .map(|t| t.id)
+            .collect();
+        for id in ids {
+            self.pause(id);
+        }
+    }
+
+    /// 排程用:續跑所有暫停中的任務(失敗的任務不動,留給使用者判斷)
+    pub fn resume_paused(self: &Arc<Self>) {
+        let paused: Vec<_> = self
+            .tasks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|t| t.status() == HttpStatus::Paused)
+            .cloned()
+            .collect();
+        for t in paused {
+            self.spawn_run(t);
+        }
+    }
+
     pub fn remove(&self, id: u64, delete_files: bool) {
         let task = {
             let mut tasks = self.tasks.lock().unwrap();
@@ -302,24 +594,28 @@ impl HttpManager {
             .map(|t| PersistedTask {
                 id: t.id,
                 url: t.url.lock().unwrap().clone(),
+                mirrors: t.mirrors.lock().unwrap().clone(),
                 file_name: t.file_name.lock().unwrap().clone(),
                 dest_dir: t.dest_dir.to_string_lossy().into_owned(),
                 total_bytes: t.total_bytes.load(Ordering::Relaxed),
                 range_supported: t.range_supported.load(Ordering::Relaxed),
+                etag: t.etag.lock().unwrap().clone(),
+                last_modified: t.last_modified.lock().unwrap().clone(),
+                checksum: t.checksum.lock().unwrap().clone(),
+                headers: t.headers.lock().unwrap().clone(),
                 segments: t
                     .segments
                     .lock()
                     .unwrap()
                     .iter()
-                    .map(|s| PersistedSegment {
-                        start: s.start,
-                        end: s.end,
-                        written: s.written.load(Ordering::Relaxed),
-                    })
+                    .map(PersistedSegment::from_segment)
                     .collect(),
                 status: t.status(),
-                error: t.error.lock().unwrap().clone(),
-                retryable: t.retryable.load(Ordering::Relaxed),
+                error: t.error.lock().unwrap().clone().map(StoredError::Info),
+                retryable: false,
+                attempts: t.attempts.lock().unwrap().clone(),
+                speed_limit_bps: t.speed_limit.get(),
+                segment_count: t.segment_count.load(Ordering::Relaxed),
             })
             .collect();
         if let Ok(json) = serde_json::to_string_pretty(&data) {
@@ -328,6 +624,7 @@ impl HttpManager {
     }
 
     /// 啟動(或續跑)一個任務。狀態立即轉 Running;實際下載在背景 task。
+    /// 失敗時依重試策略退避後自動再跑(等待期間仍是 Running,可暫停)。
     /// 結束時:使用者主動停(stop 旗標)→ 不動狀態;否則寫 Finished / Error。
     pub fn spawn_run(self: &Arc<Self>, task: Arc<HttpTask>) {
         let stop = Arc::new(AtomicBool::new(false));
@@ -338,27 +635,54 @@ impl HttpManager {
 
         let mgr = self.clone();
         tauri::async_runtime::spawn(async move {
-            let result = mgr.run_task(&task, &stop).await;
-            if stop.load(Ordering::Relaxed) {
-                // 暫停/刪除已由指令端處理狀態,這裡不能覆寫。
-                return;
-            }
-            match result {
-                Ok(()) => {
-                    *task.status.lock().unwrap() = HttpStatus::Finished;
-                    *task.error.lock().unwrap() = None;
+            let mut attempt = 1;
+            loop {
+                let result = mgr.run_task(&task, &stop).await;
+                if stop.load(Ordering::Relaxed) {
+                    // 暫停/刪除已由指令端處理狀態,這裡不能覆寫。
+                    return;
                 }
-                Err(e) => {
+                let e = match result {
+                    Ok(()) => {
+                        *task.status.lock().unwrap() = HttpStatus::Finished;
+                        *task.error.lock().unwrap() = None;
+                        break;
+                    }
+                    Err(e) => e,
+                };
+                let delay = mgr.retry.lock().unwrap().delay_for(&e, attempt);
+                retry::push_history(
+                    &mut task.attempts.lock().unwrap(),
+                    Attempt::new(attempt, e.to_string(), delay),
+                );
+                let mut info = ErrorInfo::from(&e);
+                let Some(delay) = delay else {
                     *task.status.lock().unwrap() = HttpStatus::Error;
-                    *task.error.lock().unwrap() = Some(e.message);
-                    task.retryable.store(e.retryable, Ordering::Relaxed);
+                    *task.error.lock().unwrap() = Some(info);
+                    break;
+                };
+                info.message = format!(
+                    "{}({} 秒後自動重試)",
+                    info.message,
+                    delay.as_secs_f64().ceil() as u64
+                );
+                *task.error.lock().unwrap() = Some(info);
+                mgr.persist();
+                if !retry::wait(delay, &stop).await {
+                    return;
                 }
+                *task.error.lock().unwrap() = None;
+                attempt += 1;
             }
             mgr.persist();
         });
     }
 
-    async fn run_task(&self, task: &Arc<HttpTask>, stop: &Arc<AtomicBool>) -> Result<(), TaskError> {
+    async fn run_task(
+        &self,
+        task: &Arc<HttpTask>,
+        stop: &Arc<AtomicBool>,
+    ) -> Result<(), DownloadError> {
         let url = task.url.lock().unwrap().clone();
 
         // 續傳前置檢查:.part 不見了就只能從頭來。
@@ -368,7 +692,10 @@ impl HttpManager {
         }
 
         if task.segments.lock().unwrap().is_empty() {
-            self.probe_and_prepare(task, &url).await?;
+            self.probe_and_prepare(task, &url, stop).await?;
+            if stop.load(Ordering::Relaxed) {
+                return Ok(());
+            }
         } else if !task.range_supported.load(Ordering::Relaxed) {
             // 無 Range 支援的任務只能整檔重來。
             for s in task.segments.lock().unwrap().iter() {
@@ -381,58 +708,59 @@ impl HttpManager {
                 .write(true)
                 .truncate(true)
                 .open(task.part_path())
-                .await
-                .map_err(TaskError::io)?;
+                .await?;
             let total = task.total_bytes.load(Ordering::Relaxed);
             if total > 0 {
-                file.set_len(total).await.map_err(TaskError::io)?;
+                file.set_len(total).await?;
             }
         }
 
+        self.validate_mirrors(task, stop).await;
         if stop.load(Ordering::Relaxed) {
             return Ok(());
         }
 
         let part_path = task.part_path();
         let ranged = task.range_supported.load(Ordering::Relaxed);
-        // 任一段失敗時令其他段也停下,與使用者暫停分開計。
-        let abort = Arc::new(AtomicBool::new(false));
-
-        let mut set = JoinSet::new();
-        for seg in task.segments.lock().unwrap().iter() {
-            let written = seg.written.load(Ordering::Relaxed);
-            if seg.end != UNBOUNDED && seg.start + written >= seg.end {
-                continue;
-            }
-            set.spawn(download_segment(
-                self.client.clone(),
-                url.clone(),
-                part_path.clone(),
-                seg.start,
-                seg.end,
-                seg.written.clone(),
-                stop.clone(),
-                abort.clone(),
-                ranged,
-            ));
-        }
-
-        let mut first_err: Option<TaskError> = None;
-        while let Some(res) = set.join_next().await {
-            let seg_result = res.unwrap_or_else(|e| {
-                Err(TaskError {
-                    message: format!("下載執行緒異常:{e}"),
-                    retryable: true,
-                })
-            });
-            if let Err(e) = seg_result {
-                if first_err.is_none() {
-                    first_err = Some(e);
-                    abort.store(true, Ordering::Relaxed);
-                }
+        let mut urls = vec![url];
+        urls.extend(task.mirrors.lock().unwrap().iter().cloned());
+        let sources = urls
+            .into_iter()
+            .map(|url| {
+                let headers = self.headers_for(task, &url)?;
+                Ok(Source { url, headers })
+            })
+            .collect::<Result<Vec<_>, DownloadError>>()?;
+        let sources = Mutex::new(sources);
+        let throttle = self
+            .bandwidth
+            .throttle(Engine::Http)
+            .with_task_limit(task.speed_limit.clone());
+        let if_range = task.if_range();
+        let result = download_segments(
+            &self.client,
+            &sources,
+            if_range.as_deref(),
+            &part_path,
+            &task.segments,
+            ranged,
+            self.segments_for(task),
+            stop,
+            &throttle,
+            &self.hosts,
+        )
+        .await;
+        // 下載中失敗的鏡像移除;主連結是任務本身,只在本輪不用
+        let sources = sources.into_inner().unwrap();
+        task.mirrors
+            .lock()
+            .unwrap()
+            .retain(|m| sources.iter().any(|s| s.url == *m));
+        if let Err(e) = result {
+            if matches!(e, DownloadError::RemoteChanged) {
+                // 舊進度不可再用:丟掉 .part,重試時重新探測、從頭下載
+                self.discard(task);
             }
-        }
-        if let Some(e) = first_err {
             return Err(e);
         }
         if stop.load(Ordering::Relaxed) {
@@ -442,145 +770,506 @@ impl HttpManager {
         // 完整性檢查:全部段都到位才算完成(stream 提前斷線不會回 Err)。
         let total = task.total_bytes.load(Ordering::Relaxed);
         if total > 0 && task.downloaded() < total {
-            return Err(TaskError {
-                message: "網路中斷,可重試(下載不完整)".to_string(),
-                retryable: true,
-            });
+            return Err(DownloadError::Network("下載不完整".to_string()));
+        }
+        let file_name = task.file_name.lock().unwrap().clone();
+        if let Err(e) = verify_download(&part_path, total, &file_name).await {
+            self.discard(task);
+            return Err(e);
         }
 
         let final_path = unique_path(&task.final_path());
-        tokio::fs::rename(&part_path, &final_path)
-            .await
-            .map_err(TaskError::io)?;
+        tokio::fs::rename(&part_path, &final_path).await?;
         if let Some(name) = final_path.file_name() {
             *task.file_name.lock().unwrap() = name.to_string_lossy().into_owned();
         }
+
+        // 雜湊不符時檔案留在原地供檢查,任務停在錯誤狀態
+        let expected = task.checksum.lock().unwrap().clone();
+        if let Some(expected) = expected {
+            tokio::task::spawn_blocking(move || checksum::verify_checksum(&final_path, &expected))
+                .await
+                .map_err(|e| DownloadError::Other(e.to_string()))??;
+        }
         Ok(())
     }
 
-    /// 首次請求:確認狀態碼、取檔名(Content-Disposition)、總大小與 Range
-    /// 支援度,然後預配置 .part 檔並切段。
-    async fn probe_and_prepare(&self, task: &Arc<HttpTask>, url: &str) -> Result<(), TaskError> {
+    /// 驗證不過的檔案不可信:刪掉 .part 並清空分段,重試時重新探測、從頭下載。
+    fn discard(&self, task: &HttpTask) {
+        let _ = std::fs::remove_file(task.part_path());
+        task.segments.lock().unwrap().clear();
+        task.total_bytes.store(0, Ordering::Relaxed);
+        self.persist();
+    }
+
+    /// 發一次 `Range: bytes=0-` 請求,只讀 header:狀態碼、檔名、總大小、Range 支援度、ETag。
+    /// stop 在等主機連線時被設起則回 None。
+    async fn probe(
+        &self,
+        url: &str,
+        headers: HeaderMap,
+        stop: &AtomicBool,
+    ) -> Result<Option<Probe>, DownloadError> {
+        let host = hosts::host_of(url);
+        let Some(_permit) = self
+            .hosts
+            .acquire(&host, || stop.load(Ordering::Relaxed))
+            .await
+        else {
+            return Ok(None);
+        };
         let resp = self
             .client
             .get(url)
+            .headers(headers)
             .header(RANGE, "bytes=0-")
             .send()
-            .await
-            .map_err(TaskError::net)?;
+            .await?;
         let status = resp.status();
         if !status.is_success() {
-            return Err(map_status_error(status, resp).await);
+            // 429 時先暫停這個主機,重試會等到 Retry-After 之後
+            self.hosts.back_off(&host, &resp);
+            return Err(map_status_error(resp).await);
         }
 
-        if let Some(name) = resp
-            .headers()
-            .get(CONTENT_DISPOSITION)
-            .and_then(|v| filename_from_content_disposition(&String::from_utf8_lossy(v.as_bytes())))
-        {
-            let cleaned = sanitize_folder_name(&name);
+        let headers = resp.headers();
+        let probe = Probe {
+            ranged: status == StatusCode::PARTIAL_CONTENT,
+            total: headers
+                .get(CONTENT_RANGE)
+                .and_then(|v| v.to_str().ok())
+                .and_then(total_from_content_range)
+                .or(resp.content_length())
+                .unwrap_or(0),
+            etag: headers
+                .get(ETAG)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+            file_name: headers.get(CONTENT_DISPOSITION).and_then(|v| {
+                filename_from_content_disposition(&String::from_utf8_lossy(v.as_bytes()))
+            }),
+            last_modified: headers
+                .get(LAST_MODIFIED)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+            checksum: Checksum::from_headers(headers),
+        };
+        // 內容用分段請求重抓,這條連線只要 header
+        Ok(Some(probe))
+    }
+
+    /// 首次請求:確認狀態碼、取檔名(Content-Disposition)、總大小與 Range
+    /// 支援度,然後預配置 .part 檔並切段。
+    async fn probe_and_prepare(
+        &self,
+        task: &Arc<HttpTask>,
+        url: &str,
+        stop: &AtomicBool,
+    ) -> Result<(), DownloadError> {
+        let headers = self.headers_for(task, url)?;
+        let Some(probe) = self.probe(url, headers.clone(), stop).await? else {
+            return Ok(());
+        };
+        if let Some(name) = &probe.file_name {
+            let cleaned = sanitize_folder_name(name);
             if !cleaned.is_empty() {
                 *task.file_name.lock().unwrap() = cleaned;
             }
         }
-
-        let ranged = status == StatusCode::PARTIAL_CONTENT;
-        let total = resp
-            .headers()
-            .get(CONTENT_RANGE)
-            .and_then(|v| v.to_str().ok())
-            .and_then(total_from_content_range)
-            .or(resp.content_length())
-            .unwrap_or(0);
-        drop(resp); // 內容用分段請求重抓,這條連線只要 header
-
+        let (total, ranged) = (probe.total, probe.ranged);
         task.range_supported.store(ranged, Ordering::Relaxed);
         task.total_bytes.store(total, Ordering::Relaxed);
+        *task.etag.lock().unwrap() = probe.etag;
+        *task.last_modified.lock().unwrap() = probe.last_modified;
+        if task.checksum.lock().unwrap().is_none() {
+            let found = match probe.checksum {
+                Some(c) => Some(c),
+                None => {
+                    let remote_name = reqwest::Url::parse(url)
+                        .ok()
+                        .as_ref()
+                        .and_then(filename_from_url)
+                        .unwrap_or_default();
+                    let file_name = task.file_name.lock().unwrap().clone();
+                    fetch_sidecar_checksum(
+                        &self.client,
+                        &self.hosts,
+                        url,
+                        headers,
+                        &[&remote_name, &file_name],
+                        stop,
+                    )
+                    .await
+                }
+            };
+            *task.checksum.lock().unwrap() = found;
+        }
 
-        let segments = if ranged && total >= MIN_SPLIT_BYTES {
-            let n = SEGMENT_COUNT;
-            let base = total / n;
-            (0..n)
-                .map(|i| Segment {
-                    start: i * base,
-                    end: if i == n - 1 { total } else { (i + 1) * base },
-                    written: Arc::new(AtomicU64::new(0)),
-                })
-                .collect()
-        } else {
-            vec![Segment {
-                start: 0,
-                end: if total > 0 { total } else { UNBOUNDED },
-                written: Arc::new(AtomicU64::new(0)),
-            }]
-        };
+        // 同內容已下載過(換了連結、改過檔名也算):不寫檔,移除任務並告知既有檔案位置
+        if let Some(entry) = self.library_hit(task, total) {
+            tracing::info!("HTTP: {} 已下載過: {}", url, entry.file_path);
+            if let Some(app) = self.app.get() {
+                library::emit_hit(app, &entry);
+            }
+            // final_path 可能就是既有檔案,只清 .part
+            let _ = std::fs::remove_file(task.part_path());
+            self.remove(task.id, false);
+            return Ok(());
+        }
 
-        tokio::fs::create_dir_all(&task.dest_dir)
-            .await
-            .map_err(TaskError::io)?;
+        let segments = plan_segments(total, ranged, self.segments_for(task));
+
+        tokio::fs::create_dir_all(&task.dest_dir).await?;
         let file = tokio::fs::OpenOptions::new()
             .create(true)
             .write(true)
             .truncate(true)
             .open(task.part_path())
-            .await
-            .map_err(TaskError::io)?;
+            .await?;
         if total > 0 {
-            file.set_len(total).await.map_err(TaskError::io)?;
+            file.set_len(total).await?;
         }
 
         *task.segments.lock().unwrap() = segments;
         self.persist();
         Ok(())
     }
+
+    /// 以探測到的大小 + ETag(強) / SHA-256 查已下載索引
+    fn library_hit(&self, task: &HttpTask, total: u64) -> Option<LibraryEntry> {
+        let app = self.app.get()?;
+        let etag = task.etag.lock().unwrap().clone();
+        let sha256 = task
+            .checksum
+            .lock()
+            .unwrap()
+            .clone()
+            .filter(|c| c.algo == HashAlgo::Sha256)
+            .map(|c| c.hex);
+        library::find_content(
+            app,
+            total,
+            etag.as_deref().filter(|e| !e.starts_with("W/")),
+            sha256.as_deref(),
+        )
+    }
+
+    /// 每輪開跑前確認鏡像與主連結是同一個檔案:大小相同、都支援 Range,
+    /// 兩邊都有 ETag 時還要相同。不符或連不上的鏡像直接移除。
+    async fn validate_mirrors(&self, task: &HttpTask, stop: &AtomicBool) {
+        let mirrors = task.mirrors.lock().unwrap().clone();
+        if mirrors.is_empty() {
+            return;
+        }
+        let total = task.total_bytes.load(Ordering::Relaxed);
+        let ranged = task.range_supported.load(Ordering::Relaxed);
+        let etag = task.etag.lock().unwrap().clone();
+        let mut keep = Vec::new();
+        for mirror in mirrors {
+            let host = hosts::host_of(&mirror);
+            let probed = match self.headers_for(task, &mirror) {
+                Ok(headers) => self.probe(&mirror, headers, stop).await,
+                Err(e) => Err(e),
+            };
+            match probed {
+                Ok(None) => return,
+                Ok(Some(p)) if p.matches(total, ranged, etag.as_deref()) => keep.push(mirror),
+                Ok(Some(_)) => tracing::warn!("HTTP: 鏡像 {} 的檔案與主連結不符,已移除", host),
+                Err(e) => tracing::warn!("HTTP: 鏡像 {} 無法使用,已移除: {}", host, e),
+            }
+        }
+        *task.mirrors.lock().unwrap() = keep;
+        self.persist();
+    }
+}
+
+/// 探測結果(只看 header)
+struct Probe {
+    ranged: bool,
+    /// 0 = 未知
+    total: u64,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    file_name: Option<String>,
+    /// Digest / Content-MD5 宣告的雜湊
+    checksum: Option<Checksum>,
+}
+
+impl Probe {
+    /// 鏡像可與主連結混用:大小已知且相同、支援 Range,兩邊都有 ETag 時相同
+    fn matches(&self, total: u64, ranged: bool, etag: Option<&str>) -> bool {
+        let same_etag = match (etag, self.etag.as_deref()) {
+            (Some(a), Some(b)) => a == b,
+            _ => true,
+        };
+        ranged && self.ranged && total > 0 && self.total == total && same_etag
+    }
+}
+
+/// 完成後驗證:已知大小時比對檔案大小,.zip 另外檢查中央目錄與 CRC。
+async fn verify_download(
+    part_path: &Path,
+    total: u64,
+    file_name: &str,
+) -> Result<(), DownloadError> {
+    let path = part_path.to_path_buf();
+    let is_zip = file_name.to_ascii_lowercase().ends_with(".zip");
+    tokio::task::spawn_blocking(move || {
+        if total > 0 {
+            verify::verify_size(&path, total)?;
+        }
+        if is_zip {
+            verify::verify_zip(&path)?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| DownloadError::Other(e.to_string()))?
+}
+
+/// 切段:支援 Range 時依大小切最多 max 段(每段至少 MIN_SEGMENT_BYTES),
+/// 否則單段(total 未知時 end 為 UNBOUNDED)。
+pub(crate) fn plan_segments(total: u64, ranged: bool, max: usize) -> Vec<Segment> {
+    let n = (total / MIN_SEGMENT_BYTES).min(max.clamp(1, MAX_SEGMENTS) as u64);
+    if ranged && n > 1 {
+        let base = total / n;
+        (0..n)
+            .map(|i| Segment::new(i * base, if i == n - 1 { total } else { (i + 1) * base }, 0))
+            .collect()
+    } else {
+        vec![Segment::new(
+            0,
+            if total > 0 { total } else { UNBOUNDED },
+            0,
+        )]
+    }
+}
+
+/// 工作竊取:剩最多的分段從目前位置到結尾對半切,後半段加進分段表交給閒下來的連線。
+/// 原分段的 worker 寫到新的 end 就停。剩餘太少則不切。
+fn split_largest(segments: &Mutex<Vec<Segment>>) -> Option<Segment> {
+    let mut segments = segments.lock().unwrap();
+    let victim = segments
+        .iter()
+        .filter_map(|s| s.remaining().map(|r| (r, s)))
+        .max_by_key(|(r, _)| *r);
+    let (remaining, victim) = victim?;
+    if remaining < 2 * MIN_STEAL_BYTES {
+        return None;
+    }
+    let mid = victim.pos() + remaining / 2;
+    let stolen = Segment::new(mid, victim.end(), 0);
+    victim.end.store(mid, Ordering::Relaxed);
+    segments.push(stolen.clone());
+    Some(stolen)
+}
+
+/// 未完成分段以最多 workers 條連線並行下載到 .part(需已預配置)。有連線先做完時,
+/// 排隊的分段先上,沒有就從剩最多的分段切一半來做(工作竊取,需支援 Range)。
+/// if_range 為主連結(sources 第一個)的驗證值,續傳請求帶 If-Range,遠端檔案變了
+/// 伺服器會回整檔 200,此時回 RemoteChanged 而不把新內容接到舊資料後面。
+/// 分段輪流分派到 sources 的各個來源(請求帶該來源的 header);某個來源失敗時
+/// (還有其他來源、支援 Range)把它從 sources 移除,該段改由其他來源接著下載。
+/// 其他失敗令所有段停下,回傳第一個錯誤;stop 被設起時各段寫完手上的 chunk 即返回 Ok。
+/// 新切出的分段直接加進 segments,呼叫端照常落地即可續傳。
+/// HTTP 直鏈與網站下載共用;各段共用同一個 throttle 的額度,每段發請求前向 hosts 佔一條連線。
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn download_segments(
+    client: &reqwest::Client,
+    sources: &Mutex<Vec<Source>>,
+    if_range: Option<&str>,
+    part_path: &Path,
+    segments: &Mutex<Vec<Segment>>,
+    ranged: bool,
+    workers: usize,
+    stop: &Arc<AtomicBool>,
+    throttle: &Throttle,
+    hosts: &Arc<HostLimiter>,
+) -> Result<(), DownloadError> {
+    // 任一段失敗時令其他段也停下,與使用者暫停分開計。
+    let abort = Arc::new(AtomicBool::new(false));
+    let mut turn = 0usize;
+    let primary = sources.lock().unwrap().first().map(|s| s.url.clone());
+    let mut start = |set: &mut JoinSet<_>, seg: Segment| {
+        let source = {
+            let sources = sources.lock().unwrap();
+            sources[turn % sources.len()].clone()
+        };
+        turn += 1;
+        // 鏡像的 ETag 各自不同,If-Range 只對主連結有意義
+        let validator = if_range
+            .filter(|_| primary.as_ref() == Some(&source.url))
+            .map(str::to_string);
+        let url = source.url.clone();
+        let work = download_segment(
+            client.clone(),
+            source,
+            validator,
+            part_path.to_path_buf(),
+            seg.clone(),
+            stop.clone(),
+            abort.clone(),
+            ranged,
+            throttle.clone(),
+            hosts.clone(),
+        );
+        set.spawn(async move { (seg, url, work.await) });
+    };
+
+    let mut pending: VecDeque<Segment> = segments
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|s| s.remaining() != Some(0))
+        .cloned()
+        .collect();
+    let mut set = JoinSet::new();
+    while set.len() < workers.max(1) {
+        let Some(seg) = pending.pop_front() else {
+            break;
+        };
+        start(&mut set, seg);
+    }
+
+    let mut first_err: Option<DownloadError> = None;
+    while let Some(res) = set.join_next().await {
+        let (seg, url, seg_result) = match res {
+            Ok(done) => done,
+            Err(e) => {
+                first_err.get_or_insert(DownloadError::Other(format!("下載執行緒異常:{e}")));
+                abort.store(true, Ordering::Relaxed);
+                continue;
+            }
+        };
+        match seg_result {
+            Ok(()) => {}
+            Err(e) if ranged && first_err.is_none() && drop_source(sources, &url, &e) => {
+                tracing::warn!(
+                    "HTTP: 來源 {} 失敗,改由其他鏡像接手: {}",
+                    hosts::host_of(&url),
+                    e
+                );
+                pending.push_front(seg);
+            }
+            Err(e) => {
+                first_err.get_or_insert(e);
+                abort.store(true, Ordering::Relaxed);
+                continue;
+            }
+        }
+        if stop.load(Ordering::Relaxed) || abort.load(Ordering::Relaxed) {
+            continue;
+        }
+        let next = match pending.pop_front() {
+            Some(seg) => Some(seg),
+            None if ranged => split_largest(segments),
+            None => None,
+        };
+        if let Some(seg) = next {
+            start(&mut set, seg);
+        }
+    }
+    match first_err {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
 }
 
-/// 單一分段:從 start+written 處發 Range 請求,串流寫入 .part 對應偏移。
-/// 整包資料不落記憶體,逐 chunk 寫盤。
+/// 來源本身的問題(連不上、連結失效、內容不符)才換來源;磁碟錯誤換來源也沒用。
+/// 還有其他來源時移除 url 並回 true。
+fn drop_source(sources: &Mutex<Vec<Source>>, url: &str, error: &DownloadError) -> bool {
+    if matches!(
+        error,
+        DownloadError::DiskFull(_) | DownloadError::PermissionDenied(_) | DownloadError::Cancelled
+    ) {
+        return false;
+    }
+    let mut sources = sources.lock().unwrap();
+    if !sources.iter().any(|s| s.url == url) {
+        // 同一來源的其他分段已經先把它移除了
+        return !sources.is_empty();
+    }
+    if sources.len() < 2 {
+        return false;
+    }
+    sources.retain(|s| s.url != url);
+    true
+}
+
+/// 單一分段:從 start+written 處發 Range 請求,串流寫入 .part 對應偏移,寫到 end 為止
+/// (end 可能在下載中被工作竊取調小)。整包資料不落記憶體,逐 chunk 寫盤。
+/// 主機回 429 時暫停該主機,等 Retry-After 過後這段自己重發請求,不讓整個任務失敗。
 #[allow(clippy::too_many_arguments)]
 async fn download_segment(
     client: reqwest::Client,
-    url: String,
+    source: Source,
+    if_range: Option<String>,
     part_path: PathBuf,
-    start: u64,
-    end: u64,
-    written: Arc<AtomicU64>,
+    seg: Segment,
     stop: Arc<AtomicBool>,
     abort: Arc<AtomicBool>,
     ranged: bool,
-) -> Result<(), TaskError> {
-    let pos = start + written.load(Ordering::Relaxed);
-    let mut req = client.get(&url);
-    if ranged {
-        let range = if end == UNBOUNDED {
-            format!("bytes={pos}-")
-        } else {
-            format!("bytes={pos}-{}", end - 1)
+    throttle: Throttle,
+    hosts: Arc<HostLimiter>,
+) -> Result<(), DownloadError> {
+    let host = hosts::host_of(&source.url);
+    let mut backoffs = 0;
+    let (resp, _permit) = loop {
+        let stopped = || stop.load(Ordering::Relaxed) || abort.load(Ordering::Relaxed);
+        let Some(permit) = hosts.acquire(&host, stopped).await else {
+            return Ok(());
         };
-        req = req.header(RANGE, range);
-    }
-    let resp = req.send().await.map_err(TaskError::net)?;
+        let pos = seg.pos();
+        let end = seg.end();
+        if pos >= end {
+            // 等連線期間被工作竊取切到一點不剩
+            return Ok(());
+        }
+        let mut req = client.get(&source.url).headers(source.headers.clone());
+        if ranged {
+            let range = if end == UNBOUNDED {
+                format!("bytes={pos}-")
+            } else {
+                format!("bytes={pos}-{}", end - 1)
+            };
+            req = req.header(RANGE, range);
+            if let Some(validator) = &if_range {
+                req = req.header(IF_RANGE, validator);
+            }
+        }
+        let resp = req.send().await?;
+        if backoffs < MAX_HOST_BACKOFFS {
+            if let Some(wait) = hosts.back_off(&host, &resp) {
+                backoffs += 1;
+                tracing::info!(
+                    "HTTP: {} 要求放慢 ({}),暫停 {} 秒",
+                    host,
+                    resp.status(),
+                    wait.as_secs()
+                );
+                continue;
+            }
+        }
+        break (resp, permit);
+    };
+    let mut pos = seg.pos();
     match resp.status() {
         StatusCode::PARTIAL_CONTENT => {}
+        // 帶 If-Range 卻回整檔 → 驗證值不符,遠端檔案已換過
+        StatusCode::OK if ranged && if_range.is_some() => return Err(DownloadError::RemoteChanged),
         StatusCode::OK if !ranged || pos == 0 => {}
         StatusCode::OK => {
             // 要求 Range 卻回整檔 → 伺服器行為變了,續傳資料不可信。
-            return Err(TaskError {
-                message: "伺服器不再支援續傳,請刪除任務後重新加入".to_string(),
-                retryable: false,
-            });
+            return Err(DownloadError::RangeNotSupported);
         }
-        status => return Err(map_status_error(status, resp).await),
+        _ => return Err(map_status_error(resp).await),
     }
 
     let mut file = tokio::fs::OpenOptions::new()
         .write(true)
         .open(&part_path)
-        .await
-        .map_err(TaskError::io)?;
-    file.seek(std::io::SeekFrom::Start(pos))
-        .await
-        .map_err(TaskError::io)?;
+        .await?;
+    file.seek(std::io::SeekFrom::Start(pos)).await?;
 
     let mut stream = resp.bytes_stream();
     while let Some(chunk) = stream.next().await {
@@ -588,14 +1277,55 @@ async fn download_segment(
             let _ = file.flush().await;
             return Ok(());
         }
-        let chunk = chunk.map_err(TaskError::net)?;
-        file.write_all(&chunk).await.map_err(TaskError::io)?;
-        written.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        let chunk = chunk?;
+        let end = seg.end();
+        let len = if end == UNBOUNDED {
+            chunk.len()
+        } else {
+            chunk.len().min(end.saturating_sub(pos) as usize)
+        };
+        file.write_all(&chunk[..len]).await?;
+        pos += seg.commit(pos, len);
+        if pos >= seg.end() {
+            break;
+        }
+        if !throttle.acquire_either(len as u64, &stop, &abort).await {
+            let _ = file.flush().await;
+            return Ok(());
+        }
     }
-    file.flush().await.map_err(TaskError::io)?;
+    file.flush().await?;
     Ok(())
 }
 
+/// 同目錄的 `{檔名}.sha256`(沿用原連結的 query,簽章連結可能因此失效,抓不到就算了)。
+/// 與分段請求一樣先向 hosts 佔一條連線;names 為 sidecar 內可能列的檔名,依序比對
+async fn fetch_sidecar_checksum(
+    client: &reqwest::Client,
+    hosts: &Arc<HostLimiter>,
+    url: &str,
+    headers: HeaderMap,
+    names: &[&str],
+    stop: &AtomicBool,
+) -> Option<Checksum> {
+    let mut sidecar = reqwest::Url::parse(url).ok()?;
+    let path = format!("{}.sha256", sidecar.path());
+    sidecar.set_path(&path);
+    let host = hosts::host_of(url);
+    let _permit = hosts
+        .acquire(&host, || stop.load(Ordering::Relaxed))
+        .await?;
+    let resp = client.get(sidecar).headers(headers).send().await.ok()?;
+    if !resp.status().is_success() || resp.content_length().unwrap_or(0) > 64 * 1024 {
+        hosts.back_off(&host, &resp);
+        return None;
+    }
+    let text = resp.text().await.ok()?;
+    names
+        .iter()
+        .find_map(|name| Checksum::from_sidecar(&text, name))
+}
+
 /// `filename*=UTF-8''…`(RFC 5987)優先,退回 `filename="…"`。
 fn filename_from_content_disposition(value: &str) -> Option<String> {
     for part in value.split(';') {
@@ -644,7 +1374,10 @@ fn unique_path(path: &Path) -> PathBuf {
     if !path.exists() {
         return path.to_path_buf();
     }
-    let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy())
+        .unwrap_or_default();
     let ext = path
         .extension()
         .map(|e| format!(".{}", e.to_string_lossy()))
@@ -662,6 +1395,7 @@ fn unique_path(path: &Path) -> PathBuf {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::test_server::serve;
 
     #[test]
     fn parses_content_disposition_plain() {
@@ -688,12 +1422,151 @@ mod tests {
         assert_eq!(total_from_content_range("bytes 0-499/*"), None);
     }
 
+    #[test]
+    fn plan_segments_splits_large_ranged_files() {
+        let total = MIN_SEGMENT_BYTES * 8 + 3;
+        let segs = plan_segments(total, true, DEFAULT_SEGMENTS);
+        assert_eq!(segs.len(), DEFAULT_SEGMENTS);
+        assert_eq!(segs[0].start, 0);
+        assert_eq!(segs.last().unwrap().end(), total);
+        assert!(segs.windows(2).all(|w| w[0].end() == w[1].start));
+        // 大小只夠切三段時不硬切四段
+        assert_eq!(plan_segments(MIN_SEGMENT_BYTES * 3, true, 8).len(), 3);
+    }
+
+    #[test]
+    fn plan_segments_single_when_small_or_unranged() {
+        assert_eq!(plan_segments(MIN_SEGMENT_BYTES * 2 - 1, true, 4).len(), 1);
+        assert_eq!(plan_segments(MIN_SEGMENT_BYTES * 8, false, 4).len(), 1);
+        assert_eq!(plan_segments(MIN_SEGMENT_BYTES * 8, true, 1).len(), 1);
+        assert_eq!(plan_segments(0, false, 4)[0].end(), UNBOUNDED);
+    }
+
+    #[test]
+    fn split_largest_halves_remaining_work() {
+        let total = MIN_STEAL_BYTES * 8;
+        let segments = Mutex::new(vec![
+            Segment::new(0, total / 2, total / 2),
+            Segment::new(total / 2, total, MIN_STEAL_BYTES),
+        ]);
+        let stolen = split_largest(&segments).unwrap();
+        let mid = total / 2 + MIN_STEAL_BYTES + (total / 2 - MIN_STEAL_BYTES) / 2;
+        assert_eq!((stolen.start, stolen.end()), (mid, total));
+        let segs = segments.lock().unwrap();
+        assert_eq!(segs.len(), 3);
+        assert_eq!(segs[1].end(), mid);
+        drop(segs);
+
+        // 剩餘太少就不再切
+        let small = Mutex::new(vec![Segment::new(0, MIN_STEAL_BYTES, 0)]);
+        assert!(split_largest(&small).is_none());
+        let unbounded = Mutex::new(vec![Segment::new(0, UNBOUNDED, 0)]);
+        assert!(split_largest(&unbounded).is_none());
+    }
+
+    #[tokio::test]
+    async fn sidecar_checksum_is_optional() {
+        let hex = "ab".repeat(32);
+        let body = format!("{hex}  other.iso\n{hex}  a.iso\n").into_bytes();
+        let base = serve(vec![("/files/a.iso.sha256", 200, body)]).await;
+        let client = reqwest::Client::new();
+        let hosts = Arc::new(HostLimiter::default());
+        let stop = AtomicBool::new(false);
+        let fetch = |url: String| {
+            let (client, hosts, stop) = (&client, &hosts, &stop);
+            async move {
+                fetch_sidecar_checksum(client, hosts, &url, HeaderMap::new(), &["a.iso"], stop)
+                    .await
+            }
+        };
+        let found = fetch(format!("{base}/files/a.iso")).await.unwrap();
+        assert_eq!(found.hex, hex);
+        // 沒有 sidecar(404)或連不上就當作沒有,照常下載
+        assert!(fetch(format!("{base}/files/b.iso")).await.is_none());
+        assert!(fetch("http://127.0.0.1:9/a.iso".to_string())
+            .await
+            .is_none());
+    }
+
+    #[test]
+    fn split_during_write_does_not_overcount() {
+        let seg = Segment::new(0, MIN_STEAL_BYTES * 4, 0);
+        let segments = Mutex::new(vec![seg.clone()]);
+        // 以舊 end 算出的 chunk 還在寫檔時,另一條連線把這段切走後半
+        let len = (MIN_STEAL_BYTES * 3) as usize;
+        let stolen = split_largest(&segments).unwrap();
+        assert_eq!(stolen.start, MIN_STEAL_BYTES * 2);
+        assert_eq!(seg.commit(0, len), MIN_STEAL_BYTES * 2);
+        assert_eq!(seg.remaining(), Some(0));
+        let written: u64 = segments
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|s| s.written.load(Ordering::Relaxed))
+            .sum();
+        assert_eq!(written, MIN_STEAL_BYTES * 2);
+    }
+
+    #[test]
+    fn failed_source_is_dropped_while_others_remain() {
+        let sources = Mutex::new(vec![
+            Source::new("a".to_string()),
+            Source::new("b".to_string()),
+        ]);
+        let net = DownloadError::Network(String::new());
+        assert!(!drop_source(
+            &sources,
+            "a",
+            &DownloadError::DiskFull(String::new())
+        ));
+        assert!(drop_source(&sources, "a", &net));
+        // 同一來源的另一段稍後也失敗:照樣換到剩下的來源
+        assert!(drop_source(&sources, "a", &net));
+        assert!(!drop_source(&sources, "b", &net));
+        let left: Vec<String> = sources
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|s| s.url.clone())
+            .collect();
+        assert_eq!(left, vec!["b".to_string()]);
+    }
+
+    #[test]
+    fn mirror_must_match_size_range_and_etag() {
+        let probe = |total, etag: Option<&str>| Probe {
+            ranged: true,
+            total,
+            etag: etag.map(str::to_string),
+            last_modified: None,
+            file_name: None,
+            checksum: None,
+        };
+        assert!(probe(100, None).matches(100, true, Some("\"x\"")));
+        assert!(probe(100, Some("\"x\"")).matches(100, true, Some("\"x\"")));
+        assert!(!probe(100, Some("\"y\"")).matches(100, true, Some("\"x\"")));
+        assert!(!probe(99, None).matches(100, true, None));
+        assert!(!probe(0, None).matches(0, true, None));
+        assert!(!probe(100, None).matches(100, false, None));
+    }
+
+    #[test]
+    fn if_range_prefers_strong_etag() {
+        let date = Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string());
+        assert_eq!(
+            if_range_validator(Some("\"v1\"".to_string()), date.clone()).as_deref(),
+            Some("\"v1\"")
+        );
+        assert_eq!(
+            if_range_validator(Some("W/\"v1\"".to_string()), date.clone()),
+            date
+        );
+        assert_eq!(if_range_validator(Some("W/\"v1\"".to_string()), None), None);
+    }
+
     #[test]
     fn filename_from_url_strips_query() {
-        let url = reqwest::Url::parse(
-            "https://example.com/files/movie.mkv?token=secret",
-        )
-        .unwrap();
+        let url = reqwest::Url::parse("https://example.com/files/movie.mkv?token=secret").unwrap();
         assert_eq!(filename_from_url(&url), Some("movie.mkv".to_string()));
     }
 }
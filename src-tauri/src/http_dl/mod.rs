@@ -3,4 +3,5 @@
 // token 過期換連結接續。持久化存 app_data_dir/http_tasks.json。
 pub mod commands;
 pub mod events;
+pub mod headers;
 pub mod manager;
@@ -8,6 +8,7 @@ use std::sync::Mutex;
 use serde::Serialize;
 use tauri::{AppHandle, Emitter, State};
 
+use crate::error::DownloadError;
 use crate::jin::core::{
     build_codes, build_new_content, collect_files, detect_state, extract_env, uncomment_content,
     State as FileState,
@@ -197,7 +198,7 @@ pub async fn jin_preview(
     hall: String,
     suffixes: Vec<String>,
     comment_envs: Vec<String>,
-) -> Result<JinPreview, String> {
+) -> Result<JinPreview, DownloadError> {
     let (hall, codes) = normalize(&hall, &suffixes)?;
     let roots = resolve_roots(&settings, roots);
     let envs: Vec<String> = comment_envs.iter().map(|e| e.trim().to_lowercase()).collect();
@@ -229,7 +230,7 @@ pub async fn jin_apply(
     hall: String,
     suffixes: Vec<String>,
     comment_envs: Vec<String>,
-) -> Result<Vec<JinFilePlan>, String> {
+) -> Result<Vec<JinFilePlan>, DownloadError> {
     let (hall, codes) = normalize(&hall, &suffixes)?;
     let roots = resolve_roots(&settings, roots);
     let envs: Vec<String> = comment_envs.iter().map(|e| e.trim().to_lowercase()).collect();
@@ -262,5 +263,5 @@ pub async fn jin_apply(
     .await
     .map_err(|e| e.to_string())?;
     emit_progress(&app, "done", 0, 0, "");
-    result
+    Ok(result?)
 }
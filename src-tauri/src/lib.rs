@@ -6,16 +6,25 @@ use std::sync::{
 };
 use tauri::{Manager, WindowEvent};
 
-use crate::{db::init_db, state::AppState};
+use crate::{db::init_db, queue::SiteQueue, state::AppState};
 
+pub mod bandwidth;
 pub mod commands;
+pub mod cookies;
 pub mod db;
 pub mod error;
 pub mod download_core;
+pub mod hosts;
 pub mod http_dl;
 pub mod jin;
+pub mod library;
+pub mod metadata;
 pub mod monitor;
 pub mod providers;
+pub mod proxy;
+pub mod queue;
+pub mod retry;
+pub mod schedule;
 pub mod settings;
 pub mod state;
 pub mod torrent;
@@ -38,15 +47,28 @@ pub fn run() {
             let s = settings_state.get();
 
             let db = init_db(app.handle())?;
-            let state = AppState::new(db, Arc::clone(&monitor_running));
+            // 網站與直鏈共用的登入 cookie（匯入的 cookies.txt + 伺服器回的 Set-Cookie）
+            let cookies = Arc::new(cookies::CookieJar::load(app_data_dir.join("cookies.json")));
+            let state = AppState::new(db, Arc::clone(&monitor_running), cookies.clone());
             state
-                .bandwidth_limit_bps
-                .store(s.bandwidth_limit_kbps * 1024, Ordering::Relaxed);
+                .bandwidth
+                .configure(schedule::current(&s).bandwidth_limit_kbps * 1024, s.bandwidth_shares);
+            let bandwidth = state.bandwidth.clone();
+            state.hosts.configure(s.host_limits.clone());
+            let hosts = state.hosts.clone();
+            state.proxy.configure(s.proxy.clone());
+            let proxy = state.proxy.clone();
             state
                 .monitor_paused
                 .store(!s.monitor_clipboard, Ordering::Relaxed);
             app.manage(state);
             app.manage(settings_state);
+            // 爬取規則：內建預設 + scrape_rules.json 覆寫
+            app.manage(providers::rules::RulesState::load(&app_data_dir));
+
+            // 網站下載佇列：接回上次未完成的 queued 任務
+            app.manage(SiteQueue::default());
+            queue::pump(app.handle());
 
             // BT 引擎背景初始化 — 失敗（如 port 衝突）只讓 BT 分頁失效,不擋 app 啟動
             // （spawn_init 讀 SettingsState.bt，須在 manage 之後）
@@ -55,12 +77,26 @@ pub fn run() {
             torrent::events::spawn_stats_task(app.handle().clone());
 
             // HTTP 直鏈下載（獨立於 BT 引擎與網站下載）
-            let http_mgr = http_dl::manager::HttpManager::load(app_data_dir.join("http_tasks.json"));
+            let http_mgr = http_dl::manager::HttpManager::load(
+                app_data_dir.join("http_tasks.json"),
+                bandwidth,
+                hosts,
+                cookies,
+                proxy,
+            );
+            http_mgr.set_retry_policy(s.retry.clone());
+            http_mgr.set_default_segments(s.download_segments);
+            http_mgr.set_header_profiles(s.header_profiles.clone());
+            http_mgr.set_app_handle(app.handle().clone());
             // 上次關閉時仍在跑的任務自動續傳
             http_mgr.resume_interrupted();
             app.manage(http_mgr);
             http_dl::events::spawn_http_stats_task(app.handle().clone());
 
+            // 時段排程（頻寬切換、暫停/開跑所有引擎），須在各引擎 manage 之後
+            app.manage(schedule::ScheduleState::default());
+            schedule::spawn(app.handle().clone());
+
             // 啟動剪貼簿監控邏輯
             let app_handle = app.handle().clone();
             monitor::start_clipboard_monitor(app_handle, Arc::clone(&monitor_running));
@@ -92,17 +128,26 @@ pub fn run() {
             }
         })
         .invoke_handler(tauri::generate_handler![
-            commands::network::download_with_progress,
+            commands::network::enqueue_site_tasks,
+            commands::network::pause_site_queue,
+            commands::network::pause_download,
+            commands::network::cancel_download,
+            commands::network::get_site_queue_state,
             commands::common::read_clipboard,
             commands::common::load_all_tasks,
             commands::common::remove_task,
             commands::common::remove_all_tasks,
-            commands::common::cancel_download,
             commands::common::update_task_status,
             commands::common::get_app_settings,
             commands::common::save_app_settings,
+            commands::common::get_schedule_profile,
             commands::common::reorder_tasks,
             commands::common::add_url_manually,
+            commands::common::get_scrape_rules,
+            commands::common::test_scrape_rules,
+            commands::common::import_cookies,
+            commands::common::list_cookies,
+            commands::common::clear_cookies,
             torrent::commands::add_magnet,
             torrent::commands::remove_pending,
             torrent::commands::list_torrents,
@@ -115,7 +160,10 @@ pub fn run() {
             http_dl::commands::add_http_download,
             http_dl::commands::pause_http_download,
             http_dl::commands::resume_http_download,
+            http_dl::commands::set_http_speed_limit,
             http_dl::commands::update_http_url,
+            http_dl::commands::add_http_mirror,
+            http_dl::commands::set_http_headers,
             http_dl::commands::delete_http_download,
             jin::commands::jin_preview,
             jin::commands::jin_apply,
@@ -0,0 +1,279 @@
+// src/library.rs
+// 已完成下載的索引，網站、直鏈、BT 共用：來源網址、站台作品 ID、大小、內容雜湊。
+// 加任務前先查 — 改過檔名、換過存放目錄都認得出來。索引到的檔案不在原處時，
+// 到各下載資料夾找大小與雜湊相同的檔案，找到就更新路徑；找不到的紀錄保留，之後搬回來仍認得。
+// 直鏈探測到大小後再以大小 + ETag / 雜湊查一次，換了網址的同一檔案也不重抓。
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::db;
+use crate::providers;
+use crate::settings::SettingsState;
+
+/// 重新找檔案時往下掃的資料夾層數（涵蓋每站子資料夾）
+const RESCAN_DEPTH: usize = 2;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LibraryEntry {
+    pub id: i64,
+    /// site / http / bt
+    pub kind: String,
+    pub source_url: String,
+    /// 站台名（BT 為 "bt"），直鏈為空
+    pub site: String,
+    /// 站台作品 ID（BT 為 info hash）
+    pub gallery_id: String,
+    pub title: String,
+    /// 存檔路徑（BT 為任務資料夾）
+    pub file_path: String,
+    pub size: i64,
+    /// 單檔的 SHA-256（hex），資料夾為空
+    pub sha256: String,
+    pub completed_at: i64,
+    /// 直鏈完成時伺服器回的 ETag，其他為空
+    pub etag: String,
+}
+
+/// BT 任務在索引中的來源網址，以 info hash 為準（tracker、dn 不同仍視為同一個）
+pub fn magnet_key(info_hash: &str) -> String {
+    format!("magnet:?xt=urn:btih:{}", info_hash.to_ascii_lowercase())
+}
+
+/// 查索引：來源網址相同，或同站同作品 ID
+pub fn find(
+    app: &AppHandle,
+    source_url: &str,
+    site: &str,
+    gallery_id: &str,
+) -> Option<LibraryEntry> {
+    let entries = match db::library_find(app, source_url, site, gallery_id) {
+        Ok(entries) => entries,
+        Err(e) => {
+            tracing::error!("Library: 查詢失敗: {:?}", e);
+            return None;
+        }
+    };
+    first_available(app, entries)
+}
+
+/// 以內容查索引：大小相同，且 ETag 或 SHA-256 相同（直鏈探測後、開始寫檔前用）
+pub fn find_content(
+    app: &AppHandle,
+    size: u64,
+    etag: Option<&str>,
+    sha256: Option<&str>,
+) -> Option<LibraryEntry> {
+    let (etag, sha256) = (etag.unwrap_or_default(), sha256.unwrap_or_default());
+    if size == 0 || (etag.is_empty() && sha256.is_empty()) {
+        return None;
+    }
+    match db::library_find_content(app, size as i64, sha256, etag) {
+        Ok(entries) => first_available(app, entries),
+        Err(e) => {
+            tracing::error!("Library: 查詢失敗: {:?}", e);
+            None
+        }
+    }
+}
+
+/// 依序取第一筆還找得到檔案的紀錄；不在原處的改到下載資料夾以大小 + 雜湊重新找
+fn first_available(app: &AppHandle, entries: Vec<LibraryEntry>) -> Option<LibraryEntry> {
+    let mut roots: Option<Vec<PathBuf>> = None;
+    for mut entry in entries {
+        if Path::new(&entry.file_path).exists() {
+            return Some(entry);
+        }
+        if entry.sha256.is_empty() || entry.size <= 0 {
+            continue;
+        }
+        let roots = roots.get_or_insert_with(|| download_roots(app));
+        let mut dirs: Vec<PathBuf> = Path::new(&entry.file_path)
+            .parent()
+            .map(Path::to_path_buf)
+            .into_iter()
+            .collect();
+        dirs.extend(roots.iter().cloned());
+        if let Some(found) = locate(&dirs, entry.size as u64, &entry.sha256) {
+            let found = found.to_string_lossy().into_owned();
+            tracing::info!("Library: {} 已移到 {}", entry.file_path, found);
+            if let Err(e) = db::library_update_path(app, entry.id, &found) {
+                tracing::error!("Library: 更新路徑失敗: {:?}", e);
+            }
+            entry.file_path = found;
+            return Some(entry);
+        }
+    }
+    None
+}
+
+/// 各引擎的下載資料夾（設定值，空則系統下載資料夾）
+fn download_roots(app: &AppHandle) -> Vec<PathBuf> {
+    let settings = app.state::<SettingsState>().get();
+    let mut roots: Vec<PathBuf> = [
+        settings.site_download_dir.trim(),
+        settings.http_default_dir.trim(),
+        settings.bt.default_download_dir.trim(),
+    ]
+    .into_iter()
+    .filter(|d| !d.is_empty())
+    .map(PathBuf::from)
+    .collect();
+    roots.extend(app.path().download_dir().ok());
+    roots.dedup();
+    roots
+}
+
+/// 在 dirs（往下 RESCAN_DEPTH 層）找大小與 SHA-256 都相同的檔案；只對大小相符的檔案算雜湊
+fn locate(dirs: &[PathBuf], size: u64, sha256: &str) -> Option<PathBuf> {
+    let mut pending: Vec<(PathBuf, usize)> = dirs.iter().map(|d| (d.clone(), 0)).collect();
+    let mut seen = std::collections::HashSet::new();
+    while let Some((dir, depth)) = pending.pop() {
+        if !seen.insert(dir.clone()) {
+            continue;
+        }
+        let Ok(read) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for item in read.flatten() {
+            let path = item.path();
+            let Ok(meta) = item.metadata() else {
+                continue;
+            };
+            if meta.is_dir() {
+                if depth < RESCAN_DEPTH {
+                    pending.push((path, depth + 1));
+                }
+            } else if meta.len() == size
+                && sha256_file(&path).is_ok_and(|h| h.eq_ignore_ascii_case(sha256))
+            {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+/// 網站作品網址查索引（同時比對站台作品 ID）
+pub fn find_site(app: &AppHandle, url: &str) -> Option<LibraryEntry> {
+    let site = providers::from_url(url)
+        .map(|p| p.name())
+        .unwrap_or_default();
+    let id = providers::work_id(app, url).unwrap_or_default();
+    find(app, url, site, &id)
+}
+
+/// 推 "library-hit"：自動加入（剪貼簿）、直鏈探測或剛下載完的內容撞到已下載過的檔案，前端顯示既有檔案位置
+pub fn emit_hit(app: &AppHandle, entry: &LibraryEntry) {
+    let _ = app.emit("library-hit", entry);
+}
+
+/// 背景寫入一筆完成紀錄：單檔會算 SHA-256，內容與既有檔案相同時記警告並通知前端
+pub fn spawn_record(app: AppHandle, mut entry: LibraryEntry) {
+    tauri::async_runtime::spawn(async move {
+        let path = entry.file_path.clone();
+        let hashed =
+            tokio::task::spawn_blocking(move || -> std::io::Result<Option<(u64, String)>> {
+                let path = Path::new(&path);
+                if !path.is_file() {
+                    return Ok(None);
+                }
+                Ok(Some((std::fs::metadata(path)?.len(), sha256_file(path)?)))
+            })
+            .await;
+        match hashed {
+            Ok(Ok(Some((size, hash)))) => {
+                entry.size = size as i64;
+                entry.sha256 = hash;
+            }
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => tracing::warn!("Library: 計算雜湊失敗 {}: {}", entry.file_path, e),
+            Err(e) => tracing::warn!("Library: 計算雜湊失敗 {}: {}", entry.file_path, e),
+        }
+        entry.completed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or(0);
+
+        if !entry.sha256.is_empty() {
+            if let Ok(same) = db::library_find_by_hash(&app, &entry.sha256, entry.size) {
+                let others = same
+                    .into_iter()
+                    .filter(|o| o.file_path != entry.file_path)
+                    .collect();
+                if let Some(other) = first_available(&app, others) {
+                    tracing::warn!(
+                        "Library: {} 與既有檔案內容相同: {}",
+                        entry.file_path,
+                        other.file_path
+                    );
+                    emit_hit(&app, &other);
+                }
+            }
+        }
+        if let Err(e) = db::library_insert(&app, &entry) {
+            tracing::error!("Library: 寫入失敗: {:?}", e);
+        }
+    });
+}
+
+fn sha256_file(path: &Path) -> std::io::Result<String> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha256_file_hashes_content() {
+        let dir = std::env::temp_dir().join(format!("library-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("abc.bin");
+        std::fs::write(&path, b"abc").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn locate_finds_moved_file_by_size_and_hash() {
+        let dir = std::env::temp_dir().join(format!("library-locate-{}", std::process::id()));
+        let nested = dir.join("site").join("renamed");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(dir.join("other.bin"), b"xyz").unwrap();
+        std::fs::write(nested.join("moved.bin"), b"abc").unwrap();
+        let abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+        assert_eq!(
+            locate(std::slice::from_ref(&dir), 3, abc),
+            Some(nested.join("moved.bin"))
+        );
+        assert_eq!(locate(std::slice::from_ref(&dir), 4, abc), None);
+        // 資料夾不存在時略過
+        assert_eq!(locate(&[dir.join("missing")], 3, abc), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn magnet_key_ignores_hash_case() {
+        assert_eq!(magnet_key("ABCDEF"), "magnet:?xt=urn:btih:abcdef");
+    }
+}
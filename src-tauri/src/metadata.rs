@@ -0,0 +1,270 @@
+// src/metadata.rs
+// 完成的網站下載附上作品資訊：ComicInfo.xml 寫進 ZIP（閱讀器可讀），或在 ZIP 旁放同名 .json。
+// 來源是爬取時存下的 ClipboardPayload（標題、來源網址、封面、標籤、頁數）。
+
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::error::DownloadError;
+use crate::providers::ClipboardPayload;
+
+const COMIC_INFO: &str = "ComicInfo.xml";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveMetadata {
+    /// 不附加
+    #[default]
+    None,
+    /// ComicInfo.xml 寫進 ZIP
+    ComicInfo,
+    /// ZIP 旁的同名 .json
+    Sidecar,
+}
+
+/// 依設定替完成的檔案附上作品資訊（阻塞 IO，呼叫端放 spawn_blocking）
+pub fn write(
+    mode: ArchiveMetadata,
+    task: &ClipboardPayload,
+    save_path: &Path,
+) -> Result<(), DownloadError> {
+    let downloaded_at = Local::now().timestamp();
+    match mode {
+        ArchiveMetadata::None => Ok(()),
+        ArchiveMetadata::ComicInfo => {
+            inject_comic_info(save_path, &comic_info_xml(task, downloaded_at))
+        }
+        ArchiveMetadata::Sidecar => {
+            let body = serde_json::to_string_pretty(&sidecar_json(task, downloaded_at))
+                .map_err(|e| DownloadError::Other(e.to_string()))?;
+            std::fs::write(sidecar_path(save_path), body)?;
+            Ok(())
+        }
+    }
+}
+
+/// 今天的本地日期 `YYYY-MM-DD`（檔名樣板 `{date}` 用，與排程同樣依本地時間）
+pub fn today() -> String {
+    format_day(Local::now().date_naive())
+}
+
+/// `title.zip` → `title.json`
+pub fn sidecar_path(save_path: &Path) -> PathBuf {
+    save_path.with_extension("json")
+}
+
+/// 把 ComicInfo.xml 附加進既有 ZIP；站台原檔已有的話保留原檔不覆寫
+fn inject_comic_info(zip_path: &Path, xml: &str) -> Result<(), DownloadError> {
+    let file = OpenOptions::new().read(true).write(true).open(zip_path)?;
+    if zip::ZipArchive::new(&file)?
+        .index_for_name(COMIC_INFO)
+        .is_some()
+    {
+        tracing::debug!("Metadata: {} 已含 {}，略過", zip_path.display(), COMIC_INFO);
+        return Ok(());
+    }
+    let mut zip = zip::ZipWriter::new_append(file)?;
+    let options =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    zip.start_file(COMIC_INFO, options)?;
+    zip.write_all(xml.as_bytes())?;
+    zip.finish()?;
+    Ok(())
+}
+
+/// tags 為 `{類型}:{名稱}`，取指定類型的名稱
+fn tags_of<'a>(tags: &'a [String], kind: &str) -> Vec<&'a str> {
+    tags.iter()
+        .filter_map(|t| t.split_once(':'))
+        .filter(|(k, _)| *k == kind)
+        .map(|(_, v)| v)
+        .collect()
+}
+
+/// 一般標籤：`tag:` 類型與沒有類型前綴的
+fn plain_tags(tags: &[String]) -> Vec<&str> {
+    tags.iter()
+        .filter_map(|t| match t.split_once(':') {
+            Some(("tag", v)) => Some(v),
+            Some(_) => None,
+            None => Some(t.as_str()),
+        })
+        .collect()
+}
+
+fn language_iso(tags: &[String]) -> Option<&'static str> {
+    tags_of(tags, "language").into_iter().find_map(|l| match l {
+        "japanese" => Some("ja"),
+        "english" => Some("en"),
+        "chinese" => Some("zh"),
+        "korean" => Some("ko"),
+        _ => None,
+    })
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// unix 秒數 → 本地日期 `YYYY-MM-DD`
+fn format_date(secs: i64) -> String {
+    let at = DateTime::from_timestamp(secs, 0).unwrap_or_default();
+    format_day(at.with_timezone(&Local).date_naive())
+}
+
+fn format_day(day: NaiveDate) -> String {
+    day.format("%Y-%m-%d").to_string()
+}
+
+fn comic_info_xml(task: &ClipboardPayload, downloaded_at: i64) -> String {
+    let mut fields: Vec<(&str, String)> = vec![("Title", task.title.clone())];
+    for (field, kind) in [
+        ("Series", "parody"),
+        ("Writer", "artist"),
+        ("Penciller", "artist"),
+        ("Teams", "group"),
+        ("Characters", "character"),
+        ("Genre", "category"),
+    ] {
+        let names = tags_of(&task.tags, kind);
+        if !names.is_empty() {
+            fields.push((field, names.join(", ")));
+        }
+    }
+    let tags = plain_tags(&task.tags);
+    if !tags.is_empty() {
+        fields.push(("Tags", tags.join(", ")));
+    }
+    if let Some(lang) = language_iso(&task.tags) {
+        fields.push(("LanguageISO", lang.to_string()));
+    }
+    if task.page_count > 0 {
+        fields.push(("PageCount", task.page_count.to_string()));
+    }
+    fields.push(("Web", task.url.clone()));
+    fields.push((
+        "Notes",
+        format!(
+            "Downloaded {} from {}",
+            format_date(downloaded_at),
+            task.url
+        ),
+    ));
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <ComicInfo xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n",
+    );
+    for (name, value) in fields {
+        xml.push_str(&format!("  <{0}>{1}</{0}>\n", name, escape_xml(&value)));
+    }
+    xml.push_str("</ComicInfo>\n");
+    xml
+}
+
+fn sidecar_json(task: &ClipboardPayload, downloaded_at: i64) -> serde_json::Value {
+    json!({
+        "title": task.title,
+        "source_url": task.url,
+        "cover": task.image,
+        "tags": task.tags,
+        "page_count": task.page_count,
+        "file_size": task.file_size,
+        "downloaded_at": downloaded_at,
+        "downloaded_date": format_date(downloaded_at),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::io::Read;
+
+    fn task() -> ClipboardPayload {
+        ClipboardPayload {
+            url: "https://nhentai.net/g/1/".to_string(),
+            title: "A & <B>".to_string(),
+            tags: vec![
+                "artist:foo".to_string(),
+                "language:japanese".to_string(),
+                "tag:full color".to_string(),
+                "plain".to_string(),
+            ],
+            page_count: 12,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dates_use_local_time() {
+        assert_eq!(
+            format_day(NaiveDate::from_ymd_opt(2000, 2, 29).unwrap()),
+            "2000-02-29"
+        );
+        // 本地時間深夜：UTC 可能已是隔天（或前一天），仍取本地日期
+        let late = Local.with_ymd_and_hms(2026, 10, 18, 23, 30, 0).unwrap();
+        assert_eq!(format_date(late.timestamp()), "2026-10-18");
+        let early = Local.with_ymd_and_hms(2026, 10, 18, 0, 30, 0).unwrap();
+        assert_eq!(format_date(early.timestamp()), "2026-10-18");
+    }
+
+    #[test]
+    fn comic_info_maps_tags_and_escapes() {
+        let xml = comic_info_xml(&task(), 0);
+        assert!(xml.contains("<Title>A &amp; &lt;B&gt;</Title>"));
+        assert!(xml.contains("<Writer>foo</Writer>"));
+        assert!(xml.contains("<Tags>full color, plain</Tags>"));
+        assert!(xml.contains("<LanguageISO>ja</LanguageISO>"));
+        assert!(xml.contains("<PageCount>12</PageCount>"));
+        assert!(xml.contains("<Notes>Downloaded 1970-01-01 from https://nhentai.net/g/1/</Notes>"));
+        assert!(!xml.contains("<Series>"));
+    }
+
+    #[test]
+    fn inject_appends_once() {
+        let dir = std::env::temp_dir().join(format!("metadata-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        zip.start_file(
+            "001.jpg",
+            zip::write::SimpleFileOptions::default()
+                .compression_method(zip::CompressionMethod::Stored),
+        )
+        .unwrap();
+        zip.write_all(b"img").unwrap();
+        zip.finish().unwrap();
+
+        inject_comic_info(&path, "<ComicInfo/>").unwrap();
+        inject_comic_info(&path, "<ComicInfo>second</ComicInfo>").unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(archive.len(), 2);
+        let mut xml = String::new();
+        archive
+            .by_name(COMIC_INFO)
+            .unwrap()
+            .read_to_string(&mut xml)
+            .unwrap();
+        assert_eq!(xml, "<ComicInfo/>");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,9 +1,10 @@
 use crate::db;
-use crate::providers::Site;
+use crate::library;
+use crate::providers;
+use crate::queue;
 use crate::state::AppState;
+use crate::utils;
 use clipboard::{ClipboardContext, ClipboardProvider};
-use regex::Regex;
-use sanitize_filename::sanitize;
 use std::collections::HashMap;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
@@ -78,6 +79,9 @@ pub fn start_clipboard_monitor(app_handle: AppHandle, running: Arc<AtomicBool>)
                                         let name = v.get("name").cloned();
                                         let _ = handle.emit("new-magnet-added", name);
                                     }
+                                    Ok(v) if v.get("already_downloaded").is_some() => {
+                                        let _ = handle.emit("library-hit", &v["already_downloaded"]);
+                                    }
                                     Ok(_) => {}
                                     Err(e) => {
                                         tracing::error!("Magnet add error: {}", e);
@@ -89,76 +93,62 @@ pub fn start_clipboard_monitor(app_handle: AppHandle, running: Arc<AtomicBool>)
                                 }
                             });
                         }
-                    } else if let Ok(site) = Site::from_url(&current_content) {
-                        if let Ok(normalized_url) = site.validate(&current_content) {
-                            // 節流：30 秒內同一 URL 不重複抓取
-                            let now = Instant::now();
-                            let should_fetch = {
-                                let mut map = recent_urls.lock().unwrap();
-                                map.retain(|_, t| now.duration_since(*t).as_secs() < URL_THROTTLE_SECS);
-                                if map.contains_key(&normalized_url) {
-                                    false
-                                } else {
-                                    map.insert(normalized_url.clone(), now);
-                                    true
-                                }
-                            };
+                    } else if let Ok((site, normalized_url)) =
+                        providers::normalize_url(&app_handle, &current_content)
+                    {
+                        // 節流：30 秒內同一 URL 不重複抓取
+                        let now = Instant::now();
+                        let should_fetch = {
+                            let mut map = recent_urls.lock().unwrap();
+                            map.retain(|_, t| now.duration_since(*t).as_secs() < URL_THROTTLE_SECS);
+                            if map.contains_key(&normalized_url) {
+                                false
+                            } else {
+                                map.insert(normalized_url.clone(), now);
+                                true
+                            }
+                        };
 
-                            if should_fetch {
-                                tracing::info!("Monitor: 偵測到有效 {} 連結: {}", site.to_string(), normalized_url);
+                        if should_fetch {
+                            tracing::info!("Monitor: 偵測到有效 {} 連結: {}", site.name(), normalized_url);
 
-                                let handle = app_handle.clone();
-                                let url_to_fetch = normalized_url.clone();
-                                let recent_urls = Arc::clone(&recent_urls);
+                            let handle = app_handle.clone();
+                            let url_to_fetch = normalized_url.clone();
+                            let recent_urls = Arc::clone(&recent_urls);
 
-                                // 使用 Tauri 內建的 runtime 執行異步抓取
-                                tauri::async_runtime::spawn(async move {
-                                    match site.fetch_details(&handle, &url_to_fetch).await {
-                                        Ok(payload) => {
-                                            // 檢查下載目錄是否已有同名檔案（含 _N 後綴變體）
-                                            let already_exists = handle
-                                                .path()
-                                                .download_dir()
-                                                .ok()
-                                                .and_then(|dir| std::fs::read_dir(dir).ok())
-                                                .map(|entries| {
-                                                    let prefix = sanitize(&payload.title);
-                                                    let exact = format!("{}.zip", prefix);
-                                                    // 精確比對 {prefix}_N.zip，避免誤擋標題為彼此前綴的不同作品
-                                                    let numbered = Regex::new(&format!(
-                                                        r"^{}_\d+\.zip$",
-                                                        regex::escape(&prefix)
-                                                    ))
-                                                    .ok();
-                                                    entries.filter_map(|e| e.ok()).any(|e| {
-                                                        let name = e.file_name();
-                                                        let name = name.to_string_lossy();
-                                                        name == exact.as_str()
-                                                            || numbered.as_ref().is_some_and(|re| re.is_match(&name))
-                                                    })
-                                                })
-                                                .unwrap_or(false);
+                            // 使用 Tauri 內建的 runtime 執行異步抓取
+                            tauri::async_runtime::spawn(async move {
+                                // 已下載過（不論檔名或目錄）→ 告知既有檔案位置，不再抓取
+                                if let Some(entry) = library::find_site(&handle, &url_to_fetch) {
+                                    library::emit_hit(&handle, &entry);
+                                    return;
+                                }
+                                match site.fetch_details(&handle, &url_to_fetch).await {
+                                    Ok(payload) => {
+                                        // 依目錄設定與檔名樣板檢查是否已有同名檔案（含 _N 後綴變體）
+                                        let already_exists = queue::save_target(&handle, &payload)
+                                            .map(|(dir, stem)| utils::fs::has_saved_copy(&dir, &stem))
+                                            .unwrap_or(false);
 
-                                            if already_exists {
-                                                return;
-                                            }
+                                        if already_exists {
+                                            return;
+                                        }
 
-                                            match db::insert_task(&handle, &payload) {
-                                                Ok(true) => {
-                                                    let _ = handle.emit("new-valid-url-payload", payload);
-                                                }
-                                                Ok(false) => {}
-                                                Err(e) => tracing::error!("DB Error: {:?}", e),
+                                        match db::insert_task(&handle, &payload) {
+                                            Ok(true) => {
+                                                let _ = handle.emit("new-valid-url-payload", payload);
                                             }
-                                        }
-                                        Err(e) => {
-                                            tracing::error!("Fetch Error: {}", e);
-                                            // 抓取失敗，移出節流名單讓使用者可立即重試
-                                            recent_urls.lock().unwrap().remove(&url_to_fetch);
+                                            Ok(false) => {}
+                                            Err(e) => tracing::error!("DB Error: {:?}", e),
                                         }
                                     }
-                                });
-                            }
+                                    Err(e) => {
+                                        tracing::error!("Fetch Error: {}", e);
+                                        // 抓取失敗，移出節流名單讓使用者可立即重試
+                                        recent_urls.lock().unwrap().remove(&url_to_fetch);
+                                    }
+                                }
+                            });
                         }
                     }
                 }
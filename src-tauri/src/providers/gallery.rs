@@ -0,0 +1,234 @@
+// src/providers/gallery.rs
+// 逐頁圖片下載再本地打包成 .zip：沒有整包檔案的站台（nhentai），
+// 或原始 ZIP 連結失效時的備援（wnacg）共用。
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use futures_util::{stream, StreamExt};
+use tauri::{AppHandle, Emitter};
+
+use crate::{
+    bandwidth::Throttle,
+    download_core::DownloadManager,
+    error::DownloadError,
+    http_dl::manager::{Segment, UNBOUNDED},
+    providers::{DownloadProgress, PartState},
+    utils,
+};
+
+/// 同時下載的頁數
+pub const PAGE_CONCURRENCY: usize = 4;
+
+/// zip 內第 n 頁（1 起算）的檔名：補零讓閱讀器照順序排，副檔名取自圖片網址
+pub fn page_file_name(n: usize, total: usize, url: &str) -> String {
+    let ext = url
+        .rsplit('/')
+        .next()
+        .and_then(|name| name.split(['?', '#']).next())
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase())
+        .filter(|ext| !ext.is_empty() && ext.len() <= 4)
+        .unwrap_or_else(|| "jpg".to_string());
+    format!("{:0width$}.{}", n, ext, width = total.to_string().len())
+}
+
+/// 逐頁下載到 `{save_path}.part/` 資料夾，全部到齊後打包成 save_path（.zip）。
+/// 已下載的頁面檔保留在資料夾內，暫停或失敗後再開跑只補缺頁。
+/// pages 為（下載網址, zip 內檔名）。
+#[allow(clippy::too_many_arguments)]
+pub async fn download_images(
+    client: &reqwest::Client,
+    app_handle: &AppHandle,
+    source_url: &str, // 原始網頁網址 (用於進度事件辨識)
+    pages: &[(String, String)],
+    save_path: &Path,
+    part: &PartState,
+    cancelled: &AtomicBool,
+    throttle: &Throttle,
+) -> Result<(), DownloadError> {
+    let staging = utils::fs::part_path(save_path);
+    if staging.is_file() {
+        // 整包下載留下的 .part 檔，改走逐頁模式時用不到
+        tokio::fs::remove_file(&staging).await?;
+    }
+    tokio::fs::create_dir_all(&staging).await?;
+
+    // 分段配置只用來回報已寫入位元組數（單段、長度未知）
+    part.replace(vec![Segment::new(0, UNBOUNDED, 0)]);
+    let written = part.segments.lock().unwrap()[0].written.clone();
+
+    let mut manager = DownloadManager::new();
+    let mut started = false;
+    let mut last_emit = Instant::now();
+    download_pages(
+        client,
+        pages,
+        &staging,
+        &written,
+        cancelled,
+        throttle,
+        |done, total| {
+            if !started {
+                manager.start_download(0, written.load(Ordering::Relaxed));
+                started = true;
+            }
+            if done == total || last_emit.elapsed() >= Duration::from_millis(250) {
+                emit_progress(app_handle, source_url, &manager, &written, done, total);
+                last_emit = Instant::now();
+            }
+        },
+    )
+    .await?;
+
+    let names: Vec<String> = pages.iter().map(|(_, name)| name.clone()).collect();
+    let staging_dir = staging.clone();
+    let dest = save_path.to_path_buf();
+    tokio::task::spawn_blocking(move || pack_zip(&staging_dir, &names, &dest))
+        .await
+        .map_err(|e| DownloadError::Other(format!("打包失敗: {}", e)))??;
+    tokio::fs::remove_dir_all(&staging).await?;
+
+    Ok(())
+}
+
+/// 把缺的頁面並行下載進 dir。已存在的頁面檔視為完成（寫檔先落 .tmp 再改名）。
+/// on_progress(已完成頁數, 總頁數) 於開始時與每頁完成後呼叫。
+pub async fn download_pages(
+    client: &reqwest::Client,
+    pages: &[(String, String)],
+    dir: &Path,
+    written: &AtomicU64,
+    cancelled: &AtomicBool,
+    throttle: &Throttle,
+    mut on_progress: impl FnMut(usize, usize),
+) -> Result<(), DownloadError> {
+    let total = pages.len();
+    let mut pending = Vec::new();
+    let mut existing_bytes = 0;
+    for (url, name) in pages {
+        let path = dir.join(name);
+        match tokio::fs::metadata(&path).await {
+            Ok(meta) => existing_bytes += meta.len(),
+            Err(_) => pending.push((url.clone(), path)),
+        }
+    }
+    written.store(existing_bytes, Ordering::Relaxed);
+    let mut done = total - pending.len();
+    on_progress(done, total);
+
+    let mut results = stream::iter(pending.into_iter().map(|(url, path)| {
+        let client = client.clone();
+        async move { fetch_page(&client, &url, path, cancelled).await }
+    }))
+    .buffer_unordered(PAGE_CONCURRENCY);
+
+    while let Some(result) = results.next().await {
+        let bytes = result?;
+        written.fetch_add(bytes, Ordering::Relaxed);
+        done += 1;
+        on_progress(done, total);
+
+        // 以頁為單位限速：超前就等，等待時不再拉新頁
+        if !throttle.acquire(bytes, cancelled).await {
+            return Err(DownloadError::Cancelled);
+        }
+    }
+
+    if cancelled.load(Ordering::Relaxed) {
+        return Err(DownloadError::Cancelled);
+    }
+    Ok(())
+}
+
+/// 下載單頁到 path，回傳位元組數
+async fn fetch_page(
+    client: &reqwest::Client,
+    url: &str,
+    path: PathBuf,
+    cancelled: &AtomicBool,
+) -> Result<u64, DownloadError> {
+    if cancelled.load(Ordering::Relaxed) {
+        return Err(DownloadError::Cancelled);
+    }
+    let res = client.get(url).send().await?;
+    if !res.status().is_success() {
+        return Err(DownloadError::from_response(&res));
+    }
+    let bytes = res.bytes().await?;
+
+    let tmp = path.with_extension("tmp");
+    tokio::fs::write(&tmp, &bytes).await?;
+    tokio::fs::rename(&tmp, &path).await?;
+    Ok(bytes.len() as u64)
+}
+
+/// 依 names 順序把 dir 內的頁面打包成 dest。圖片本身已壓縮，用 Stored 不再壓一次。
+/// 先寫到 dir 內的暫存檔再改名，打包中斷不會留下看似完整的 zip。
+pub fn pack_zip(dir: &Path, names: &[String], dest: &Path) -> Result<(), DownloadError> {
+    let tmp = dir.join(".archive.zip");
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&tmp)?);
+    let options =
+        zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+    for name in names {
+        zip.start_file(name.as_str(), options)?;
+        std::io::copy(&mut std::fs::File::open(dir.join(name))?, &mut zip)?;
+    }
+    zip.finish()?;
+    std::fs::rename(&tmp, dest)?;
+    Ok(())
+}
+
+/// 進度以頁數計；速度仍以位元組計，剩餘時間依本次的每頁平均耗時估算
+fn emit_progress(
+    app_handle: &AppHandle,
+    source_url: &str,
+    manager: &DownloadManager,
+    written: &AtomicU64,
+    done: usize,
+    total: usize,
+) {
+    let metrics = manager.calculate_metrics(written.load(Ordering::Relaxed), 0);
+    let progress = if total > 0 {
+        done as f64 / total as f64 * 100.0
+    } else {
+        -1.0
+    };
+    let remaining = (total - done) as f64;
+    // 還估不出來（尚無速度或還沒完成任何一頁）送 0，前端不顯示；JSON 表示不了 INFINITY
+    let time_remaining_secs = if metrics.speed_bytes_per_sec > 0.0 && done > 0 {
+        remaining * metrics.downloaded_size as f64 / done as f64 / metrics.speed_bytes_per_sec
+    } else {
+        0.0
+    };
+    if let Err(e) = app_handle.emit(
+        "download_progress",
+        DownloadProgress {
+            url: source_url.to_string(),
+            progress,
+            speed_bytes_per_sec: metrics.speed_bytes_per_sec,
+            time_remaining_secs,
+        },
+    ) {
+        tracing::warn!("進度事件 emit 失敗: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_file_name_pads_and_keeps_extension() {
+        assert_eq!(
+            page_file_name(3, 120, "https://img.example/data/a/b/003.PNG"),
+            "003.png"
+        );
+        assert_eq!(page_file_name(7, 9, "//img.example/x/7.webp?v=1"), "7.webp");
+        assert_eq!(
+            page_file_name(12, 40, "https://img.example/view/12"),
+            "12.jpg"
+        );
+    }
+}
@@ -1,21 +1,26 @@
-use std::fmt;
 use std::path::PathBuf;
-use std::sync::{atomic::{AtomicBool, AtomicU64}, Arc};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
 
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tauri::AppHandle;
 
+use crate::bandwidth::Throttle;
 use crate::error::DownloadError;
+use crate::http_dl::manager::{PersistedSegment, Segment};
+use crate::retry::Attempt;
 
+pub mod gallery;
 pub mod nhentai;
+pub mod rules;
 pub mod wnacg;
 
-pub enum Site {
-    Wnacg,
-    NHentai,
-}
+use rules::SiteRules;
 
-#[derive(Clone, Serialize, Deserialize)]
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct ClipboardPayload {
     pub url: String,
     pub title: String,
@@ -25,6 +30,95 @@ pub struct ClipboardPayload {
     pub file_size: i64, // 檔案位元組數，-1 = 未知（探測失敗或站台未回報）
     pub created_at: i64,
     pub db_status: String,
+    /// 完成後的存檔路徑，未完成為空字串
+    #[serde(default)]
+    pub save_path: String,
+    /// save_path 對應 .part 已寫入的位元組數（續傳起點），完成後歸零
+    #[serde(default)]
+    pub downloaded_bytes: i64,
+    /// .part 分段配置 JSON（PartState），前端不需要
+    #[serde(default, skip_serializing)]
+    pub segments: String,
+    /// 頁數，0 = 未知
+    #[serde(default)]
+    pub page_count: i64,
+    /// 標籤，格式 `{類型}:{名稱}`（站台未提供則為空）
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 下載失敗紀錄（含自動重試），新的在後
+    #[serde(default)]
+    pub attempts: Vec<Attempt>,
+    /// 原始 ZIP 連結失效，改以逐頁圖片本地打包
+    #[serde(default)]
+    pub rebuilt: bool,
+}
+
+/// 下載完成的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadOutcome {
+    /// 站台提供的原始檔案（或站台本來就是逐頁打包）
+    Original,
+    /// 原始 ZIP 失效，改以逐頁圖片本地打包
+    RebuiltFromImages,
+}
+
+/// 網站下載 .part 的分段配置：CDN 支援 Range 且檔案夠大時沿用直鏈的多段切法，
+/// 否則單段（單連線）。佇列每秒以 JSON 落地到 tasks.segments，重開 app 照樣續傳。
+#[derive(Default)]
+pub struct PartState {
+    pub segments: Mutex<Vec<Segment>>,
+}
+
+impl PartState {
+    /// 空字串或壞資料 → 空配置（從頭下載）
+    pub fn from_json(json: &str) -> Self {
+        let segments = serde_json::from_str::<Vec<PersistedSegment>>(json)
+            .map(|v| v.into_iter().map(PersistedSegment::into_segment).collect())
+            .unwrap_or_default();
+        PartState {
+            segments: Mutex::new(segments),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let persisted: Vec<PersistedSegment> = self
+            .segments
+            .lock()
+            .unwrap()
+            .iter()
+            .map(PersistedSegment::from_segment)
+            .collect();
+        serde_json::to_string(&persisted).unwrap_or_default()
+    }
+
+    pub fn downloaded(&self) -> u64 {
+        self.segments
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|s| s.written.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.lock().unwrap().len()
+    }
+
+    /// 配置與遠端探測結果相符才能續傳（檔案大小變了、不再支援 Range 都要重來）
+    pub fn fits(&self, total: u64, ranged: bool) -> bool {
+        let segments = self.segments.lock().unwrap();
+        // 工作竊取切出的分段附加在後面，以最大的 end 為準
+        let end = segments.iter().map(|s| s.end()).max();
+        match (segments.len(), end) {
+            (0, _) | (_, None) => false,
+            (1, Some(end)) => end == crate::http_dl::manager::UNBOUNDED || end == total,
+            (_, Some(end)) => ranged && end == total,
+        }
+    }
+
+    pub fn replace(&self, segments: Vec<Segment>) {
+        *self.segments.lock().unwrap() = segments;
+    }
 }
 
 #[derive(Serialize, Clone)]
@@ -35,88 +129,106 @@ pub struct DownloadProgress {
     pub time_remaining_secs: f64,
 }
 
-impl fmt::Display for Site {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Site::Wnacg => write!(f, "wnacg"),
-            Site::NHentai => write!(f, "nhentai"),
-        }
-    }
-}
+/// 站台實作。新增站台只需實作此 trait 並登記到 PROVIDERS，
+/// 剪貼簿監控、手動新增與下載佇列都經由 registry 分派，不直接點名站台。
+#[async_trait]
+pub trait Provider: Send + Sync {
+    /// 站台代號（日誌用）
+    fn name(&self) -> &'static str;
 
-impl Site {
-    /// 根據 host 辨識屬於哪個網站
-    pub fn from_url(url: &str) -> Result<Self, String> {
-        let parsed = url::Url::parse(url).map_err(|_| "無效的 URL 格式".to_string())?;
-        match parsed.host_str() {
-            Some(h) if h == "wnacg.com" || h.ends_with(".wnacg.com") => Ok(Site::Wnacg),
-            Some(h) if h == "nhentai.net" || h.ends_with(".nhentai.net") => Ok(Site::NHentai),
-            _ => Err("不支援的網站域名".to_string()),
-        }
+    /// host 是否屬於此站台：內建規則 base_url 的主網域（含子網域），與 normalize 的檢查一致
+    fn matches_host(&self, host: &str) -> bool {
+        self.default_rules().matches_host(host)
     }
 
-    /// 驗證是否該站以一部作品為單位的網址路徑
-    pub fn validate(&self, url: &str) -> Result<String, String> {
-        match self {
-            Site::Wnacg => wnacg::validate(url),
-            Site::NHentai => nhentai::validate(url),
-        }
-    }
+    /// 內建爬取規則（scrape_rules.json 可逐欄覆寫）
+    fn default_rules(&self) -> SiteRules;
+
+    /// 驗證是否該站以一部作品為單位的網址路徑，回傳規範化的 URL
+    fn normalize(&self, rules: &SiteRules, url: &str) -> Result<String, String>;
 
     /// 解析下載頁面 取得 ClipboardPayload 所需的資料
-    pub async fn fetch_details(
+    async fn fetch_details(
         &self,
         handle: &AppHandle,
         url: &str,
-    ) -> Result<ClipboardPayload, String> {
-        match self {
-            Site::Wnacg => wnacg::fetch_payload_details(handle, url.to_string())
-                .await
-                .map_err(|e| e.to_string()),
-            Site::NHentai => Err("NHentai fetch 尚未實作".to_string()),
-        }
-    }
+    ) -> Result<ClipboardPayload, DownloadError>;
 
-    pub async fn download(
+    /// 由下載頁解析實際檔案網址；沒有單一檔案的站台（逐頁打包）回空字串
+    async fn resolve_file_url(
+        &self,
+        handle: &AppHandle,
+        download_page_href: &str,
+    ) -> Result<String, DownloadError>;
+
+    /// 下載到 save_path（經由 .part），cached_file_url 為 DB 快取的檔案網址（可能為空或過期）
+    #[allow(clippy::too_many_arguments)]
+    async fn download(
         &self,
         client: &reqwest::Client,
         app_handle: &AppHandle,
         source_url: String,
         cached_file_url: String,
         save_path: PathBuf,
+        part: Arc<PartState>,
         cancelled: Arc<AtomicBool>,
-        bandwidth_limit_bps: Arc<AtomicU64>,
-    ) -> Result<(), DownloadError> {
-        match self {
-            Site::Wnacg => {
-                let had_cache = !cached_file_url.is_empty();
-                let file_url = if had_cache {
-                    cached_file_url
-                } else {
-                    wnacg::get_file_url(app_handle, &source_url).await?
-                };
-                let result = wnacg::download(
-                    client,
-                    app_handle,
-                    source_url.clone(),
-                    file_url,
-                    save_path.clone(),
-                    cancelled.clone(),
-                    bandwidth_limit_bps.clone(),
-                )
-                .await;
-
-                // 快取的 file_url 可能過期：404 時重抓最新連結再試一次，
-                // 重抓本身 404（下載頁已消失）才回傳 NotFound
-                if had_cache && matches!(&result, Err(DownloadError::NotFound)) {
-                    tracing::info!("快取 file_url 失效，重新抓取: {}", source_url);
-                    let fresh_url = wnacg::get_file_url(app_handle, &source_url).await?;
-                    return wnacg::download(client, app_handle, source_url, fresh_url, save_path, cancelled, bandwidth_limit_bps)
-                        .await;
-                }
-                result
-            }
-            Site::NHentai => Err(DownloadError::Other("NHentai 下載尚未實作".to_string())),
-        }
+        throttle: Throttle,
+    ) -> Result<DownloadOutcome, DownloadError>;
+}
+
+/// 已支援的站台
+static PROVIDERS: &[&dyn Provider] = &[&wnacg::Wnacg, &nhentai::NHentai];
+
+/// 根據 host 辨識屬於哪個站台
+pub fn from_url(url: &str) -> Result<&'static dyn Provider, String> {
+    let parsed = url::Url::parse(url).map_err(|_| "無效的 URL 格式".to_string())?;
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| "不支援的網站域名".to_string())?;
+    PROVIDERS
+        .iter()
+        .copied()
+        .find(|p| p.matches_host(host))
+        .ok_or_else(|| "不支援的網站域名".to_string())
+}
+
+/// 作品 ID：站台規則 url_pattern 的第一個 capture（無法辨識回 None）
+pub fn work_id(handle: &AppHandle, url: &str) -> Option<String> {
+    let site = from_url(url).ok()?;
+    let path = url::Url::parse(url).ok()?.path().to_string();
+    rules::get(handle, site.name())
+        .match_path(&path)
+        .ok()
+        .flatten()
+}
+
+/// 辨識站台並以目前生效的規則規範化網址
+pub fn normalize_url(
+    handle: &AppHandle,
+    url: &str,
+) -> Result<(&'static dyn Provider, String), String> {
+    let site = from_url(url)?;
+    let normalized = site.normalize(&rules::get(handle, site.name()), url)?;
+    Ok((site, normalized))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_url_dispatches_by_host() {
+        assert_eq!(
+            from_url("https://www.wnacg.com/photos-index-aid-1.html")
+                .unwrap()
+                .name(),
+            "wnacg"
+        );
+        assert_eq!(
+            from_url("https://nhentai.net/g/1/").unwrap().name(),
+            "nhentai"
+        );
+        assert!(from_url("https://example.com/g/1/").is_err());
+        assert!(from_url("not a url").is_err());
     }
 }
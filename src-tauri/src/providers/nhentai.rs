@@ -1,36 +1,498 @@
+use crate::{
+    bandwidth::Throttle,
+    error::DownloadError,
+    providers::{
+        gallery,
+        rules::{self, SiteRules},
+        ClipboardPayload, DownloadOutcome, PartState, Provider,
+    },
+    state::AppState,
+};
+
+use async_trait::async_trait;
 use regex::Regex;
-use std::sync::OnceLock;
-use url::Url;
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::{atomic::AtomicBool, Arc};
+use tauri::{AppHandle, Manager};
 
-static RE_VALIDATE: OnceLock<Regex> = OnceLock::new();
+/// nhentai 各服務的網址前綴，取自規則的 base_url / image_base_url / thumb_base_url；測試時改指本機 stub
+pub struct Endpoints {
+    /// 作品頁 `{site}/g/{id}/` 與 API `{site}/api/gallery/{id}`
+    pub site: String,
+    /// 內頁原圖 `{images}/galleries/{media_id}/{n}.{ext}`
+    pub images: String,
+    /// 封面 `{thumbs}/galleries/{media_id}/cover.{ext}`
+    pub thumbs: String,
+}
 
-/// 驗證 nhentai URL 並回傳規範化的 URL 字串
-pub fn validate(content: &str) -> Result<String, String> {
-    // 1. 初步解析 URL
-    let parsed_url = Url::parse(content).map_err(|_| "無效的 URL 格式".to_string())?;
+impl Endpoints {
+    pub fn from_rules(rules: &SiteRules) -> Self {
+        Endpoints {
+            site: rules.base_url.trim_end_matches('/').to_string(),
+            images: rules.image_base_url.trim_end_matches('/').to_string(),
+            thumbs: rules.thumb_base_url.trim_end_matches('/').to_string(),
+        }
+    }
+}
+
+/// API `/api/gallery/{id}` 與作品頁內嵌 `window._gallery` 共用的 JSON 結構（只取用得到的欄位）
+#[derive(Deserialize)]
+pub struct Gallery {
+    pub media_id: String,
+    pub title: GalleryTitle,
+    pub images: GalleryImages,
+    #[serde(default)]
+    pub tags: Vec<GalleryTag>,
+    pub num_pages: u32,
+}
+
+#[derive(Deserialize)]
+pub struct GalleryTitle {
+    pub english: Option<String>,
+    pub japanese: Option<String>,
+    pub pretty: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GalleryImages {
+    pub pages: Vec<GalleryImage>,
+    pub cover: GalleryImage,
+}
+
+#[derive(Deserialize)]
+pub struct GalleryImage {
+    /// 圖片格式代碼：j/p/g/w
+    pub t: String,
+}
+
+#[derive(Deserialize)]
+pub struct GalleryTag {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub name: String,
+}
+
+impl GalleryImage {
+    fn ext(&self) -> &'static str {
+        match self.t.as_str() {
+            "p" => "png",
+            "g" => "gif",
+            "w" => "webp",
+            _ => "jpg",
+        }
+    }
+}
+
+impl Gallery {
+    /// 英文標題優先，與 wnacg 一樣直接拿來當檔名
+    pub fn title(&self) -> String {
+        [
+            &self.title.english,
+            &self.title.japanese,
+            &self.title.pretty,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|t| t.trim())
+        .find(|t| !t.is_empty())
+        .unwrap_or("無法找到標題")
+        .to_string()
+    }
+
+    pub fn cover_url(&self, endpoints: &Endpoints) -> String {
+        format!(
+            "{}/galleries/{}/cover.{}",
+            endpoints.thumbs,
+            self.media_id,
+            self.images.cover.ext()
+        )
+    }
 
-    // 2. 驗證 Scheme 與 Host
-    // nhentai 強制使用 https，且主網域為 nhentai.net
-    if parsed_url.scheme() != "https" {
-        return Err("必須使用 https 協定".to_string());
+    /// 每頁的（下載網址, zip 內檔名）；檔名補零讓閱讀器照順序排
+    pub fn pages(&self, endpoints: &Endpoints) -> Vec<(String, String)> {
+        let total = self.images.pages.len();
+        self.images
+            .pages
+            .iter()
+            .enumerate()
+            .map(|(i, page)| {
+                let url = format!(
+                    "{}/galleries/{}/{}.{}",
+                    endpoints.images,
+                    self.media_id,
+                    i + 1,
+                    page.ext()
+                );
+                let name = gallery::page_file_name(i + 1, total, &url);
+                (url, name)
+            })
+            .collect()
     }
 
-    if parsed_url.host_str() != Some("nhentai.net") {
-        return Err("域名必須為 nhentai.net".to_string());
+    /// `{類型}:{名稱}`，例如 `artist:xxx`、`tag:yyy`
+    pub fn tag_list(&self) -> Vec<String> {
+        self.tags
+            .iter()
+            .map(|t| format!("{}:{}", t.kind, t.name))
+            .collect()
     }
+}
+
+/// 驗證 nhentai URL 並回傳規範化的 URL 字串
+pub fn validate(rules: &SiteRules, content: &str) -> Result<String, String> {
+    // 1. 解析 URL 並驗證 Scheme 與 Host（以規則的 base_url 為準）
+    let parsed_url = rules.check_url(content)?;
 
-    // 3. 使用 Regex 驗證 Path 並提取 ID
+    // 2. 以規則的 url_pattern 驗證 Path 並提取 ID
     // 格式通常為 /g/123456/ 或 /g/123456
-    let re = RE_VALIDATE.get_or_init(|| Regex::new(r"^/g/(\d+)/?$").unwrap());
+    let id = rules
+        .match_path(parsed_url.path())?
+        .ok_or_else(|| format!("路徑格式錯誤，應符合 {}", rules.url_pattern))?;
+
+    // 3. 規範化：依 work_url 樣板重建，避免帶有額外的參數或不一致的斜線
+    Ok(rules.work_url_for(&id))
+}
 
-    if !re.is_match(parsed_url.path()) {
-        return Err("路徑格式錯誤，應為 /g/{ID}/".to_string());
+pub struct NHentai;
+
+#[async_trait]
+impl Provider for NHentai {
+    fn name(&self) -> &'static str {
+        "nhentai"
     }
 
-    // 4. 規範化：確保回傳的格式統一（例如統一加上結尾斜線，或去掉 query parameters）
-    let caps = re.captures(parsed_url.path()).unwrap();
-    let id = &caps[1];
+    fn default_rules(&self) -> SiteRules {
+        SiteRules {
+            base_url: "https://nhentai.net".to_string(),
+            work_url: "/g/{id}/".to_string(),
+            url_pattern: r"^/g/(\d+)/?$".to_string(),
+            image_base_url: "https://i.nhentai.net".to_string(),
+            thumb_base_url: "https://t.nhentai.net".to_string(),
+            embedded_json: r#"window\._gallery\s*=\s*JSON\.parse\("((?:[^"\\]|\\.)*)"\)"#
+                .to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn normalize(&self, rules: &SiteRules, url: &str) -> Result<String, String> {
+        validate(rules, url)
+    }
+
+    async fn fetch_details(
+        &self,
+        handle: &AppHandle,
+        url: &str,
+    ) -> Result<ClipboardPayload, DownloadError> {
+        fetch_payload_details(handle, url.to_string()).await
+    }
+
+    /// 沒有單一檔案：逐頁抓圖後本地打包
+    async fn resolve_file_url(
+        &self,
+        _handle: &AppHandle,
+        _download_page_href: &str,
+    ) -> Result<String, DownloadError> {
+        Ok(String::new())
+    }
+
+    async fn download(
+        &self,
+        client: &reqwest::Client,
+        app_handle: &AppHandle,
+        source_url: String,
+        _cached_file_url: String,
+        save_path: PathBuf,
+        part: Arc<PartState>,
+        cancelled: Arc<AtomicBool>,
+        throttle: Throttle,
+    ) -> Result<DownloadOutcome, DownloadError> {
+        download_gallery(
+            client, app_handle, source_url, save_path, part, cancelled, throttle,
+        )
+        .await?;
+        Ok(DownloadOutcome::Original)
+    }
+}
 
-    // 建構標準化的 URL 避免帶有額外的參數或不一致的斜線
-    Ok(format!("https://nhentai.net/g/{}/", id))
+/// 從作品網址取出 gallery ID
+fn gallery_id(rules: &SiteRules, url: &str) -> Result<String, DownloadError> {
+    let normalized = validate(rules, url).map_err(DownloadError::Other)?;
+    Ok(normalized
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or_default()
+        .to_string())
+}
+
+/// 先打 API；API 被擋（Cloudflare 常回 403）時退回解析作品頁內嵌的 `window._gallery`
+pub async fn fetch_gallery(
+    client: &reqwest::Client,
+    endpoints: &Endpoints,
+    rules: &SiteRules,
+    id: &str,
+) -> Result<Gallery, DownloadError> {
+    let res = client
+        .get(format!("{}/api/gallery/{}", endpoints.site, id))
+        .send()
+        .await?;
+    if matches!(
+        res.status(),
+        reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE
+    ) {
+        return Err(DownloadError::NotFound);
+    }
+    if res.status().is_success() {
+        let body = res.text().await?;
+        return serde_json::from_str(&body)
+            .map_err(|e| DownloadError::Other(format!("nhentai: API 回應解析失敗: {}", e)));
+    }
+    tracing::warn!("nhentai: API 回應 {}，改解析作品頁", res.status());
+
+    let res = client
+        .get(format!(
+            "{}{}",
+            endpoints.site,
+            rules.work_url.replace("{id}", id)
+        ))
+        .send()
+        .await?;
+    if !res.status().is_success() {
+        return Err(DownloadError::from_response(&res));
+    }
+    parse_gallery_html(rules, &res.text().await?)
+}
+
+/// 作品頁以 `window._gallery = JSON.parse("...")` 內嵌與 API 相同的 JSON（regex 見規則 embedded_json）
+pub fn parse_gallery_html(rules: &SiteRules, html: &str) -> Result<Gallery, DownloadError> {
+    let re = Regex::new(&rules.embedded_json)
+        .map_err(|e| DownloadError::Other(format!("nhentai: embedded_json 無效: {}", e)))?;
+    let literal = re
+        .captures(html)
+        .map(|c| c[1].to_string())
+        .ok_or_else(|| DownloadError::Other("nhentai: 作品頁找不到 gallery 資料".to_string()))?;
+    // 字串常值的跳脫（" 等）與 JSON 相同，包回引號交給 serde_json 還原
+    let json: String = serde_json::from_str(&format!("\"{}\"", literal))
+        .map_err(|e| DownloadError::Other(format!("nhentai: gallery 資料解析失敗: {}", e)))?;
+    serde_json::from_str(&json)
+        .map_err(|e| DownloadError::Other(format!("nhentai: gallery 資料解析失敗: {}", e)))
+}
+
+pub async fn fetch_payload_details(
+    app_handle: &AppHandle,
+    url: String,
+) -> Result<ClipboardPayload, DownloadError> {
+    tracing::info!("fetch_payload_details: {}", url);
+
+    let state = app_handle.state::<AppState>();
+    let rules = rules::get(app_handle, "nhentai");
+    let endpoints = Endpoints::from_rules(&rules);
+    let gallery = fetch_gallery(
+        &state.client,
+        &endpoints,
+        &rules,
+        &gallery_id(&rules, &url)?,
+    )
+    .await?;
+
+    let created_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+
+    Ok(ClipboardPayload {
+        title: gallery.title(),
+        image: gallery.cover_url(&endpoints),
+        // 沒有獨立下載頁；進度事件以作品網址為 key
+        download_page_href: url.clone(),
+        url,
+        file_url: String::new(),
+        // 逐頁抓圖再打包，事先不知道總大小
+        file_size: -1,
+        created_at,
+        db_status: "idle".to_string(),
+        save_path: String::new(),
+        downloaded_bytes: 0,
+        segments: String::new(),
+        page_count: i64::from(gallery.num_pages),
+        tags: gallery.tag_list(),
+        rebuilt: false,
+        attempts: Vec::new(),
+    })
+}
+
+/// 取 gallery 資料後逐頁下載並打包成 save_path（.zip）
+#[allow(clippy::too_many_arguments)]
+pub async fn download_gallery(
+    client: &reqwest::Client,
+    app_handle: &AppHandle,
+    source_url: String, // 原始網頁網址 (用於進度事件辨識)
+    save_path: PathBuf,
+    part: Arc<PartState>,
+    cancelled: Arc<AtomicBool>,
+    throttle: Throttle,
+) -> Result<(), DownloadError> {
+    let rules = rules::get(app_handle, "nhentai");
+    let endpoints = Endpoints::from_rules(&rules);
+    let gallery = fetch_gallery(
+        client,
+        &endpoints,
+        &rules,
+        &gallery_id(&rules, &source_url)?,
+    )
+    .await?;
+    let pages = gallery.pages(&endpoints);
+
+    gallery::download_images(
+        client,
+        app_handle,
+        &source_url,
+        &pages,
+        &save_path,
+        &part,
+        &cancelled,
+        &throttle,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bandwidth::{BandwidthLimiter, Engine};
+    use crate::utils::test_server::serve;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    const GALLERY_JSON: &str = include_str!("fixtures/nhentai_gallery.json");
+    const GALLERY_HTML: &str = include_str!("fixtures/nhentai_gallery.html");
+
+    fn stub_endpoints(base: &str) -> Endpoints {
+        Endpoints {
+            site: base.to_string(),
+            images: base.to_string(),
+            thumbs: base.to_string(),
+        }
+    }
+
+    #[test]
+    fn validate_normalizes_gallery_url() {
+        let rules = NHentai.default_rules();
+        assert_eq!(
+            validate(&rules, "https://nhentai.net/g/123456?page=2").unwrap(),
+            "https://nhentai.net/g/123456/"
+        );
+        assert!(validate(&rules, "https://nhentai.net/tag/foo/").is_err());
+        assert_eq!(
+            validate(&rules, "https://www.nhentai.net/g/123456").unwrap(),
+            "https://nhentai.net/g/123456/"
+        );
+        assert!(validate(&rules, "http://nhentai.net/g/123456/").is_err());
+        assert_eq!(
+            gallery_id(&rules, "https://nhentai.net/g/123456/").unwrap(),
+            "123456"
+        );
+    }
+
+    #[test]
+    fn parses_metadata_from_api_json() {
+        let gallery: Gallery = serde_json::from_str(GALLERY_JSON).unwrap();
+        let endpoints = Endpoints::from_rules(&NHentai.default_rules());
+        assert_eq!(gallery.title(), "Sample Gallery");
+        assert_eq!(gallery.num_pages, 3);
+        assert_eq!(
+            gallery.cover_url(&endpoints),
+            "https://t.nhentai.net/galleries/987654/cover.jpg"
+        );
+        assert_eq!(gallery.tag_list(), vec!["tag:full color", "artist:someone"]);
+        let pages = gallery.pages(&endpoints);
+        assert_eq!(pages[1].0, "https://i.nhentai.net/galleries/987654/2.png");
+        assert_eq!(pages[1].1, "2.png");
+    }
+
+    #[test]
+    fn parses_metadata_embedded_in_gallery_page() {
+        let gallery = parse_gallery_html(&NHentai.default_rules(), GALLERY_HTML).unwrap();
+        assert_eq!(gallery.media_id, "987654");
+        assert_eq!(gallery.title(), "Sample \"Quoted\" Gallery");
+        assert_eq!(gallery.images.pages.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_gallery_page_when_api_blocked() {
+        let base = serve(vec![
+            ("/api/gallery/123456", 403, Vec::new()),
+            ("/g/123456/", 200, GALLERY_HTML.as_bytes().to_vec()),
+        ])
+        .await;
+        let rules = NHentai.default_rules();
+        let gallery = fetch_gallery(
+            &reqwest::Client::new(),
+            &stub_endpoints(&base),
+            &rules,
+            "123456",
+        )
+        .await
+        .unwrap();
+        assert_eq!(gallery.num_pages, 3);
+
+        let base = serve(Vec::new()).await;
+        let missing =
+            fetch_gallery(&reqwest::Client::new(), &stub_endpoints(&base), &rules, "1").await;
+        assert!(matches!(missing, Err(DownloadError::NotFound)));
+    }
+
+    #[tokio::test]
+    async fn downloads_pages_and_packs_zip() {
+        let base = serve(vec![
+            ("/api/gallery/123456", 200, GALLERY_JSON.as_bytes().to_vec()),
+            ("/galleries/987654/1.jpg", 200, b"page-1".to_vec()),
+            ("/galleries/987654/2.png", 200, b"page-2".to_vec()),
+            ("/galleries/987654/3.jpg", 200, b"page-3".to_vec()),
+        ])
+        .await;
+        let client = reqwest::Client::new();
+        let endpoints = stub_endpoints(&base);
+        let gallery = fetch_gallery(&client, &endpoints, &NHentai.default_rules(), "123456")
+            .await
+            .unwrap();
+        let pages = gallery.pages(&endpoints);
+
+        let dir = std::env::temp_dir().join(format!("nhentai-test-{}", std::process::id()));
+        let staging = dir.join("Sample Gallery.zip.part");
+        std::fs::create_dir_all(&staging).unwrap();
+        // 上次已下載的頁面不重抓
+        std::fs::write(staging.join("1.jpg"), b"cached").unwrap();
+
+        let written = AtomicU64::new(0);
+        let mut calls = Vec::new();
+        gallery::download_pages(
+            &client,
+            &pages,
+            &staging,
+            &written,
+            &AtomicBool::new(false),
+            &Arc::new(BandwidthLimiter::default()).throttle(Engine::Site),
+            |done, total| calls.push((done, total)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(calls.first(), Some(&(1, 3)));
+        assert_eq!(calls.last(), Some(&(3, 3)));
+        assert_eq!(written.load(Ordering::Relaxed), 18);
+
+        let dest = dir.join("Sample Gallery.zip");
+        let names: Vec<String> = pages.into_iter().map(|(_, n)| n).collect();
+        gallery::pack_zip(&staging, &names, &dest).unwrap();
+
+        let mut archive = zip::ZipArchive::new(std::fs::File::open(&dest).unwrap()).unwrap();
+        let entries: Vec<String> = archive.file_names().map(str::to_string).collect();
+        assert_eq!(entries.len(), 3);
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name("2.png").unwrap(), &mut content)
+            .unwrap();
+        assert_eq!(content, "page-2");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
 }
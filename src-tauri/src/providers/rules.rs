@@ -0,0 +1,308 @@
+// src/providers/rules.rs
+// 站台爬取規則：站台網址、網址 regex、selector 清單、下載連結規則。內建規則為預設，
+// app_data_dir/scrape_rules.json 可逐站逐欄覆寫 — 站台改版時改檔案即可，不必重新發版。
+//
+// 檔案格式（只需寫要改的欄位）：
+// { "wnacg": { "title": ["#bodywrap > h2", "h1"] }, "nhentai": { "url_pattern": "^/g/(\\d+)/?$" } }
+//
+// 站台換網域時改 base_url（及 image_base_url / thumb_base_url）；作品網址的驗證與規範化都以此為準。
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use regex::Regex;
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::{AppHandle, Manager};
+use url::Url;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SiteRules {
+    /// 站台網址（scheme + 主機），站內相對連結以此補全；主網域與其子網域都算本站
+    pub base_url: String,
+    /// 作品頁網址樣板（接在 base_url 後），`{id}` 代入作品 ID
+    pub work_url: String,
+    /// 作品網址 path 的 regex，第一個 capture 為作品 ID
+    pub url_pattern: String,
+    /// 下載頁網址 path 的 regex，第一個 capture 為作品 ID（沒有下載頁的站台留空）
+    pub download_page_pattern: String,
+    /// 內頁原圖網址前綴（圖片放在另一個主機的站台用）
+    pub image_base_url: String,
+    /// 封面縮圖網址前綴
+    pub thumb_base_url: String,
+    /// 作品頁標題 selector，依序嘗試
+    pub title: Vec<String>,
+    /// 作品頁封面 selector（取 src）
+    pub cover: Vec<String>,
+    /// 作品頁上下載頁連結的 selector（取 href）
+    pub download_page: Vec<String>,
+    /// 下載頁上實際檔案連結的 selector（取 href）
+    pub file_link: Vec<String>,
+    /// 頁面內嵌 JSON 的 regex，第一個 capture 為 JS 字串常值
+    pub embedded_json: String,
+    /// 作品索引頁上各圖片瀏覽頁連結的 selector（取全部 href）
+    pub gallery_page: Vec<String>,
+    /// 作品索引頁「下一頁」的 selector（取 href）
+    pub gallery_next: Vec<String>,
+    /// 圖片瀏覽頁上原圖的 selector（取 src）
+    pub gallery_image: Vec<String>,
+}
+
+impl SiteRules {
+    /// 比對作品網址 path，回傳作品 ID（不符回 None）
+    pub fn match_path(&self, path: &str) -> Result<Option<String>, String> {
+        first_capture("url_pattern", &self.url_pattern, path)
+    }
+
+    /// 比對下載頁網址 path，回傳作品 ID（不符回 None）
+    pub fn match_download_page(&self, path: &str) -> Result<Option<String>, String> {
+        first_capture("download_page_pattern", &self.download_page_pattern, path)
+    }
+
+    /// 站台主網域：base_url 的主機去掉開頭的 `www.`
+    fn domain(&self) -> Option<String> {
+        let base = Url::parse(&self.base_url).ok()?;
+        let host = base.host_str()?;
+        Some(host.strip_prefix("www.").unwrap_or(host).to_string())
+    }
+
+    /// host 是否屬於本站（主網域或其子網域）
+    pub fn matches_host(&self, host: &str) -> bool {
+        self.domain().is_some_and(|domain| {
+            host == domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .is_some_and(|sub| sub.ends_with('.'))
+        })
+    }
+
+    /// 解析作品網址，並檢查 scheme 與 host 和 base_url 一致
+    pub fn check_url(&self, content: &str) -> Result<Url, String> {
+        let parsed = Url::parse(content).map_err(|_| "無效的 URL 格式".to_string())?;
+        let base = Url::parse(&self.base_url).map_err(|e| format!("base_url 無效: {}", e))?;
+        if parsed.scheme() != base.scheme() {
+            return Err(format!("必須使用 {} 協定", base.scheme()));
+        }
+        if !parsed.host_str().is_some_and(|h| self.matches_host(h)) {
+            return Err(format!("域名必須為 {}", self.domain().unwrap_or_default()));
+        }
+        Ok(parsed)
+    }
+
+    /// 依 work_url 樣板組出作品頁網址
+    pub fn work_url_for(&self, id: &str) -> String {
+        format!(
+            "{}{}",
+            self.base_url.trim_end_matches('/'),
+            self.work_url.replace("{id}", id)
+        )
+    }
+
+    /// 站內相對連結補成絕對網址（`//` 開頭沿用 base_url 的 scheme）
+    pub fn absolute_url(&self, raw: &str) -> String {
+        Url::parse(&self.base_url)
+            .and_then(|base| base.join(raw))
+            .map(String::from)
+            .unwrap_or_else(|_| raw.to_string())
+    }
+}
+
+/// regex 比對 text，回傳第一個 capture（沒有 capture group 時回整段）
+fn first_capture(field: &str, pattern: &str, text: &str) -> Result<Option<String>, String> {
+    let re = Regex::new(pattern).map_err(|e| format!("{} 無效: {}", field, e))?;
+    Ok(re.captures(text).map(|c| {
+        c.get(1)
+            .unwrap_or_else(|| c.get(0).unwrap())
+            .as_str()
+            .to_string()
+    }))
+}
+
+/// 依序嘗試 selectors，回傳第一個命中的元素（無效的 selector 略過）
+pub fn select_first<'a>(document: &'a Html, selectors: &[String]) -> Option<ElementRef<'a>> {
+    for sel in selectors {
+        if let Ok(parsed) = Selector::parse(sel) {
+            if let Some(el) = document.select(&parsed).next() {
+                return Some(el);
+            }
+        }
+    }
+    None
+}
+
+/// 依序嘗試 selectors，回傳第一個有命中的 selector 的全部元素
+pub fn select_all<'a>(document: &'a Html, selectors: &[String]) -> Vec<ElementRef<'a>> {
+    for sel in selectors {
+        if let Ok(parsed) = Selector::parse(sel) {
+            let found: Vec<_> = document.select(&parsed).collect();
+            if !found.is_empty() {
+                return found;
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// 以檔案內容逐欄覆寫內建規則
+fn merge(name: &str, defaults: &SiteRules, overlay: Option<&Value>) -> Result<SiteRules, String> {
+    let Some(overlay) = overlay else {
+        return Ok(defaults.clone());
+    };
+    let Value::Object(overlay) = overlay else {
+        return Err(format!("{}: 規則必須是物件", name));
+    };
+    let mut merged = serde_json::to_value(defaults).map_err(|e| e.to_string())?;
+    if let Value::Object(map) = &mut merged {
+        for (k, v) in overlay {
+            map.insert(k.clone(), v.clone());
+        }
+    }
+    serde_json::from_value(merged).map_err(|e| format!("{}: {}", name, e))
+}
+
+pub struct RulesState {
+    inner: RwLock<HashMap<&'static str, SiteRules>>,
+    path: PathBuf,
+}
+
+impl RulesState {
+    /// 載入 scrape_rules.json；檔案有誤時記錄警告並使用內建規則
+    pub fn load(app_data_dir: &Path) -> Self {
+        let state = Self {
+            inner: RwLock::new(HashMap::new()),
+            path: app_data_dir.join("scrape_rules.json"),
+        };
+        if let Err(e) = state.reload() {
+            tracing::warn!("Rules: {}，改用內建規則", e);
+            *state.inner.write().unwrap() = effective(&HashMap::new()).unwrap_or_default();
+        }
+        state
+    }
+
+    /// 重新讀取規則檔。檔案不存在 = 全用內建規則；內容有誤回錯誤並保留現有規則
+    pub fn reload(&self) -> Result<(), String> {
+        let file: HashMap<String, Value> = match std::fs::read_to_string(&self.path) {
+            Ok(s) => serde_json::from_str(&s).map_err(|e| format!("規則檔解析失敗: {}", e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(format!("讀取規則檔失敗: {}", e)),
+        };
+        *self.inner.write().unwrap() = effective(&file)?;
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> SiteRules {
+        self.inner
+            .read()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn all(&self) -> HashMap<&'static str, SiteRules> {
+        self.inner.read().unwrap().clone()
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// 每個已登記站台的生效規則（內建 + 檔案覆寫）
+fn effective(file: &HashMap<String, Value>) -> Result<HashMap<&'static str, SiteRules>, String> {
+    super::PROVIDERS
+        .iter()
+        .map(|p| {
+            Ok((
+                p.name(),
+                merge(p.name(), &p.default_rules(), file.get(p.name()))?,
+            ))
+        })
+        .collect()
+}
+
+/// 取某站台目前生效的規則
+pub fn get(handle: &AppHandle, name: &str) -> SiteRules {
+    handle.state::<RulesState>().get(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn defaults() -> SiteRules {
+        SiteRules {
+            base_url: "https://www.example.com".to_string(),
+            work_url: "/g/{id}/".to_string(),
+            url_pattern: r"^/g/(\d+)/?$".to_string(),
+            title: vec!["h1".to_string(), "h2".to_string()],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overlay_replaces_only_given_fields() {
+        let overlay = serde_json::json!({ "title": ["#main h1"] });
+        let merged = merge("test", &defaults(), Some(&overlay)).unwrap();
+        assert_eq!(merged.title, vec!["#main h1"]);
+        assert_eq!(merged.url_pattern, r"^/g/(\d+)/?$");
+
+        assert!(merge(
+            "test",
+            &defaults(),
+            Some(&serde_json::json!({ "title": "h1" }))
+        )
+        .is_err());
+        assert!(merge("test", &defaults(), Some(&serde_json::json!(["h1"]))).is_err());
+    }
+
+    #[test]
+    fn match_path_returns_first_capture() {
+        let rules = defaults();
+        assert_eq!(rules.match_path("/g/123/").unwrap().as_deref(), Some("123"));
+        assert_eq!(rules.match_path("/tag/foo/").unwrap(), None);
+
+        let broken = SiteRules {
+            url_pattern: "(".to_string(),
+            ..Default::default()
+        };
+        assert!(broken.match_path("/g/1/").is_err());
+    }
+
+    #[test]
+    fn urls_follow_base_url() {
+        let rules = defaults();
+        assert!(rules.matches_host("example.com"));
+        assert!(rules.matches_host("img.example.com"));
+        assert!(!rules.matches_host("notexample.com"));
+
+        assert!(rules.check_url("https://example.com/g/1/").is_ok());
+        assert!(rules.check_url("http://www.example.com/g/1/").is_err());
+        assert!(rules.check_url("https://example.org/g/1/").is_err());
+
+        assert_eq!(rules.work_url_for("42"), "https://www.example.com/g/42/");
+        assert_eq!(
+            rules.absolute_url("/a.html"),
+            "https://www.example.com/a.html"
+        );
+        assert_eq!(
+            rules.absolute_url("//img.example.com/1.jpg"),
+            "https://img.example.com/1.jpg"
+        );
+        assert_eq!(
+            rules.absolute_url("https://cdn.test/1.jpg"),
+            "https://cdn.test/1.jpg"
+        );
+    }
+
+    #[test]
+    fn select_first_skips_invalid_and_missing_selectors() {
+        let document = Html::parse_document("<div id='a'><h2>title</h2></div>");
+        let selectors = ["::bad".to_string(), "h1".to_string(), "#a > h2".to_string()];
+        let el = select_first(&document, &selectors).unwrap();
+        assert_eq!(el.text().collect::<String>(), "title");
+    }
+}
@@ -1,60 +1,299 @@
-use crate::{download_core::DownloadManager, error::DownloadError, providers::{ClipboardPayload, DownloadProgress}, state::AppState};
+use crate::{
+    bandwidth::Throttle,
+    download_core::DownloadManager,
+    error::DownloadError,
+    hosts,
+    http_dl::manager::{download_segments, plan_segments, Source, MAX_SEGMENTS},
+    providers::{
+        gallery::{self, PAGE_CONCURRENCY},
+        rules::{self, select_all, select_first, SiteRules},
+        ClipboardPayload, DownloadOutcome, DownloadProgress, PartState, Provider,
+    },
+    settings::SettingsState,
+    state::AppState,
+    utils,
+};
 
-use futures_util::StreamExt;
-use regex::Regex;
-use scraper::{ElementRef, Html, Selector};
-use std::path::PathBuf;
-use tokio::io::AsyncWriteExt;
+use async_trait::async_trait;
+use futures_util::{stream, StreamExt, TryStreamExt};
+use scraper::Html;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::sync::{
     atomic::{AtomicBool, AtomicU64, Ordering},
-    Arc, OnceLock,
+    Arc, Mutex,
 };
 use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 use url::Url;
 
-static RE_VALIDATE: OnceLock<Regex> = OnceLock::new();
+/// 上限低於此值（bytes/sec）時單連線就跑得滿，不再分段
+const MIN_SPLIT_CAP_BPS: u64 = 256 * 1024;
+
+/// 支援 Range 就分段；有限速時各段共用同一個 throttle 的額度，只有上限很低才維持單連線
+fn should_split(ranged: bool, cap: u64) -> bool {
+    ranged && (cap == 0 || cap >= MIN_SPLIT_CAP_BPS)
+}
+
+/// 驗證 wnacg URL 並回傳規範化的 URL 字串
+pub fn validate(rules: &SiteRules, content: &str) -> Result<String, String> {
+    // 1. 解析 URL 並驗證 Scheme 與 Host（以規則的 base_url 為準）
+    let parsed_url = rules.check_url(content)?;
+
+    // 2. 以規則的 url_pattern 驗證 Path 並提取 ID
+    let id = rules
+        .match_path(parsed_url.path())?
+        .ok_or_else(|| format!("路徑格式錯誤，應符合 {}", rules.url_pattern))?;
+
+    // 3. 依 work_url 樣板重建，統一主機並去掉多餘參數
+    Ok(rules.work_url_for(&id))
+}
+
+pub struct Wnacg;
+
+#[async_trait]
+impl Provider for Wnacg {
+    fn name(&self) -> &'static str {
+        "wnacg"
+    }
+
+    fn default_rules(&self) -> SiteRules {
+        let list = |v: &[&str]| v.iter().map(|s| s.to_string()).collect();
+        SiteRules {
+            base_url: "https://www.wnacg.com".to_string(),
+            work_url: "/photos-index-aid-{id}.html".to_string(),
+            url_pattern: r"^/photos-index-aid-(\d+)\.html$".to_string(),
+            download_page_pattern: r"aid-(\d+)".to_string(),
+            image_base_url: String::new(),
+            thumb_base_url: String::new(),
+            title: list(&["#bodywrap > h2", "#bodywrap h2", "h1", "h2"]),
+            cover: list(&["#bodywrap .pic_box img", ".pic_box img", ".grid img"]),
+            download_page: list(&["#ads > a", "a.ads", "a[href*='down']"]),
+            file_link: list(&["#ads > a", "a.ads", "a[href*='down']"]),
+            embedded_json: String::new(),
+            gallery_page: list(&[".gallary_item .pic_box a", "li.gallary_item a"]),
+            gallery_next: list(&[".paginator .next a", ".next a"]),
+            gallery_image: list(&["#picarea", "img.photo"]),
+        }
+    }
+
+    fn normalize(&self, rules: &SiteRules, url: &str) -> Result<String, String> {
+        validate(rules, url)
+    }
+
+    async fn fetch_details(
+        &self,
+        handle: &AppHandle,
+        url: &str,
+    ) -> Result<ClipboardPayload, DownloadError> {
+        fetch_payload_details(handle, url.to_string()).await
+    }
+
+    async fn resolve_file_url(
+        &self,
+        handle: &AppHandle,
+        download_page_href: &str,
+    ) -> Result<String, DownloadError> {
+        get_file_url(handle, download_page_href).await
+    }
 
-fn select_first<'a>(document: &'a Html, selectors: &[&str]) -> Option<ElementRef<'a>> {
-    for sel in selectors {
-        if let Ok(parsed) = Selector::parse(sel) {
-            if let Some(el) = document.select(&parsed).next() {
-                return Some(el);
+    async fn download(
+        &self,
+        client: &reqwest::Client,
+        app_handle: &AppHandle,
+        source_url: String,
+        cached_file_url: String,
+        save_path: PathBuf,
+        part: Arc<PartState>,
+        cancelled: Arc<AtomicBool>,
+        throttle: Throttle,
+    ) -> Result<DownloadOutcome, DownloadError> {
+        // .part 是資料夾 = 上次已改走逐頁重建，直接接著補缺頁
+        if !utils::fs::part_path(&save_path).is_dir() {
+            let result = download_zip(
+                client,
+                app_handle,
+                source_url.clone(),
+                cached_file_url,
+                save_path.clone(),
+                part.clone(),
+                cancelled.clone(),
+                throttle.clone(),
+            )
+            .await;
+            if !matches!(result, Err(DownloadError::NotFound)) {
+                return result.map(|()| DownloadOutcome::Original);
             }
+            tracing::warn!("wnacg: ZIP 連結失效，改以逐頁圖片重建: {}", source_url);
         }
+
+        rebuild_from_images(
+            client,
+            app_handle,
+            &source_url,
+            &save_path,
+            &part,
+            &cancelled,
+            &throttle,
+        )
+        .await?;
+        Ok(DownloadOutcome::RebuiltFromImages)
     }
-    None
 }
 
-/// 驗證 wnacg URL 並回傳規範化的 URL 字串
-pub fn validate(content: &str) -> Result<String, String> {
-    // 1. 初步解析 URL
-    let parsed_url = Url::parse(content).map_err(|_| "無效的 URL 格式".to_string())?;
+/// 下載站台提供的 ZIP。快取的 file_url 可能過期：404 時重抓最新連結再試一次
+/// （.part 照樣續傳），重抓本身 404（下載頁已消失）才回傳 NotFound
+#[allow(clippy::too_many_arguments)]
+async fn download_zip(
+    client: &reqwest::Client,
+    app_handle: &AppHandle,
+    source_url: String,
+    cached_file_url: String,
+    save_path: PathBuf,
+    part: Arc<PartState>,
+    cancelled: Arc<AtomicBool>,
+    throttle: Throttle,
+) -> Result<(), DownloadError> {
+    let had_cache = !cached_file_url.is_empty();
+    let file_url = if had_cache {
+        cached_file_url
+    } else {
+        get_file_url(app_handle, &source_url).await?
+    };
+    let result = download_file(
+        client,
+        app_handle,
+        source_url.clone(),
+        file_url,
+        save_path.clone(),
+        part.clone(),
+        cancelled.clone(),
+        throttle.clone(),
+    )
+    .await;
 
-    // 2. 驗證 Scheme 與 Host (快速過濾)
-    if parsed_url.scheme() != "https" {
-        return Err("必須使用 https 協定".to_string());
+    if had_cache && matches!(&result, Err(DownloadError::NotFound)) {
+        tracing::info!("快取 file_url 失效，重新抓取: {}", source_url);
+        let fresh_url = get_file_url(app_handle, &source_url).await?;
+        return download_file(
+            client, app_handle, source_url, fresh_url, save_path, part, cancelled, throttle,
+        )
+        .await;
     }
+    result
+}
 
-    if parsed_url.host_str() != Some("www.wnacg.com") {
-        return Err("域名必須為 www.wnacg.com".to_string());
+/// ZIP 失效時的備援：沿作品索引頁（含分頁）找出每張圖的瀏覽頁，取原圖後本地打包。
+/// source_url 為下載頁網址，以規則的 download_page_pattern 取作品 ID，再依 work_url 組出索引頁。
+async fn rebuild_from_images(
+    client: &reqwest::Client,
+    app_handle: &AppHandle,
+    source_url: &str,
+    save_path: &Path,
+    part: &PartState,
+    cancelled: &AtomicBool,
+    throttle: &Throttle,
+) -> Result<(), DownloadError> {
+    let rules = rules::get(app_handle, "wnacg");
+    let path = Url::parse(source_url)
+        .map(|u| u.path().to_string())
+        .unwrap_or_default();
+    let aid = rules
+        .match_download_page(&path)
+        .map_err(DownloadError::Other)?
+        .ok_or_else(|| DownloadError::Other("wnacg: 無法從網址取得作品 ID".to_string()))?;
+    let index_url = rules.work_url_for(&aid);
+
+    let images = gallery_image_urls(client, &rules, &index_url).await?;
+    if images.is_empty() {
+        return Err(DownloadError::Other(
+            "wnacg: 索引頁找不到任何圖片".to_string(),
+        ));
     }
+    let pages: Vec<(String, String)> = images
+        .iter()
+        .enumerate()
+        .map(|(i, url)| {
+            (
+                url.clone(),
+                gallery::page_file_name(i + 1, images.len(), url),
+            )
+        })
+        .collect();
+
+    gallery::download_images(
+        client, app_handle, source_url, &pages, save_path, part, cancelled, throttle,
+    )
+    .await
+}
 
-    // 3. 使用 Regex 驗證 Path 並提取 ID (兼顧檢查與提取)
-    let re = RE_VALIDATE.get_or_init(|| Regex::new(r"^/photos-index-aid-(\d+)\.html$").unwrap());
+/// 抓頁面 HTML；404/410 回 NotFound
+async fn fetch_html(client: &reqwest::Client, url: &str) -> Result<String, DownloadError> {
+    let res = client.get(url).send().await?;
+    if !res.status().is_success() {
+        return Err(DownloadError::from_response(&res));
+    }
+    Ok(res.text().await?)
+}
 
-    if !re.is_match(parsed_url.path()) {
-        return Err("路徑格式錯誤，應為 /photos-index-aid-{ID}.html".to_string());
+/// 依頁序回傳整部作品的原圖網址
+async fn gallery_image_urls(
+    client: &reqwest::Client,
+    rules: &SiteRules,
+    index_url: &str,
+) -> Result<Vec<String>, DownloadError> {
+    let mut view_pages = Vec::new();
+    let mut visited = HashSet::new();
+    let mut next = Some(index_url.to_string());
+    while let Some(url) = next.take() {
+        // 防止「下一頁」繞回已走過的分頁
+        if !visited.insert(url.clone()) {
+            break;
+        }
+        let html = fetch_html(client, &url).await?;
+        let (pages, next_url) = parse_gallery_index(rules, &html);
+        view_pages.extend(pages);
+        next = next_url;
     }
 
-    // 回傳規範化後的字串
-    Ok(parsed_url.to_string())
+    stream::iter(view_pages.into_iter().map(|url| {
+        let client = client.clone();
+        let rules = rules.clone();
+        async move {
+            let html = fetch_html(&client, &url).await?;
+            parse_view_image(&rules, &html)
+                .ok_or_else(|| DownloadError::Other(format!("wnacg: 圖片頁找不到圖片: {}", url)))
+        }
+    }))
+    .buffered(PAGE_CONCURRENCY)
+    .try_collect()
+    .await
+}
+
+/// 索引頁：（各圖片瀏覽頁網址, 下一頁網址）
+fn parse_gallery_index(rules: &SiteRules, html: &str) -> (Vec<String>, Option<String>) {
+    let document = Html::parse_document(html);
+    let pages = select_all(&document, &rules.gallery_page)
+        .into_iter()
+        .filter_map(|el| el.value().attr("href"))
+        .map(|href| rules.absolute_url(href))
+        .collect();
+    let next = select_first(&document, &rules.gallery_next)
+        .and_then(|el| el.value().attr("href"))
+        .map(|href| rules.absolute_url(href));
+    (pages, next)
+}
+
+/// 圖片瀏覽頁：原圖網址
+fn parse_view_image(rules: &SiteRules, html: &str) -> Option<String> {
+    let document = Html::parse_document(html);
+    select_first(&document, &rules.gallery_image)
+        .and_then(|el| el.value().attr("src"))
+        .map(|src| rules.absolute_url(src))
 }
 
 /// 輔助用函數
-pub async fn get_file_url(
-    app_handle: &AppHandle,
-    url: &str,
-) -> Result<String, DownloadError> {
+pub async fn get_file_url(app_handle: &AppHandle, url: &str) -> Result<String, DownloadError> {
     tracing::debug!("get_file_url: {}", url);
 
     // 取 state 中的 client 執行 reqwest get 請求
@@ -62,40 +301,30 @@ pub async fn get_file_url(
     let client = &state.client;
     let res = client.get(url).send().await?;
 
-    if matches!(res.status(), reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE) {
-        return Err(DownloadError::NotFound);
-    }
     if !res.status().is_success() {
-        return Err(DownloadError::Other(format!("網絡請求失敗，狀態碼: {}", res.status())));
+        return Err(DownloadError::from_response(&res));
     }
 
     let html_content = res.text().await?;
     let document = Html::parse_document(&html_content);
 
-    let raw = select_first(&document, &["#ads > a", "a.ads", "a[href*='down']"])
+    let rules = rules::get(app_handle, "wnacg");
+    let raw = select_first(&document, &rules.file_link)
         .and_then(|el| el.value().attr("href"))
         .ok_or_else(|| DownloadError::Other("wnacg: 無法找到下載連結".to_string()))?;
 
-    let href = if raw.starts_with("http") {
-        raw.to_string()
-    } else if raw.starts_with("//") {
-        format!("https:{}", raw)
-    } else {
-        format!("https://www.wnacg.com{}", raw)
-    };
-
-    Ok(href)
+    Ok(rules.absolute_url(raw))
 }
 
 /// Range 探測：對實際 ZIP 連結發 `Range: bytes=0-0`，驗證能否真的取到 bytes
-/// 並回傳檔案總大小。比 HEAD 可靠（強制走真實下載路徑，有些 CDN 不支援 HEAD）。
-/// - 206 Partial：從 `Content-Range: bytes 0-0/{total}` 解析總大小
-/// - 200（伺服器忽略 Range）：退回 `Content-Length`
+/// 並回傳（檔案總大小, 是否支援 Range）。比 HEAD 可靠（強制走真實下載路徑，有些 CDN 不支援 HEAD）。
+/// - 206 Partial：從 `Content-Range: bytes 0-0/{total}` 解析總大小，支援 Range
+/// - 200（伺服器忽略 Range）：退回 `Content-Length`，不支援 Range
 /// - 404/410：回 `NOT_FOUND`，代表連結預檢即失效
 async fn probe_file_size(
     client: &reqwest::Client,
     file_url: &str,
-) -> Result<i64, DownloadError> {
+) -> Result<(i64, bool), DownloadError> {
     let res = client
         .get(file_url)
         .header(reqwest::header::RANGE, "bytes=0-0")
@@ -103,10 +332,6 @@ async fn probe_file_size(
         .await?;
     let status = res.status();
 
-    if matches!(status, reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE) {
-        return Err(DownloadError::NotFound);
-    }
-
     if status == reqwest::StatusCode::PARTIAL_CONTENT {
         if let Some(total) = res
             .headers()
@@ -115,16 +340,16 @@ async fn probe_file_size(
             .and_then(|cr| cr.rsplit('/').next())
             .and_then(|s| s.trim().parse::<i64>().ok())
         {
-            return Ok(total);
+            return Ok((total, true));
         }
     }
 
     if status.is_success() {
         // 伺服器忽略 Range（回 200），退回 Content-Length；拿不到則回 -1（未知）
-        return Ok(res.content_length().map(|l| l as i64).unwrap_or(-1));
+        return Ok((res.content_length().map(|l| l as i64).unwrap_or(-1), false));
     }
 
-    Err(DownloadError::Other(format!("探測失敗，狀態碼: {}", status)))
+    Err(DownloadError::from_response(&res))
 }
 
 pub async fn fetch_payload_details(
@@ -133,16 +358,14 @@ pub async fn fetch_payload_details(
 ) -> Result<ClipboardPayload, DownloadError> {
     tracing::info!("fetch_payload_details: {}", url);
 
+    let rules = rules::get(app_handle, "wnacg");
     // 取 state 中的 client 執行 reqwest get 請求
     let state = app_handle.state::<AppState>();
     let client = &state.client;
     let res = client.get(&url).send().await?;
 
-    if matches!(res.status(), reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE) {
-        return Err(DownloadError::NotFound);
-    }
     if !res.status().is_success() {
-        return Err(DownloadError::Other(format!("網絡請求失敗，狀態碼: {}", res.status())));
+        return Err(DownloadError::from_response(&res));
     }
 
     let html_content = res.text().await?;
@@ -151,51 +374,45 @@ pub async fn fetch_payload_details(
     let (title, image, download_page_href) = {
         let document = Html::parse_document(&html_content);
 
-        let title = select_first(&document, &["#bodywrap > h2", "#bodywrap h2", "h1", "h2"])
+        let title = select_first(&document, &rules.title)
             .map(|el| el.text().collect::<String>().trim().to_string())
             .unwrap_or_else(|| "無法找到標題".to_string());
 
-        let image = select_first(&document, &[
-            "#bodywrap .pic_box img",
-            ".pic_box img",
-            ".grid img",
-        ])
-        .and_then(|el| el.value().attr("src"))
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| "placeholder.png".to_string());
+        let image = select_first(&document, &rules.cover)
+            .and_then(|el| el.value().attr("src"))
+            .map(|s| s.to_string())
+            .unwrap_or_else(|| "placeholder.png".to_string());
 
-        let download_page_href_raw = select_first(&document, &["#ads > a", "a.ads", "a[href*='down']"])
+        let download_page_href_raw = select_first(&document, &rules.download_page)
             .and_then(|el| el.value().attr("href"))
             .ok_or_else(|| DownloadError::Other("wnacg: 無法找到下載頁面連結".to_string()))?;
 
-        let download_page_href = if download_page_href_raw.starts_with("http") {
-            download_page_href_raw.to_string()
-        } else if download_page_href_raw.starts_with("//") {
-            format!("https:{}", download_page_href_raw)
-        } else {
-            format!("https://www.wnacg.com{}", download_page_href_raw)
-        };
+        let download_page_href = rules.absolute_url(download_page_href_raw);
 
         (title, image, download_page_href)
     }; // document 在此 drop，之後才 await
 
     // 順帶抓實際 ZIP URL，快取進 DB 省掉下載時的額外請求；失敗不中斷
-    let file_url = get_file_url(app_handle, &download_page_href)
+    let mut file_url = get_file_url(app_handle, &download_page_href)
         .await
         .unwrap_or_default();
 
     // Range 探測：驗證連結真的能下載並取得檔案大小
     let mut file_size: i64 = -1;
-    let mut db_status = "idle".to_string();
+    let mut rebuilt = false;
     if file_url.is_empty() {
         tracing::warn!("fetch_payload_details: 無法預取 file_url，下載時將重新抓取");
     } else {
         match probe_file_size(client, &file_url).await {
-            Ok(size) => file_size = size,
+            Ok((size, _)) => file_size = size,
             Err(DownloadError::NotFound) => {
-                // 預檢就確定 ZIP 連結已失效，直接標 not_found
-                tracing::warn!("fetch_payload_details: ZIP 連結預檢 404/410: {}", file_url);
-                db_status = "not_found".to_string();
+                // 預檢就確定 ZIP 連結已失效：仍以 idle 加入，下載時改以逐頁圖片重建
+                tracing::warn!(
+                    "fetch_payload_details: ZIP 連結預檢 404/410，改以圖片重建: {}",
+                    file_url
+                );
+                file_url.clear();
+                rebuilt = true;
             }
             Err(e) => {
                 // 暫時性失敗，大小未知，仍以 idle 加入、下載時再試
@@ -217,128 +434,374 @@ pub async fn fetch_payload_details(
         file_url,
         file_size,
         created_at,
-        db_status,
+        db_status: "idle".to_string(),
+        save_path: String::new(),
+        downloaded_bytes: 0,
+        segments: String::new(),
+        page_count: 0,
+        tags: Vec::new(),
+        rebuilt,
+        attempts: Vec::new(),
     })
 }
 
-pub async fn download(
+/// 下載到 `{save_path}.part`，完成後改名成 save_path。失敗或取消時 .part 與
+/// part 的分段進度保留（呼叫端持久化到 tasks 表），下次接著下載。
+/// 每次開跑先探測連結：CDN 支援 Range 且檔案夠大時沿用直鏈的多段並行下載，
+/// 否則單連線串流。
+#[allow(clippy::too_many_arguments)]
+pub async fn download_file(
     client: &reqwest::Client,
     app_handle: &AppHandle,
     source_url: String, // 原始網頁網址 (用於進度事件辨識)
     file_url: String,   // 實際檔案下載網址
     save_path: PathBuf,
+    part: Arc<PartState>,
     cancelled: Arc<AtomicBool>,
-    bandwidth_limit_bps: Arc<AtomicU64>, // 0 = 無限制
+    throttle: Throttle,
 ) -> Result<(), DownloadError> {
-    let resp = client.get(&file_url).send().await?;
+    let part_path = utils::fs::part_path(&save_path);
+
+    let (size, ranged) = probe_file_size(client, &file_url).await?;
+    let total = u64::try_from(size).unwrap_or(0);
+    if !part_path.exists() || !part.fits(total, ranged) {
+        let split = should_split(ranged, throttle.cap());
+        let max = app_handle.state::<SettingsState>().get().download_segments;
+        part.replace(plan_segments(total, split, max));
+        if part.segment_count() > 1 {
+            // 多段各寫各的偏移，先預配置整個檔案
+            let file = tokio::fs::File::create(&part_path).await?;
+            file.set_len(total).await?;
+        }
+    }
 
-    if matches!(resp.status(), reqwest::StatusCode::NOT_FOUND | reqwest::StatusCode::GONE) {
-        return Err(DownloadError::NotFound);
+    if part.segment_count() > 1 {
+        download_segmented(
+            client,
+            app_handle,
+            &source_url,
+            &file_url,
+            &save_path,
+            &part,
+            total,
+            &cancelled,
+            &throttle,
+        )
+        .await
+    } else {
+        let written = part.segments.lock().unwrap()[0].written.clone();
+        download_single(
+            client, app_handle, source_url, file_url, save_path, written, cancelled, throttle,
+        )
+        .await
     }
-    if !resp.status().is_success() {
-        return Err(DownloadError::Other(format!(
-            "下載失敗 status: {}, Url: {}",
-            resp.status(),
-            file_url
+}
+
+/// 多段並行：借用直鏈的分段引擎，旁邊每 250ms 發一次進度事件
+#[allow(clippy::too_many_arguments)]
+async fn download_segmented(
+    client: &reqwest::Client,
+    app_handle: &AppHandle,
+    source_url: &str,
+    file_url: &str,
+    save_path: &Path,
+    part: &PartState,
+    total: u64,
+    cancelled: &Arc<AtomicBool>,
+    throttle: &Throttle,
+) -> Result<(), DownloadError> {
+    let part_path = utils::fs::part_path(save_path);
+    let hosts = app_handle.state::<AppState>().hosts.clone();
+    let sources = Mutex::new(vec![Source::new(file_url.to_string())]);
+    let workers = app_handle
+        .state::<SettingsState>()
+        .get()
+        .download_segments
+        .clamp(1, MAX_SEGMENTS);
+    let mut manager = DownloadManager::new();
+    manager.start_download(total, part.downloaded());
+
+    let work = download_segments(
+        client,
+        &sources,
+        None,
+        &part_path,
+        &part.segments,
+        true,
+        workers,
+        cancelled,
+        throttle,
+        &hosts,
+    );
+    tokio::pin!(work);
+    let mut tick = tokio::time::interval(std::time::Duration::from_millis(250));
+    let result = loop {
+        tokio::select! {
+            r = &mut work => break r,
+            _ = tick.tick() => {
+                emit_progress(app_handle, source_url, &manager, part.downloaded(), total);
+            }
+        }
+    };
+    result?;
+    if cancelled.load(Ordering::Relaxed) {
+        return Err(DownloadError::Cancelled);
+    }
+
+    let downloaded = part.downloaded();
+    if downloaded < total {
+        return Err(DownloadError::Network(format!(
+            "下載不完整（{}/{} bytes），可續傳",
+            downloaded, total
         )));
     }
 
-    let total_size = resp.content_length().unwrap_or(0);
-
-    // 串流寫檔包進 async block：任何失敗（含取消）統一在外層刪除殘檔，
-    // 避免半截 .zip 留在下載目錄被 monitor 的存在檢查誤判
-    let result: Result<(), DownloadError> = async {
-        // tokio::fs 非阻塞寫檔：同步 I/O 會卡住 async runtime 的 worker thread，
-        // 慢碟時拖累同 runtime 上的 BT stats / 直鏈下載 / IPC
-        let mut file = tokio::fs::File::create(&save_path).await?;
-        let mut downloaded: u64 = 0;
-        let mut stream = resp.bytes_stream();
-        let mut manager = DownloadManager::new();
-        manager.start_download(total_size);
-
-        let mut throttle_downloaded: u64 = 0;
-        let mut throttle_start = std::time::Instant::now();
-        let mut last_limit = bandwidth_limit_bps.load(Ordering::Relaxed);
-        let mut last_emit = std::time::Instant::now();
-        let emit_interval = std::time::Duration::from_millis(250);
-
-        while let Some(chunk) = stream.next().await {
-            if cancelled.load(Ordering::Relaxed) {
-                return Err(DownloadError::Cancelled);
-            }
+    tokio::fs::rename(&part_path, save_path).await?;
+    emit_progress(app_handle, source_url, &manager, downloaded, total);
+    Ok(())
+}
 
-            let chunk = chunk?;
-            file.write_all(&chunk).await?;
-            downloaded += chunk.len() as u64;
+/// 單連線串流。written 為 .part 已寫入的位元組數：> 0 時以
+/// `Range: bytes=N-` 續傳；伺服器不支援續傳（回 200）就從頭寫。
+#[allow(clippy::too_many_arguments)]
+async fn download_single(
+    client: &reqwest::Client,
+    app_handle: &AppHandle,
+    source_url: String, // 原始網頁網址 (用於進度事件辨識)
+    file_url: String,   // 實際檔案下載網址
+    save_path: PathBuf,
+    written: Arc<AtomicU64>,
+    cancelled: Arc<AtomicBool>,
+    throttle: Throttle,
+) -> Result<(), DownloadError> {
+    let part_path = utils::fs::part_path(&save_path);
 
-            let current_limit = bandwidth_limit_bps.load(Ordering::Relaxed);
-            if current_limit != last_limit {
-                throttle_start = std::time::Instant::now();
-                throttle_downloaded = 0;
-                last_limit = current_limit;
-            }
-            throttle_downloaded += chunk.len() as u64;
+    // 續傳起點：DB 記錄與 .part 實際長度取小（兩者可能差最後一次落地的間隔）
+    let on_disk = tokio::fs::metadata(&part_path)
+        .await
+        .map(|m| m.len())
+        .unwrap_or(0);
+    let mut offset = written.load(Ordering::Relaxed).min(on_disk);
 
-            if current_limit > 0 {
-                let expected = std::time::Duration::from_secs_f64(
-                    throttle_downloaded as f64 / current_limit as f64,
-                );
-                let actual = throttle_start.elapsed();
-                if expected > actual {
-                    // 限速 sleep 切小段，期間仍能即時回應取消
-                    let mut remaining = expected - actual;
-                    let step = std::time::Duration::from_millis(250);
-                    while remaining > std::time::Duration::ZERO {
-                        if cancelled.load(Ordering::Relaxed) {
-                            return Err(DownloadError::Cancelled);
-                        }
-                        let d = remaining.min(step);
-                        tokio::time::sleep(d).await;
-                        remaining -= d;
-                    }
-                }
-            }
+    // 與分段路徑一樣守每主機連線上限與 429 暫停；permit 持有到串流結束
+    let limiter = app_handle.state::<AppState>().hosts.clone();
+    let host = hosts::host_of(&file_url);
+    let Some(_permit) = limiter
+        .acquire(&host, || cancelled.load(Ordering::Relaxed))
+        .await
+    else {
+        return Err(DownloadError::Cancelled);
+    };
 
-            // 節流：每 250ms 發一次進度事件；emit 失敗只記錄，不中斷下載
-            if last_emit.elapsed() >= emit_interval {
-                let metrics = manager.calculate_metrics(downloaded, total_size);
-                if let Err(e) = app_handle.emit(
-                    "download_progress",
-                    DownloadProgress {
-                        url: source_url.clone(),
-                        progress: metrics.percentage,
-                        speed_bytes_per_sec: metrics.speed_bytes_per_sec,
-                        time_remaining_secs: metrics.time_remaining_secs,
-                    },
-                ) {
-                    tracing::warn!("進度事件 emit 失敗: {}", e);
-                }
-                last_emit = std::time::Instant::now();
-            }
+    let mut resp = send_from(client, &file_url, offset).await?;
+    if offset > 0 && resp.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE {
+        // 續傳位置超出檔案（遠端檔案變了），整檔重來
+        tracing::warn!("續傳位置無效，從頭下載: {}", source_url);
+        offset = 0;
+        resp = send_from(client, &file_url, 0).await?;
+    }
+
+    if !resp.status().is_success() {
+        // 429 時暫停這個主機，重試會等到 Retry-After 之後
+        limiter.back_off(&host, &resp);
+        return Err(DownloadError::from_response(&resp));
+    }
+
+    let resumed = offset > 0 && resp.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+    if resumed {
+        // 回傳區段要從要求的位置開始，否則接上去的資料是錯的
+        let range_start = resp
+            .headers()
+            .get(reqwest::header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|cr| cr.trim().strip_prefix("bytes "))
+            .and_then(|cr| cr.split('-').next())
+            .and_then(|s| s.trim().parse::<u64>().ok());
+        if range_start != Some(offset) {
+            written.store(0, Ordering::Relaxed);
+            return Err(DownloadError::Integrity(
+                "續傳區段不符，將從頭下載".to_string(),
+            ));
         }
+    } else {
+        // 伺服器忽略 Range（回 200）：整檔重來
+        offset = 0;
+    }
 
-        file.flush().await?;
-
-        // 下載完成後補發最終進度（確保前端顯示 100%）
-        let metrics = manager.calculate_metrics(downloaded, total_size);
-        if let Err(e) = app_handle.emit(
-            "download_progress",
-            DownloadProgress {
-                url: source_url.clone(),
-                progress: metrics.percentage,
-                speed_bytes_per_sec: metrics.speed_bytes_per_sec,
-                time_remaining_secs: metrics.time_remaining_secs,
-            },
-        ) {
-            tracing::warn!("進度事件 emit 失敗: {}", e);
+    let total_size = if resumed {
+        resp.headers()
+            .get(reqwest::header::CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|cr| cr.rsplit('/').next())
+            .and_then(|s| s.trim().parse::<u64>().ok())
+            .or_else(|| resp.content_length().map(|l| l + offset))
+            .unwrap_or(0)
+    } else {
+        resp.content_length().unwrap_or(0)
+    };
+
+    // tokio::fs 非阻塞寫檔：同步 I/O 會卡住 async runtime 的 worker thread，
+    // 慢碟時拖累同 runtime 上的 BT stats / 直鏈下載 / IPC
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&part_path)
+        .await?;
+    // 截掉 DB 記錄之後多寫的尾巴，從 offset 接著寫
+    file.set_len(offset).await?;
+    file.seek(std::io::SeekFrom::Start(offset)).await?;
+    written.store(offset, Ordering::Relaxed);
+
+    let mut stream = resp.bytes_stream();
+    let mut manager = DownloadManager::new();
+    manager.start_download(total_size, offset);
+
+    let mut last_emit = std::time::Instant::now();
+    let emit_interval = std::time::Duration::from_millis(250);
+
+    while let Some(chunk) = stream.next().await {
+        if cancelled.load(Ordering::Relaxed) {
+            file.flush().await?;
+            return Err(DownloadError::Cancelled);
         }
 
-        Ok(())
+        let chunk = chunk?;
+        file.write_all(&chunk).await?;
+        let downloaded =
+            written.fetch_add(chunk.len() as u64, Ordering::Relaxed) + chunk.len() as u64;
+
+        // 限速：超出全域額度就等，期間仍能即時回應取消
+        if !throttle.acquire(chunk.len() as u64, &cancelled).await {
+            file.flush().await?;
+            return Err(DownloadError::Cancelled);
+        }
+
+        // 節流：每 250ms 發一次進度事件；emit 失敗只記錄，不中斷下載
+        if last_emit.elapsed() >= emit_interval {
+            emit_progress(app_handle, &source_url, &manager, downloaded, total_size);
+            last_emit = std::time::Instant::now();
+        }
     }
-    .await;
 
-    if result.is_err() {
-        let _ = std::fs::remove_file(&save_path);
+    file.flush().await?;
+    drop(file);
+
+    // 串流提前斷線不會回 Err：大小不足時保留 .part，下次續傳
+    let downloaded = written.load(Ordering::Relaxed);
+    if total_size > 0 && downloaded < total_size {
+        return Err(DownloadError::Network(format!(
+            "下載不完整（{}/{} bytes），可續傳",
+            downloaded, total_size
+        )));
+    }
+
+    tokio::fs::rename(&part_path, &save_path).await?;
+
+    // 下載完成後補發最終進度（確保前端顯示 100%）
+    emit_progress(app_handle, &source_url, &manager, downloaded, total_size);
+
+    Ok(())
+}
+
+/// 發出下載請求；offset > 0 時帶 Range 要求續傳
+async fn send_from(
+    client: &reqwest::Client,
+    file_url: &str,
+    offset: u64,
+) -> Result<reqwest::Response, DownloadError> {
+    let mut req = client.get(file_url);
+    if offset > 0 {
+        req = req.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+    }
+    Ok(req.send().await?)
+}
+
+fn emit_progress(
+    app_handle: &AppHandle,
+    source_url: &str,
+    manager: &DownloadManager,
+    downloaded: u64,
+    total_size: u64,
+) {
+    let metrics = manager.calculate_metrics(downloaded, total_size);
+    if let Err(e) = app_handle.emit(
+        "download_progress",
+        DownloadProgress {
+            url: source_url.to_string(),
+            progress: metrics.percentage,
+            speed_bytes_per_sec: metrics.speed_bytes_per_sec,
+            time_remaining_secs: metrics.time_remaining_secs,
+        },
+    ) {
+        tracing::warn!("進度事件 emit 失敗: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bandwidth_cap_only_disables_splitting_when_tiny() {
+        assert!(should_split(true, 0));
+        assert!(should_split(true, 5 * 1024 * 1024));
+        assert!(!should_split(true, 64 * 1024));
+        assert!(!should_split(false, 0));
+    }
+
+    #[test]
+    fn parse_gallery_index_collects_pages_and_next() {
+        let html = r#"<ul>
+            <li class="gallary_item"><div class="pic_box"><a href="/photos-view-id-1.html"></a></div></li>
+            <li class="gallary_item"><div class="pic_box"><a href="/photos-view-id-2.html"></a></div></li>
+        </ul>
+        <div class="paginator"><span class="next"><a href="/photos-index-page-2-aid-9.html">後頁</a></span></div>"#;
+        let (pages, next) = parse_gallery_index(&Wnacg.default_rules(), html);
+        assert_eq!(
+            pages,
+            vec![
+                "https://www.wnacg.com/photos-view-id-1.html",
+                "https://www.wnacg.com/photos-view-id-2.html",
+            ]
+        );
+        assert_eq!(
+            next.as_deref(),
+            Some("https://www.wnacg.com/photos-index-page-2-aid-9.html")
+        );
+
+        let (pages, next) = parse_gallery_index(&Wnacg.default_rules(), "<p>empty</p>");
+        assert!(pages.is_empty());
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn parse_view_image_makes_src_absolute() {
+        let rules = Wnacg.default_rules();
+        let html = r#"<img id="picarea" src="//img.wnacg.com/data/1/01.jpg">"#;
+        assert_eq!(
+            parse_view_image(&rules, html).as_deref(),
+            Some("https://img.wnacg.com/data/1/01.jpg")
+        );
+        assert_eq!(parse_view_image(&rules, "<p></p>"), None);
+    }
+
+    #[test]
+    fn validate_accepts_any_wnacg_host() {
+        let rules = Wnacg.default_rules();
+        assert_eq!(
+            validate(&rules, "https://wnacg.com/photos-index-aid-9.html?p=1").unwrap(),
+            "https://www.wnacg.com/photos-index-aid-9.html"
+        );
+        assert!(validate(&rules, "https://m.wnacg.com/photos-index-aid-9.html").is_ok());
+        assert!(validate(&rules, "https://example.com/photos-index-aid-9.html").is_err());
+        assert!(validate(&rules, "https://www.wnacg.com/photos-view-id-1.html").is_err());
+        assert_eq!(
+            rules
+                .match_download_page("/download-index-aid-9.html")
+                .unwrap()
+                .as_deref(),
+            Some("9")
+        );
     }
-    result
 }
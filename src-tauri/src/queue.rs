@@ -0,0 +1,459 @@
+// src/queue.rs
+// 網站下載佇列：後端自己從 tasks 表挑 queued 任務，同時跑 AppSettings.site_concurrency 個。
+// 佇列狀態就是 DB 的 db_status（queued → downloading → done/paused/error/not_found），
+// 前端只負責 enqueue / 暫停 / 排序 — webview 重載或 app 重開都不會丟佇列。
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::bandwidth::Engine;
+use crate::db;
+use crate::error::DownloadError;
+use crate::http_dl::manager::{Segment, UNBOUNDED};
+use crate::library::{self, LibraryEntry};
+use crate::metadata;
+use crate::providers::{self, ClipboardPayload, DownloadOutcome, PartState};
+use crate::retry::{self, Attempt};
+use crate::settings::SettingsState;
+use crate::state::AppState;
+use crate::utils;
+
+/// 下載中任務的停止旗標。每個任務一顆，暫停/取消只影響自己。
+struct RunningTask {
+    cancelled: Arc<AtomicBool>,
+    /// 被停下後落地的 db_status：暫停 → paused，取消 → idle
+    stop_status: &'static str,
+}
+
+#[derive(Default)]
+pub struct SiteQueue {
+    /// key 為 tasks.url
+    running: Mutex<HashMap<String, RunningTask>>,
+}
+
+impl SiteQueue {
+    pub fn running(&self) -> Vec<String> {
+        self.running.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// 任務被停下後要落地的狀態（不在執行中回 None）
+    fn stop_status(&self, url: &str) -> Option<&'static str> {
+        self.running.lock().unwrap().get(url).map(|t| t.stop_status)
+    }
+}
+
+/// .part 分段進度落地 DB 的間隔（殺掉 app 最多丟這段進度）
+const PERSIST_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 推 "site-queue-state"：下載中的 url 清單與排隊數，前端據此顯示批次狀態
+pub fn emit_state(app: &AppHandle) {
+    let _ = app.emit("site-queue-state", snapshot(app));
+}
+
+pub fn snapshot(app: &AppHandle) -> serde_json::Value {
+    let running = app.state::<SiteQueue>().running();
+    let queued = db::count_queued(app).unwrap_or(0);
+    json!({ "running": running, "queued": queued })
+}
+
+/// 推 "site-task-status"：單一任務狀態變化（含完成路徑、是否由圖片重建，或錯誤 {code, message}）
+fn emit_task_status(
+    app: &AppHandle,
+    url: &str,
+    status: &str,
+    done: Option<&(String, DownloadOutcome)>,
+    error: Option<&DownloadError>,
+) {
+    let _ = app.emit(
+        "site-task-status",
+        json!({
+            "url": url,
+            "status": status,
+            "save_path": done.map(|(path, _)| path),
+            "rebuilt": done.is_some_and(|(_, o)| *o == DownloadOutcome::RebuiltFromImages),
+            "error": error,
+        }),
+    );
+}
+
+/// 把任務排進佇列並立即嘗試開跑
+pub fn enqueue(app: &AppHandle, urls: &[String]) -> Result<(), String> {
+    let queued = db::enqueue_tasks(app, urls).map_err(|e| format!("加入佇列失敗: {:?}", e))?;
+    for url in &queued {
+        emit_task_status(app, url, "queued", None, None);
+    }
+    pump(app);
+    Ok(())
+}
+
+/// 暫停整個佇列：排隊中的改回 paused，下載中的發取消（worker 收尾時標 paused）
+pub fn pause(app: &AppHandle) -> Result<(), String> {
+    let queue = app.state::<SiteQueue>();
+    // 持鎖改 DB：避免 pump 在中間把剛要改回 paused 的任務搶去開跑
+    let unqueued = {
+        let mut running = queue.running.lock().unwrap();
+        for task in running.values_mut() {
+            task.stop_status = "paused";
+            task.cancelled.store(true, Ordering::Relaxed);
+        }
+        db::unqueue_all(app).map_err(|e| format!("暫停佇列失敗: {:?}", e))?
+    };
+    for url in &unqueued {
+        emit_task_status(app, url, "paused", None, None);
+    }
+    emit_state(app);
+    Ok(())
+}
+
+/// 清空所有任務：下載中的先發取消（worker 收尾時刪 .part），再刪 DB 列
+pub fn clear(app: &AppHandle) -> Result<(), String> {
+    let queue = app.state::<SiteQueue>();
+    // 持鎖刪 DB：避免 pump 在中間把即將刪除的任務搶去開跑
+    {
+        let mut running = queue.running.lock().unwrap();
+        for task in running.values_mut() {
+            task.stop_status = "idle";
+            task.cancelled.store(true, Ordering::Relaxed);
+        }
+        db::clear_all_tasks(app).map_err(|e| format!("刪除全部任務失敗: {:?}", e))?;
+    }
+    emit_state(app);
+    Ok(())
+}
+
+/// 暫停單一任務（之後可再 enqueue）
+pub fn pause_task(app: &AppHandle, url: &str) -> Result<(), String> {
+    stop_task(app, url, "paused")
+}
+
+/// 取消單一任務，回到待下載狀態
+pub fn cancel_task(app: &AppHandle, url: &str) -> Result<(), String> {
+    stop_task(app, url, "idle")
+}
+
+/// 下載中的發取消、由 worker 收尾時寫入 status；還在排隊的直接改 status
+fn stop_task(app: &AppHandle, url: &str, status: &'static str) -> Result<(), String> {
+    let queue = app.state::<SiteQueue>();
+    let dequeued = {
+        let mut running = queue.running.lock().unwrap();
+        if let Some(task) = running.get_mut(url) {
+            task.stop_status = status;
+            task.cancelled.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
+        db::dequeue_task(app, url, status).map_err(|e| format!("更新狀態失敗: {:?}", e))?
+    };
+    if dequeued {
+        emit_task_status(app, url, status, None, None);
+        emit_state(app);
+    }
+    Ok(())
+}
+
+/// 補滿空槽：依 sort_order 取 queued 任務開跑，直到達同時下載上限。
+/// enqueue、任務結束、設定變更後都會呼叫，重複呼叫無害。
+pub fn pump(app: &AppHandle) {
+    let queue = app.state::<SiteQueue>();
+    let limit = app.state::<SettingsState>().get().site_concurrency.max(1);
+
+    loop {
+        let (task, cancelled) = {
+            let mut running = queue.running.lock().unwrap();
+            if running.len() >= limit {
+                break;
+            }
+            match db::claim_next_queued(app) {
+                Ok(Some(task)) => {
+                    let cancelled = Arc::new(AtomicBool::new(false));
+                    running.insert(
+                        task.url.clone(),
+                        RunningTask {
+                            cancelled: cancelled.clone(),
+                            stop_status: "paused",
+                        },
+                    );
+                    (task, cancelled)
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::error!("Queue: 讀取佇列失敗: {:?}", e);
+                    break;
+                }
+            }
+        };
+
+        emit_task_status(app, &task.url, "downloading", None, None);
+        let handle = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let result = run_task(&handle, &task, cancelled).await;
+            finish(&handle, &task.url, result);
+        });
+    }
+
+    emit_state(app);
+}
+
+/// 任務收尾：寫回 DB 狀態、通知前端，再補下一個
+fn finish(app: &AppHandle, url: &str, result: Result<(String, DownloadOutcome), DownloadError>) {
+    let stop_status = app
+        .state::<SiteQueue>()
+        .running
+        .lock()
+        .unwrap()
+        .remove(url)
+        .map_or("paused", |t| t.stop_status);
+    let status = match &result {
+        Ok(_) => "done",
+        Err(DownloadError::Cancelled) => stop_status,
+        Err(DownloadError::NotFound) => "not_found",
+        Err(_) => "error",
+    };
+    let db_result = match &result {
+        Ok((save_path, outcome)) => db::finish_task(
+            app,
+            url,
+            save_path,
+            *outcome == DownloadOutcome::RebuiltFromImages,
+        ),
+        Err(_) => db::update_task_status(app, url, status),
+    };
+    if let Err(e) = db_result {
+        tracing::error!("Queue: 更新任務狀態失敗: {:?}", e);
+    }
+    if let Err(e) = &result {
+        tracing::warn!("Queue: 下載失敗 {}: {}", url, e);
+    }
+    emit_task_status(
+        app,
+        url,
+        status,
+        result.as_ref().ok(),
+        result.as_ref().err(),
+    );
+
+    pump(app);
+}
+
+/// 網站下載的存檔目錄與檔名（不含 .zip）：設定的目錄（空 = 系統下載資料夾），
+/// 可選每站子資料夾；檔名套用樣板。重複檢查與挑新檔名都以此為準
+pub fn save_target(
+    app: &AppHandle,
+    task: &ClipboardPayload,
+) -> Result<(PathBuf, String), DownloadError> {
+    let settings = app.state::<SettingsState>().get();
+    let mut dir = match settings.site_download_dir.trim() {
+        "" => app
+            .path()
+            .download_dir()
+            .map_err(|e| DownloadError::Other(e.to_string()))?,
+        custom => PathBuf::from(custom),
+    };
+    let site = providers::from_url(&task.url)
+        .map(|p| p.name())
+        .unwrap_or_default();
+    if settings.site_subfolder_per_site && !site.is_empty() {
+        dir.push(site);
+    }
+    let id = providers::work_id(app, &task.url).unwrap_or_default();
+    let date = metadata::today();
+    let stem = utils::fs::render_file_stem(
+        &settings.site_filename_template,
+        &utils::fs::NameVars {
+            title: &task.title,
+            site,
+            id: &id,
+            date: &date,
+        },
+    );
+    Ok((dir, stem))
+}
+
+/// 續傳：上次的目標路徑旁還有 .part 就沿用其分段進度；否則依目錄設定與檔名樣板挑新檔名
+fn resolve_save_path(
+    app: &AppHandle,
+    task: &ClipboardPayload,
+) -> Result<(PathBuf, PartState), DownloadError> {
+    if !task.save_path.is_empty() {
+        let path = PathBuf::from(&task.save_path);
+        if utils::fs::part_path(&path).exists() {
+            let part = PartState::from_json(&task.segments);
+            if part.segment_count() == 0 && task.downloaded_bytes > 0 {
+                // 舊版只記單一連線的已寫入數
+                part.replace(vec![Segment::new(
+                    0,
+                    UNBOUNDED,
+                    task.downloaded_bytes as u64,
+                )]);
+            }
+            return Ok((path, part));
+        }
+    }
+    let (dir, stem) = save_target(app, task)?;
+    std::fs::create_dir_all(&dir)?;
+    Ok((
+        utils::fs::get_unique_save_path(dir, &stem),
+        PartState::default(),
+    ))
+}
+
+/// 落地分段配置與已寫入總數
+fn persist_part(app: &AppHandle, url: &str, part: &PartState) {
+    let _ = db::update_part_progress(app, url, part.downloaded(), &part.to_json());
+}
+
+/// 跑一個任務：下載期間每秒把 .part 分段進度落地，回傳存檔路徑與完成方式
+async fn run_task(
+    app: &AppHandle,
+    task: &ClipboardPayload,
+    cancelled: Arc<AtomicBool>,
+) -> Result<(String, DownloadOutcome), DownloadError> {
+    let (save_path, part) = resolve_save_path(app, task)?;
+    let part = Arc::new(part);
+    db::set_download_target(app, &task.url, &save_path.to_string_lossy())
+        .map_err(|e| DownloadError::Other(format!("寫入資料庫失敗: {:?}", e)))?;
+
+    // 失敗時依 AppSettings.retry 退避後在同一個 .part 上重跑；等待期間仍佔著下載槽，可暫停/取消
+    let mut attempt = 1;
+    let result = loop {
+        let result = attempt_download(app, task, &save_path, &part, cancelled.clone()).await;
+        let e = match &result {
+            Err(e) if !matches!(e, DownloadError::Cancelled) => e,
+            _ => break result,
+        };
+        let policy = app.state::<SettingsState>().get().retry;
+        let delay = policy.delay_for(e, attempt);
+        record_attempt(app, &task.url, Attempt::new(attempt, e.to_string(), delay));
+        let Some(delay) = delay else {
+            break result;
+        };
+        tracing::warn!("Queue: 下載失敗 {}，{:?} 後重試: {}", task.url, delay, e);
+        persist_part(app, &task.url, &part);
+        if !retry::wait(delay, &cancelled).await {
+            break Err(DownloadError::Cancelled);
+        }
+        attempt += 1;
+    };
+
+    // 取消 = 放棄這次下載：.part 刪掉、進度歸零；暫停與失敗則保留給下次續傳
+    if matches!(result, Err(DownloadError::Cancelled))
+        && app.state::<SiteQueue>().stop_status(&task.url) == Some("idle")
+    {
+        let _ = utils::fs::remove_part(&save_path);
+        part.replace(Vec::new());
+    }
+    if result.is_err() {
+        persist_part(app, &task.url, &part);
+    }
+    if result.is_ok() {
+        write_metadata(app, task, &save_path).await;
+        library::spawn_record(
+            app.clone(),
+            LibraryEntry {
+                kind: "site".to_string(),
+                source_url: task.url.clone(),
+                site: providers::from_url(&task.url)
+                    .map(|p| p.name().to_string())
+                    .unwrap_or_default(),
+                gallery_id: providers::work_id(app, &task.url).unwrap_or_default(),
+                title: task.title.clone(),
+                file_path: save_path.to_string_lossy().to_string(),
+                ..Default::default()
+            },
+        );
+    }
+
+    result.map(|outcome| (save_path.to_string_lossy().to_string(), outcome))
+}
+
+/// 跑一次下載（每秒落地 .part 進度）並驗證成品
+async fn attempt_download(
+    app: &AppHandle,
+    task: &ClipboardPayload,
+    save_path: &std::path::Path,
+    part: &Arc<PartState>,
+    cancelled: Arc<AtomicBool>,
+) -> Result<DownloadOutcome, DownloadError> {
+    let download = download_task(app, task, save_path.to_path_buf(), part.clone(), cancelled);
+    tokio::pin!(download);
+    let mut persist = tokio::time::interval(PERSIST_INTERVAL);
+    let result = loop {
+        tokio::select! {
+            r = &mut download => break r,
+            _ = persist.tick() => persist_part(app, &task.url, part),
+        }
+    };
+    let result = match result {
+        Ok(outcome) => verify_archive(save_path).await.map(|()| outcome),
+        Err(e) => Err(e),
+    };
+    // 驗證不過 = 檔案不可信：整個丟掉，重試或下次從頭下載
+    if matches!(result, Err(DownloadError::Integrity(_))) {
+        let _ = std::fs::remove_file(save_path);
+        part.replace(Vec::new());
+    }
+    result
+}
+
+/// 記下一次失敗並推 "site-task-attempt"（前端顯示重試倒數與紀錄）
+fn record_attempt(app: &AppHandle, url: &str, attempt: Attempt) {
+    if let Err(e) = db::push_attempt(app, url, attempt.clone()) {
+        tracing::error!("Queue: 寫入嘗試紀錄失敗: {:?}", e);
+    }
+    let _ = app.emit(
+        "site-task-attempt",
+        json!({ "url": url, "attempt": attempt }),
+    );
+}
+
+/// 完成的 ZIP 檢查中央目錄與 CRC（串流中斷又沒有 Content-Length 時只有這裡擋得住）
+async fn verify_archive(save_path: &std::path::Path) -> Result<(), DownloadError> {
+    let path = save_path.to_path_buf();
+    tokio::task::spawn_blocking(move || utils::verify::verify_zip(&path))
+        .await
+        .map_err(|e| DownloadError::Other(e.to_string()))?
+}
+
+/// 依設定附上 ComicInfo.xml 或 .json；失敗只記警告，不影響任務完成
+async fn write_metadata(app: &AppHandle, task: &ClipboardPayload, save_path: &std::path::Path) {
+    let mode = app.state::<SettingsState>().get().archive_metadata;
+    if mode == metadata::ArchiveMetadata::None {
+        return;
+    }
+    let (task, path) = (task.clone(), save_path.to_path_buf());
+    match tokio::task::spawn_blocking(move || metadata::write(mode, &task, &path)).await {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => tracing::warn!("Queue: 寫入作品資訊失敗 {}: {}", save_path.display(), e),
+        Err(e) => tracing::warn!("Queue: 寫入作品資訊失敗 {}: {}", save_path.display(), e),
+    }
+}
+
+/// 下載單一網站任務到 save_path（經由 .part）
+async fn download_task(
+    app: &AppHandle,
+    task: &ClipboardPayload,
+    save_path: PathBuf,
+    part: Arc<PartState>,
+    cancelled: Arc<AtomicBool>,
+) -> Result<DownloadOutcome, DownloadError> {
+    let state = app.state::<AppState>();
+
+    // 進度事件以 download_page_href 為 key（前端以此對應列）
+    let site = providers::from_url(&task.download_page_href).map_err(DownloadError::Other)?;
+    site.download(
+        &state.client,
+        app,
+        task.download_page_href.clone(),
+        task.file_url.clone(),
+        save_path,
+        part,
+        cancelled,
+        state.bandwidth.throttle(Engine::Site),
+    )
+    .await
+}
@@ -0,0 +1,234 @@
+// src/retry.rs
+// 下載自動重試策略（AppSettings.retry）：次數上限、指數退避、抖動、哪些錯誤類別要重試。
+// 直鏈（HttpManager::spawn_run）與網站佇列（queue::run_task）共用，每次失敗記進任務的嘗試紀錄。
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::DownloadError;
+
+/// 可自動重試的錯誤類別
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryClass {
+    /// 連線/串流中斷、逾時
+    Network,
+    /// HTTP 5xx
+    Server,
+    /// HTTP 429；至少等到伺服器給的 Retry-After
+    RateLimited,
+    /// 完成後驗證不過（ZIP 損毀、大小不符），或續傳時遠端檔案已變更
+    Integrity,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// 含第一次在內的總嘗試次數，1 = 不重試
+    pub max_attempts: u32,
+    /// 第一次重試前的等待秒數，之後每次加倍
+    pub base_delay_secs: u64,
+    /// 等待秒數上限
+    pub max_delay_secs: u64,
+    /// 抖動比例 0–1：實際等待 = 退避秒數 × (1 ± jitter) 內隨機
+    pub jitter: f64,
+    /// 要自動重試的錯誤類別
+    pub retry_on: Vec<RetryClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay_secs: 2,
+            max_delay_secs: 60,
+            jitter: 0.2,
+            retry_on: vec![
+                RetryClass::Network,
+                RetryClass::Server,
+                RetryClass::RateLimited,
+                RetryClass::Integrity,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 attempt 次（從 1 起算）失敗後是否重試；要重試回傳等待時間
+    pub fn next_delay(&self, class: Option<RetryClass>, attempt: u32) -> Option<Duration> {
+        let class = class?;
+        if attempt >= self.max_attempts || !self.retry_on.contains(&class) {
+            return None;
+        }
+        Some(self.backoff(attempt, random_unit()))
+    }
+
+    /// 依錯誤決定是否重試；429 的等待不短於 Retry-After
+    pub fn delay_for(&self, error: &DownloadError, attempt: u32) -> Option<Duration> {
+        let delay = self.next_delay(error.retry_class(), attempt)?;
+        Some(error.retry_after().map_or(delay, |after| delay.max(after)))
+    }
+
+    /// 指數退避加抖動；unit 為 [0, 1) 的隨機數
+    fn backoff(&self, attempt: u32, unit: f64) -> Duration {
+        let exp = self
+            .base_delay_secs
+            .saturating_mul(1u64 << (attempt - 1).min(20))
+            .min(self.max_delay_secs) as f64;
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 - jitter + 2.0 * jitter * unit;
+        Duration::from_secs_f64((exp * factor).max(0.0))
+    }
+}
+
+/// 每次失敗的紀錄
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attempt {
+    /// 第幾次嘗試（從 1 起算）
+    pub attempt: u32,
+    /// 失敗時間（unix 秒）
+    pub at: i64,
+    pub error: String,
+    /// 幾秒後重試；None = 不再自動重試
+    pub retry_in_secs: Option<u64>,
+}
+
+/// 嘗試紀錄只留最近這麼多筆
+pub const MAX_HISTORY: usize = 20;
+
+impl Attempt {
+    pub fn new(attempt: u32, error: String, delay: Option<Duration>) -> Self {
+        Self {
+            attempt,
+            at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0),
+            error,
+            retry_in_secs: delay.map(|d| d.as_secs_f64().ceil() as u64),
+        }
+    }
+}
+
+/// 追加一筆並截掉過舊的紀錄
+pub fn push_history(history: &mut Vec<Attempt>, attempt: Attempt) {
+    history.push(attempt);
+    if history.len() > MAX_HISTORY {
+        history.drain(..history.len() - MAX_HISTORY);
+    }
+}
+
+/// 等待 delay；stop 被設起時提早返回 false
+pub async fn wait(delay: Duration, stop: &AtomicBool) -> bool {
+    let step = Duration::from_millis(200);
+    let deadline = tokio::time::Instant::now() + delay;
+    while tokio::time::Instant::now() < deadline {
+        if stop.load(Ordering::Relaxed) {
+            return false;
+        }
+        tokio::time::sleep(step.min(deadline - tokio::time::Instant::now())).await;
+    }
+    !stop.load(Ordering::Relaxed)
+}
+
+/// [0, 1) 的隨機數（抖動用，不需密碼學強度）
+fn random_unit() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos() as u64)
+            .unwrap_or(0),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_and_caps() {
+        let policy = RetryPolicy {
+            base_delay_secs: 2,
+            max_delay_secs: 10,
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1, 0.5), Duration::from_secs(2));
+        assert_eq!(policy.backoff(2, 0.5), Duration::from_secs(4));
+        assert_eq!(policy.backoff(3, 0.5), Duration::from_secs(8));
+        assert_eq!(policy.backoff(4, 0.5), Duration::from_secs(10));
+        assert_eq!(policy.backoff(64, 0.5), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = RetryPolicy {
+            base_delay_secs: 10,
+            jitter: 0.2,
+            ..Default::default()
+        };
+        assert_eq!(policy.backoff(1, 0.0), Duration::from_secs(8));
+        assert_eq!(policy.backoff(1, 0.5), Duration::from_secs(10));
+        assert!(policy.backoff(1, 0.999) <= Duration::from_secs(12));
+        let unit = random_unit();
+        assert!((0.0..1.0).contains(&unit));
+    }
+
+    #[test]
+    fn next_delay_respects_attempts_and_classes() {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            retry_on: vec![RetryClass::Network],
+            ..Default::default()
+        };
+        assert!(policy.next_delay(Some(RetryClass::Network), 1).is_some());
+        assert!(policy.next_delay(Some(RetryClass::Network), 2).is_some());
+        assert!(policy.next_delay(Some(RetryClass::Network), 3).is_none());
+        assert!(policy.next_delay(Some(RetryClass::Server), 1).is_none());
+        assert!(policy.next_delay(None, 1).is_none());
+    }
+
+    #[test]
+    fn rate_limit_waits_at_least_retry_after() {
+        let policy = RetryPolicy {
+            base_delay_secs: 1,
+            ..Default::default()
+        };
+        let limited = DownloadError::RateLimited {
+            retry_after: Some(90),
+        };
+        assert!(policy.delay_for(&limited, 1).unwrap() >= Duration::from_secs(90));
+        assert!(policy.delay_for(&DownloadError::Forbidden, 1).is_none());
+    }
+
+    #[test]
+    fn queued_rate_limit_waits_past_max_delay() {
+        // 網站佇列與直鏈都用 AppSettings.retry 的預設值；Retry-After 比上限長時以伺服器為準
+        let policy = RetryPolicy::default();
+        let limited = DownloadError::RateLimited {
+            retry_after: Some(300),
+        };
+        for attempt in 1..policy.max_attempts {
+            assert!(policy.delay_for(&limited, attempt).unwrap() >= Duration::from_secs(300));
+        }
+        assert!(policy
+            .delay_for(&DownloadError::Other("任務不存在".to_string()), 1)
+            .is_none());
+    }
+
+    #[test]
+    fn history_keeps_latest_entries() {
+        let mut history = Vec::new();
+        for i in 1..=(MAX_HISTORY as u32 + 5) {
+            push_history(&mut history, Attempt::new(i, String::new(), None));
+        }
+        assert_eq!(history.len(), MAX_HISTORY);
+        assert_eq!(history[0].attempt, 6);
+    }
+}
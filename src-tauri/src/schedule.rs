@@ -0,0 +1,340 @@
+// src/schedule.rs
+// 時段排程（AppSettings.schedule）：依星期與時間切換總頻寬（alt-speed），
+// 進入時段時可暫停或開跑網站、直鏈、BT 三個引擎。背景每 30 秒檢查一次，
+// 生效時段變了就套用並推 "schedule-profile"；存設定時頻寬與生效時段也立即更新（不執行時段動作）。
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{Datelike, Local, Timelike};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::bandwidth::Engine;
+use crate::db;
+use crate::http_dl::manager::HttpManager;
+use crate::queue;
+use crate::settings::{AppSettings, SettingsState};
+use crate::state::AppState;
+use crate::torrent::state::{bt_limits, BtEngine};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 進入時段時對所有引擎做的事
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScheduleAction {
+    /// 只切換頻寬
+    #[default]
+    None,
+    /// 暫停網站佇列、直鏈與 BT 任務
+    PauseAll,
+    /// 開跑暫停中的網站任務、直鏈任務與未完成的 BT 任務
+    StartAll,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScheduleRule {
+    pub name: String,
+    /// 星期幾（1 = 週一 … 7 = 週日），空 = 每天
+    pub days: Vec<u8>,
+    /// 開始時間 "HH:MM"（含）
+    pub start: String,
+    /// 結束時間 "HH:MM"（不含）；早於開始時間表示跨午夜，等於開始時間表示整天
+    pub end: String,
+    /// 時段內的總頻寬 KB/s，0 = 不限；None = 沿用 bandwidth_limit_kbps
+    pub bandwidth_limit_kbps: Option<u64>,
+    pub action: ScheduleAction,
+}
+
+impl Default for ScheduleRule {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            days: Vec::new(),
+            start: "00:00".to_string(),
+            end: "00:00".to_string(),
+            bandwidth_limit_kbps: None,
+            action: ScheduleAction::None,
+        }
+    }
+}
+
+impl ScheduleRule {
+    /// weekday 1–7（週一起算）、minute 為當天第幾分鐘
+    fn matches(&self, weekday: u8, minute: u16) -> bool {
+        let (Some(start), Some(end)) = (parse_hm(&self.start), parse_hm(&self.end)) else {
+            return false;
+        };
+        let on = |day: u8| self.days.is_empty() || self.days.contains(&day);
+        if start == end {
+            return on(weekday);
+        }
+        if start < end {
+            return on(weekday) && (start..end).contains(&minute);
+        }
+        // 跨午夜：午夜後那段算前一天的時段
+        let yesterday = if weekday == 1 { 7 } else { weekday - 1 };
+        (minute >= start && on(weekday)) || (minute < end && on(yesterday))
+    }
+}
+
+/// 時段規則依序比對，第一個符合的生效
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Schedule {
+    pub enabled: bool,
+    pub rules: Vec<ScheduleRule>,
+}
+
+/// 目前生效的設定檔
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ActiveProfile {
+    /// 生效規則的索引，None = 沒有時段符合（一般設定）
+    pub rule: Option<usize>,
+    pub name: Option<String>,
+    /// 實際套用的總頻寬 KB/s，0 = 不限
+    pub bandwidth_limit_kbps: u64,
+    pub action: ScheduleAction,
+}
+
+/// "HH:MM" → 當天第幾分鐘
+fn parse_hm(s: &str) -> Option<u16> {
+    let (h, m) = s.trim().split_once(':')?;
+    let (h, m): (u16, u16) = (h.parse().ok()?, m.parse().ok()?);
+    (h < 24 && m < 60).then_some(h * 60 + m)
+}
+
+fn profile_at(settings: &AppSettings, weekday: u8, minute: u16) -> ActiveProfile {
+    let schedule = &settings.schedule;
+    let hit = schedule
+        .enabled
+        .then(|| {
+            schedule
+                .rules
+                .iter()
+                .position(|r| r.matches(weekday, minute))
+        })
+        .flatten();
+    match hit {
+        Some(i) => {
+            let rule = &schedule.rules[i];
+            ActiveProfile {
+                rule: Some(i),
+                name: Some(rule.name.clone()),
+                bandwidth_limit_kbps: rule
+                    .bandwidth_limit_kbps
+                    .unwrap_or(settings.bandwidth_limit_kbps),
+                action: rule.action,
+            }
+        }
+        None => ActiveProfile {
+            rule: None,
+            name: None,
+            bandwidth_limit_kbps: settings.bandwidth_limit_kbps,
+            action: ScheduleAction::None,
+        },
+    }
+}
+
+/// 依本機時間算目前生效的設定檔
+pub fn current(settings: &AppSettings) -> ActiveProfile {
+    let now = Local::now();
+    profile_at(
+        settings,
+        now.weekday().number_from_monday() as u8,
+        (now.hour() * 60 + now.minute()) as u16,
+    )
+}
+
+/// 最近一次套用的設定檔（前端開啟時查詢用）
+#[derive(Default)]
+pub struct ScheduleState {
+    active: Mutex<Option<ActiveProfile>>,
+}
+
+impl ScheduleState {
+    pub fn get(&self) -> Option<ActiveProfile> {
+        self.active.lock().unwrap().clone()
+    }
+}
+
+/// 依目前時段把總頻寬與份額套到全域限速器與執行中的 BT session
+pub fn apply_limits(app: &AppHandle, settings: &AppSettings) {
+    let state = app.state::<AppState>();
+    let kbps = current(settings).bandwidth_limit_kbps;
+    state
+        .bandwidth
+        .configure(kbps * 1024, settings.bandwidth_shares);
+    if let Some(Ok(ts)) = app.try_state::<BtEngine>().map(|e| e.get()) {
+        ts.apply_limits(bt_limits(
+            &settings.bt,
+            state.bandwidth.engine_cap(Engine::Bt),
+        ));
+    }
+}
+
+/// 比對目前生效的時段，變了就套用頻寬、記進 ScheduleState 並推 "schedule-profile"。
+/// 回傳新進入的時段（規則換了），由呼叫端決定要不要執行時段動作；第一次套用不算進入
+pub fn refresh(app: &AppHandle, settings: &AppSettings) -> Option<ActiveProfile> {
+    let profile = current(settings);
+    let state = app.state::<ScheduleState>();
+    let previous = state.get();
+    if previous.as_ref() == Some(&profile) {
+        return None;
+    }
+    apply_limits(app, settings);
+    tracing::info!(
+        "Schedule: 切換至 {}（{} KB/s）",
+        profile.name.as_deref().unwrap_or("一般"),
+        profile.bandwidth_limit_kbps
+    );
+    *state.active.lock().unwrap() = Some(profile.clone());
+    let _ = app.emit("schedule-profile", &profile);
+    previous.filter(|p| p.rule != profile.rule).map(|_| profile)
+}
+
+/// 背景排程：每 30 秒比對一次生效時段，進入新時段時執行其動作。
+/// 啟動時只套用頻寬不執行動作（動作只在時段切換的當下觸發）
+pub fn spawn(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let settings = app.state::<SettingsState>().get();
+            if let Some(profile) = refresh(&app, &settings) {
+                run_action(&app, profile.action).await;
+            }
+        }
+    });
+}
+
+async fn run_action(app: &AppHandle, action: ScheduleAction) {
+    match action {
+        ScheduleAction::None => {}
+        ScheduleAction::PauseAll => {
+            if let Err(e) = queue::pause(app) {
+                tracing::warn!("Schedule: 暫停網站佇列失敗: {}", e);
+            }
+            if let Some(http) = app.try_state::<Arc<HttpManager>>() {
+                http.pause_all();
+            }
+            set_torrents_paused(app, true).await;
+        }
+        ScheduleAction::StartAll => {
+            match db::paused_urls(app) {
+                Ok(urls) => {
+                    if let Err(e) = queue::enqueue(app, &urls) {
+                        tracing::warn!("Schedule: 開跑網站佇列失敗: {}", e);
+                    }
+                }
+                Err(e) => tracing::warn!("Schedule: 讀取暫停任務失敗: {:?}", e),
+            }
+            if let Some(http) = app.try_state::<Arc<HttpManager>>() {
+                http.resume_paused();
+            }
+            set_torrents_paused(app, false).await;
+        }
+    }
+}
+
+/// 暫停所有 BT 任務，或開跑暫停中且未完成的（已完成的不自動做種）
+async fn set_torrents_paused(app: &AppHandle, paused: bool) {
+    let Ok(ts) = app.state::<BtEngine>().get() else {
+        return;
+    };
+    for d in ts.api.api_torrent_list().torrents {
+        let Some(id) = d.id else { continue };
+        let result = if paused {
+            ts.api.api_torrent_action_pause(id.into()).await
+        } else {
+            match ts.api.api_stats_v1(id.into()) {
+                Ok(stats) if !stats.finished => ts.api.api_torrent_action_start(id.into()).await,
+                _ => continue,
+            }
+        };
+        // 已是目標狀態的任務 librqbit 會回錯誤，只記 debug
+        if let Err(e) = result {
+            tracing::debug!("Schedule: BT 任務 {} 切換失敗: {}", id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(days: &[u8], start: &str, end: &str) -> ScheduleRule {
+        ScheduleRule {
+            days: days.to_vec(),
+            start: start.to_string(),
+            end: end.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parses_hours_and_minutes() {
+        assert_eq!(parse_hm("01:30"), Some(90));
+        assert_eq!(parse_hm(" 23:59 "), Some(1439));
+        assert_eq!(parse_hm("24:00"), None);
+        assert_eq!(parse_hm("8"), None);
+    }
+
+    #[test]
+    fn windows_respect_days_and_midnight() {
+        let work = rule(&[1, 2, 3, 4, 5], "09:00", "18:00");
+        assert!(work.matches(1, 9 * 60));
+        assert!(!work.matches(1, 18 * 60));
+        assert!(!work.matches(6, 10 * 60));
+
+        // 週五 23:00 起跨到週六 07:00
+        let night = rule(&[5], "23:00", "07:00");
+        assert!(night.matches(5, 23 * 60 + 30));
+        assert!(night.matches(6, 60));
+        assert!(!night.matches(5, 60));
+        assert!(!night.matches(6, 23 * 60 + 30));
+
+        assert!(rule(&[], "00:00", "00:00").matches(3, 720));
+        assert!(!rule(&[], "bad", "07:00").matches(3, 0));
+    }
+
+    #[test]
+    fn first_matching_rule_sets_bandwidth() {
+        let settings = AppSettings {
+            bandwidth_limit_kbps: 500,
+            schedule: Schedule {
+                enabled: true,
+                rules: vec![
+                    ScheduleRule {
+                        name: "night".to_string(),
+                        bandwidth_limit_kbps: Some(0),
+                        ..rule(&[], "01:00", "08:00")
+                    },
+                    ScheduleRule {
+                        name: "work".to_string(),
+                        bandwidth_limit_kbps: Some(200),
+                        ..rule(&[1, 2, 3, 4, 5], "00:00", "00:00")
+                    },
+                ],
+            },
+            ..Default::default()
+        };
+        let p = profile_at(&settings, 2, 120);
+        assert_eq!((p.rule, p.bandwidth_limit_kbps), (Some(0), 0));
+        let p = profile_at(&settings, 2, 600);
+        assert_eq!((p.rule, p.bandwidth_limit_kbps), (Some(1), 200));
+        let p = profile_at(&settings, 7, 600);
+        assert_eq!((p.rule, p.bandwidth_limit_kbps), (None, 500));
+
+        let disabled = AppSettings {
+            schedule: Schedule {
+                enabled: false,
+                ..settings.schedule.clone()
+            },
+            ..settings
+        };
+        assert_eq!(profile_at(&disabled, 2, 120).rule, None);
+    }
+}
@@ -7,6 +7,14 @@ use std::sync::Mutex;
 
 use serde::{Deserialize, Serialize};
 
+use crate::bandwidth::BandwidthShares;
+use crate::hosts::HostLimits;
+use crate::http_dl::headers::HeaderProfile;
+use crate::http_dl::manager::DEFAULT_SEGMENTS;
+use crate::metadata::ArchiveMetadata;
+use crate::proxy::ProxySettings;
+use crate::retry::RetryPolicy;
+use crate::schedule::Schedule;
 use crate::torrent::settings::BtSettings;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,11 +22,35 @@ use crate::torrent::settings::BtSettings;
 pub struct AppSettings {
     /// 剪貼簿監控開關（啟動時套用到 AppState.monitor_paused）
     pub monitor_clipboard: bool,
-    /// 網站下載頻寬限制 KB/s，0 = 不限（啟動時套用到 AppState.bandwidth_limit_bps）
+    /// 總頻寬限制 KB/s（網站、直鏈、BT 合計），0 = 不限（即時生效）
     pub bandwidth_limit_kbps: u64,
+    /// 各引擎最多可用總頻寬的比例（%）
+    pub bandwidth_shares: BandwidthShares,
+    /// 時段排程：依時間切換總頻寬、暫停或開跑所有引擎
+    pub schedule: Schedule,
+    /// 網站下載佇列同時進行的任務數（即時生效）
+    pub site_concurrency: usize,
+    /// 每主機的同時連線數與請求間隔（直鏈與網站整包下載，即時生效）
+    pub host_limits: HostLimits,
+    /// 單一檔案的並行連線數（直鏈任務未指定時與網站整包下載用，新開跑的下載生效）
+    pub download_segments: usize,
+    /// 直鏈請求依主機自動帶的 header 與 cookie（Referer、登入 cookie…，即時生效）
+    pub header_profiles: Vec<HeaderProfile>,
+    /// 代理（網站、直鏈即時生效；BT 僅 SOCKS5，變更時重建 session）
+    pub proxy: ProxySettings,
     /// 直鏈下載預設目錄，空 = 系統下載資料夾
     pub http_default_dir: String,
-    /// BT 設定（port/限速重啟生效，預設目錄即時生效）
+    /// 網站下載存放目錄，空 = 系統下載資料夾
+    pub site_download_dir: String,
+    /// 網站下載檔名樣板（不含 .zip）：{title} {site} {id} {date}
+    pub site_filename_template: String,
+    /// 網站下載依站台分子資料夾（{目錄}/{site}/）
+    pub site_subfolder_per_site: bool,
+    /// 網站下載完成後附上作品資訊：none / comic_info（寫進 ZIP）/ sidecar（同名 .json）
+    pub archive_metadata: ArchiveMetadata,
+    /// 網站下載與直鏈下載的自動重試策略（即時生效）
+    pub retry: RetryPolicy,
+    /// BT 設定（port 重啟生效，限速與預設目錄即時生效）
     pub bt: BtSettings,
     /// 工作需求遊戲設定分頁掃描的根目錄（code.*.php 所在）
     pub jin_roots: Vec<String>,
@@ -35,7 +67,19 @@ impl Default for AppSettings {
         Self {
             monitor_clipboard: true,
             bandwidth_limit_kbps: 0,
+            bandwidth_shares: BandwidthShares::default(),
+            schedule: Schedule::default(),
+            site_concurrency: 2,
+            host_limits: HostLimits::default(),
+            download_segments: DEFAULT_SEGMENTS,
+            header_profiles: Vec::new(),
+            proxy: ProxySettings::default(),
             http_default_dir: String::new(),
+            site_download_dir: String::new(),
+            site_filename_template: "{title}".to_string(),
+            site_subfolder_per_site: false,
+            archive_metadata: ArchiveMetadata::None,
+            retry: RetryPolicy::default(),
             bt: BtSettings::default(),
             jin_roots: DEFAULT_JIN_ROOTS.iter().map(|s| s.to_string()).collect(),
         }
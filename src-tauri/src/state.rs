@@ -1,35 +1,44 @@
 // src/state.rs
 use reqwest::Client;
 use rusqlite::Connection;
-use std::sync::{
-    atomic::{AtomicBool, AtomicU64},
-    Arc, Mutex,
-};
+use std::sync::{atomic::AtomicBool, Arc, Mutex};
 use std::time::Duration;
 
+use crate::bandwidth::{BandwidthLimiter, Engine};
+use crate::cookies::CookieJar;
+use crate::hosts::HostLimiter;
+use crate::proxy::ProxyRouter;
+
 pub struct AppState {
     pub db: Mutex<Connection>,
     pub client: Client,
     pub monitor_running: Arc<AtomicBool>,
     pub monitor_paused: Arc<AtomicBool>,
-    pub download_cancelled: Arc<AtomicBool>,
-    pub bandwidth_limit_bps: Arc<AtomicU64>, // 0 = 無限制
+    pub bandwidth: Arc<BandwidthLimiter>, // 網站、直鏈、BT 共用
+    pub hosts: Arc<HostLimiter>,          // 網站、直鏈共用的每主機連線限制
+    pub cookies: Arc<CookieJar>,          // 網站、直鏈共用的登入 cookie
+    pub proxy: Arc<ProxyRouter>,          // 網站、直鏈共用的代理路由
 }
 
 impl AppState {
-    pub fn new(db: Connection, monitor_running: Arc<AtomicBool>) -> Self {
+    pub fn new(db: Connection, monitor_running: Arc<AtomicBool>, cookies: Arc<CookieJar>) -> Self {
+        let proxy = Arc::new(ProxyRouter::default());
         Self {
             db: Mutex::new(db),
             client: Client::builder()
                 .connect_timeout(Duration::from_secs(30))
                 // 單次 read 間隔超時，避免 server 停止傳輸時串流永久卡住
                 .read_timeout(Duration::from_secs(30))
+                .cookie_provider(cookies.clone())
+                .proxy(proxy.reqwest_proxy(Engine::Site))
                 .build()
                 .expect("failed to build reqwest client"),
             monitor_running,
             monitor_paused: Arc::new(AtomicBool::new(false)),
-            download_cancelled: Arc::new(AtomicBool::new(false)),
-            bandwidth_limit_bps: Arc::new(AtomicU64::new(0)),
+            bandwidth: Arc::new(BandwidthLimiter::default()),
+            hosts: Arc::new(HostLimiter::default()),
+            cookies,
+            proxy,
         }
     }
 }
@@ -7,6 +7,8 @@ use serde_json::{json, Value};
 use tauri::{AppHandle, Manager, State};
 
 use super::state::{BtEngine, PendingAdd};
+use crate::error::DownloadError;
+use crate::library;
 use crate::settings::SettingsState;
 
 /// Windows 安全的資料夾名：去非法字元、尾端點/空格、保留裝置名，長度上限 120 bytes
@@ -37,8 +39,8 @@ pub(crate) fn sanitize_folder_name(name: &str) -> String {
     let upper = cleaned.to_ascii_uppercase();
     let base = upper.split('.').next().unwrap_or("");
     const RESERVED: &[&str] = &[
-        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7",
-        "COM8", "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
     ];
     if RESERVED.contains(&base) {
         cleaned.push('_');
@@ -54,13 +56,14 @@ pub async fn add_magnet_inner(
     magnet: String,
     out_dir: Option<String>,
     paused: bool,
-) -> Result<Value, String> {
+) -> Result<Value, DownloadError> {
     let state = app.state::<BtEngine>().get()?;
     let magnet = magnet.trim().to_string();
     if !magnet.starts_with("magnet:") {
-        return Err("無效的磁力連結".to_string());
+        return Err(DownloadError::Other("無效的磁力連結".to_string()));
     }
-    let parsed = Magnet::parse(&magnet).map_err(|_| "無效的磁力連結".to_string())?;
+    let parsed =
+        Magnet::parse(&magnet).map_err(|_| DownloadError::Other("無效的磁力連結".to_string()))?;
     let hash = parsed.as_id20().map(|h| h.as_string());
 
     // 重複 infohash → 回報既有任務，不重複加
@@ -80,17 +83,17 @@ pub async fn add_magnet_inner(
         }) {
             return Ok(json!({ "already_exists": true, "id": null }));
         }
+        // 已下載完成過（任務可能已從清單移除）且資料夾還在
+        let key = library::magnet_key(hash);
+        if let Some(entry) = library::find(&app, &key, "bt", &hash.to_ascii_lowercase()) {
+            return Ok(json!({ "already_downloaded": entry }));
+        }
     }
 
     // 未指定目錄時用 BT 設定的預設下載目錄
     let out_dir = out_dir
         .filter(|s| !s.trim().is_empty())
-        .unwrap_or_else(|| {
-            app.state::<SettingsState>()
-                .get()
-                .bt
-                .default_download_dir
-        });
+        .unwrap_or_else(|| app.state::<SettingsState>().get().bt.default_download_dir);
 
     // 每個任務放同名子資料夾：magnet dn= 名稱 sanitize，無 dn 用 infohash。
     // librqbit 對明確給的 output_folder 不再套自己的子資料夾，不會雙層。
@@ -163,13 +166,13 @@ pub async fn add_magnet(
     magnet: String,
     out_dir: Option<String>,
     paused: Option<bool>,
-) -> Result<Value, String> {
+) -> Result<Value, DownloadError> {
     add_magnet_inner(app, magnet, out_dir, paused.unwrap_or(false)).await
 }
 
 /// 取消抓取中 / 移除加入失敗的 pending 項
 #[tauri::command]
-pub fn remove_pending(state: State<'_, BtEngine>, key: u64) -> Result<(), String> {
+pub fn remove_pending(state: State<'_, BtEngine>, key: u64) -> Result<(), DownloadError> {
     let ts = state.get()?;
     if let Some(p) = ts.pending.lock().unwrap().remove(&key) {
         if let Some(handle) = p.handle {
@@ -180,41 +183,44 @@ pub fn remove_pending(state: State<'_, BtEngine>, key: u64) -> Result<(), String
 }
 
 #[tauri::command]
-pub async fn list_torrents(state: State<'_, BtEngine>) -> Result<Value, String> {
+pub async fn list_torrents(state: State<'_, BtEngine>) -> Result<Value, DownloadError> {
     let ts = state.get()?;
-    serde_json::to_value(ts.api.api_torrent_list()).map_err(|e| e.to_string())
+    serde_json::to_value(ts.api.api_torrent_list()).map_err(|e| DownloadError::Other(e.to_string()))
 }
 
 #[tauri::command]
-pub async fn torrent_details(state: State<'_, BtEngine>, id: usize) -> Result<Value, String> {
+pub async fn torrent_details(
+    state: State<'_, BtEngine>,
+    id: usize,
+) -> Result<Value, DownloadError> {
     let ts = state.get()?;
     let details = ts
         .api
         .api_torrent_details(id.into())
-        .map_err(|e| e.to_string())?;
-    serde_json::to_value(details).map_err(|e| e.to_string())
+        .map_err(|e| DownloadError::Other(e.to_string()))?;
+    serde_json::to_value(details).map_err(|e| DownloadError::Other(e.to_string()))
 }
 
 #[tauri::command]
-pub async fn pause_torrent(state: State<'_, BtEngine>, id: usize) -> Result<(), String> {
+pub async fn pause_torrent(state: State<'_, BtEngine>, id: usize) -> Result<(), DownloadError> {
     state
         .get()?
         .api
         .api_torrent_action_pause(id.into())
         .await
         .map(|_| ())
-        .map_err(|e| e.to_string())
+        .map_err(|e| DownloadError::Other(e.to_string()))
 }
 
 #[tauri::command]
-pub async fn resume_torrent(state: State<'_, BtEngine>, id: usize) -> Result<(), String> {
+pub async fn resume_torrent(state: State<'_, BtEngine>, id: usize) -> Result<(), DownloadError> {
     state
         .get()?
         .api
         .api_torrent_action_start(id.into())
         .await
         .map(|_| ())
-        .map_err(|e| e.to_string())
+        .map_err(|e| DownloadError::Other(e.to_string()))
 }
 
 #[tauri::command]
@@ -222,14 +228,15 @@ pub async fn delete_torrent(
     state: State<'_, BtEngine>,
     id: usize,
     delete_files: bool,
-) -> Result<(), String> {
+) -> Result<(), DownloadError> {
     let ts = state.get()?;
     let res = if delete_files {
         ts.api.api_torrent_action_delete(id.into()).await
     } else {
         ts.api.api_torrent_action_forget(id.into()).await
     };
-    res.map(|_| ()).map_err(|e| e.to_string())
+    res.map(|_| ())
+        .map_err(|e| DownloadError::Other(e.to_string()))
 }
 
 #[tauri::command]
@@ -6,6 +6,9 @@ use serde_json::json;
 use tauri::{AppHandle, Emitter, Manager};
 
 use super::state::BtEngine;
+use crate::bandwidth::Engine;
+use crate::library::{self, LibraryEntry};
+use crate::state::AppState;
 
 const MIB: f64 = 1024.0 * 1024.0;
 
@@ -17,6 +20,8 @@ pub fn spawn_stats_task(app: AppHandle) {
     tauri::async_runtime::spawn(async move {
         let mut was_finished: HashMap<usize, bool> = HashMap::new();
         let mut first_tick = true;
+        // BT 實際下載量記回全域頻寬（BT 本身由 librqbit 限速，其他引擎分到剩下的）
+        let mut last_fetched: Option<u64> = None;
         let mut interval = tokio::time::interval(Duration::from_secs(1));
         loop {
             interval.tick().await;
@@ -58,6 +63,19 @@ pub fn spawn_stats_task(app: AppHandle) {
                 let prev = was_finished.insert(id, stats.finished).unwrap_or(false);
                 if !first_tick && !prev && stats.finished {
                     let _ = app.emit("torrent-finished", json!({ "id": id, "name": d.name }));
+                    library::spawn_record(
+                        app.clone(),
+                        LibraryEntry {
+                            kind: "bt".to_string(),
+                            source_url: library::magnet_key(&d.info_hash),
+                            site: "bt".to_string(),
+                            gallery_id: d.info_hash.to_ascii_lowercase(),
+                            title: d.name.clone().unwrap_or_default(),
+                            file_path: d.output_folder.clone(),
+                            size: stats.total_bytes as i64,
+                            ..Default::default()
+                        },
+                    );
                 }
 
                 torrents.push(json!({
@@ -95,6 +113,12 @@ pub fn spawn_stats_task(app: AppHandle) {
                 .collect();
 
             let ss = state.api.api_session_stats();
+            if let Some(prev) = last_fetched {
+                app.state::<AppState>()
+                    .bandwidth
+                    .charge(Engine::Bt, ss.fetched_bytes.saturating_sub(prev));
+            }
+            last_fetched = Some(ss.fetched_bytes);
             let payload = json!({
                 "torrents": torrents,
                 "pending": pending,
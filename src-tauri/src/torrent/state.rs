@@ -11,6 +11,8 @@ use librqbit::{Api, Session, SessionOptions, SessionPersistenceConfig};
 use tauri::{AppHandle, Emitter, Manager};
 
 use super::settings::BtSettings;
+use crate::bandwidth::Engine;
+use crate::state::AppState;
 
 /// 背景解析 metadata 中的 magnet add。librqbit 的 add_torrent 要等
 /// metadata 抓完才返回（冷門種子可能永遠等不到），所以 add 丟到背景跑，
@@ -39,6 +41,12 @@ pub struct BtEngine {
     pub inner: RwLock<Option<Arc<TorrentState>>>,
     pub last_error: RwLock<Option<String>>,
     initializing: AtomicBool,
+    /// 同一時間只跑一個 init：restart 要等進行中的 init 收掉才建新的（兩個 session 會搶 port）
+    init_lock: tokio::sync::Mutex<()>,
+    /// restart 時遞增；init 完成時代數已變就丟掉自己建好的 session
+    generation: AtomicU64,
+    /// 目前（或進行中）session 建立時用的代理位址
+    proxy: Mutex<Option<String>>,
 }
 
 impl BtEngine {
@@ -73,16 +81,29 @@ pub fn spawn_init(app: AppHandle) {
         }
     }
     tauri::async_runtime::spawn(async move {
+        let engine = app.state::<BtEngine>();
+        let guard = engine.init_lock.lock().await;
+        let generation = engine.generation.load(Ordering::SeqCst);
         let result = async {
-            let dir = app.path().app_data_dir().context("無法取得 app data 目錄")?;
+            let dir = app
+                .path()
+                .app_data_dir()
+                .context("無法取得 app data 目錄")?;
             std::fs::create_dir_all(&dir)?;
-            let bt_settings = app.state::<crate::settings::SettingsState>().get().bt;
-            init(dir, bt_settings).await
+            let settings = app.state::<crate::settings::SettingsState>().get();
+            let cap = app.state::<AppState>().bandwidth.engine_cap(Engine::Bt);
+            let proxy = settings.proxy.bt_url();
+            *engine.proxy.lock().unwrap() = proxy.clone();
+            init(dir, settings.bt, cap, proxy).await
         }
         .await;
 
-        let engine = app.state::<BtEngine>();
         match result {
+            // 初始化期間使用者改了代理：這個 session 用的是舊設定，收掉交給 restart 重建
+            Ok(ts) if engine.generation.load(Ordering::SeqCst) != generation => {
+                tracing::info!("BT: 代理已變更，捨棄舊設定建立的 session");
+                ts.session.stop().await;
+            }
             Ok(ts) => {
                 *engine.inner.write().unwrap() = Some(Arc::new(ts));
                 *engine.last_error.write().unwrap() = None;
@@ -96,10 +117,77 @@ pub fn spawn_init(app: AppHandle) {
             }
         }
         engine.initializing.store(false, Ordering::SeqCst);
+        drop(guard);
+    });
+}
+
+/// 重建 session：存設定後呼叫，BT 代理與目前 session 建立時相同則不動
+/// （librqbit 只在建立時讀代理）。進行中的 init 會被作廢並等它收掉，再以新設定建立。
+/// 種子由 fastresume 接回；還在解析 metadata 的 magnet 會中斷，需重新加入。
+pub fn restart(app: AppHandle) {
+    let old = {
+        let engine = app.state::<BtEngine>();
+        let proxy = app
+            .state::<crate::settings::SettingsState>()
+            .get()
+            .proxy
+            .bt_url();
+        if *engine.proxy.lock().unwrap() == proxy {
+            return;
+        }
+        engine.generation.fetch_add(1, Ordering::SeqCst);
+        let old = engine.inner.write().unwrap().take();
+        let _ = app.emit("bt-engine-status", engine.status());
+        old
+    };
+    tauri::async_runtime::spawn(async move {
+        if let Some(ts) = old {
+            for (_, pending) in ts.pending.lock().unwrap().drain() {
+                if let Some(handle) = pending.handle {
+                    handle.abort();
+                }
+            }
+            ts.session.stop().await;
+        }
+        // 等作廢的 init 收掉它的 session，port 才空得出來
+        drop(app.state::<BtEngine>().init_lock.lock().await);
+        spawn_init(app);
     });
 }
 
-pub async fn init(app_data_dir: PathBuf, settings: BtSettings) -> anyhow::Result<TorrentState> {
+/// BT 上下行限速：下行取 BT 自己的上限與全域頻寬份額（cap，0 = 不限）中較嚴者
+pub fn bt_limits(settings: &BtSettings, cap: u64) -> LimitsConfig {
+    let own = settings
+        .download_limit_bps
+        .filter(|&bps| bps > 0)
+        .map(u64::from);
+    let shared = (cap > 0).then_some(cap);
+    let download = match (own, shared) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+    LimitsConfig {
+        upload_bps: settings.upload_limit_bps.and_then(NonZeroU32::new),
+        download_bps: download.and_then(|bps| NonZeroU32::new(bps.min(u64::from(u32::MAX)) as u32)),
+    }
+}
+
+impl TorrentState {
+    /// 即時套用限速（存設定時呼叫），不需重啟 session
+    pub fn apply_limits(&self, limits: LimitsConfig) {
+        self.session
+            .ratelimits
+            .set_download_bps(limits.download_bps);
+        self.session.ratelimits.set_upload_bps(limits.upload_bps);
+    }
+}
+
+pub async fn init(
+    app_data_dir: PathBuf,
+    settings: BtSettings,
+    bandwidth_cap: u64,
+    socks_proxy_url: Option<String>,
+) -> anyhow::Result<TorrentState> {
     let session_dir = app_data_dir.join("bt-session");
     let download_dir = PathBuf::from(&settings.default_download_dir);
 
@@ -118,10 +206,9 @@ pub async fn init(app_data_dir: PathBuf, settings: BtSettings) -> anyhow::Result
             fastresume: true,
             listen_port_range: Some(listen_port_range),
             enable_upnp_port_forwarding: true,
-            ratelimits: LimitsConfig {
-                upload_bps: settings.upload_limit_bps.and_then(NonZeroU32::new),
-                download_bps: settings.download_limit_bps.and_then(NonZeroU32::new),
-            },
+            ratelimits: bt_limits(&settings, bandwidth_cap),
+            // tracker 與 peer 連線走 SOCKS5(DHT、UDP tracker 不經代理)
+            socks_proxy_url,
             ..Default::default()
         },
     )
@@ -0,0 +1,210 @@
+use std::io::Read;
+use std::path::Path;
+
+use base64::Engine as _;
+use md5::Md5;
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::DownloadError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgo {
+    Sha256,
+    Md5,
+}
+
+impl HashAlgo {
+    fn label(self) -> &'static str {
+        match self {
+            HashAlgo::Sha256 => "SHA-256",
+            HashAlgo::Md5 => "MD5",
+        }
+    }
+
+    fn hex_len(self) -> usize {
+        match self {
+            HashAlgo::Sha256 => 64,
+            HashAlgo::Md5 => 32,
+        }
+    }
+}
+
+/// 預期的檔案雜湊（hex 一律小寫）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checksum {
+    pub algo: HashAlgo,
+    pub hex: String,
+}
+
+impl Checksum {
+    fn new(algo: HashAlgo, hex: &str) -> Option<Self> {
+        let hex = hex.trim().to_ascii_lowercase();
+        (hex.len() == algo.hex_len() && hex.bytes().all(|b| b.is_ascii_hexdigit()))
+            .then_some(Checksum { algo, hex })
+    }
+
+    fn from_base64(algo: HashAlgo, value: &str) -> Option<Self> {
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(value.trim().trim_matches(':'))
+            .ok()?;
+        let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+        Checksum::new(algo, &hex)
+    }
+
+    /// 使用者輸入："sha256:…"、"md5:…"，或只給 hex（依長度判斷演算法）
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        match input.split_once(':') {
+            Some((algo, hex)) => match algo.trim().to_ascii_lowercase().replace('-', "").as_str() {
+                "sha256" => Checksum::new(HashAlgo::Sha256, hex),
+                "md5" => Checksum::new(HashAlgo::Md5, hex),
+                _ => None,
+            },
+            None => Checksum::new(HashAlgo::Sha256, input)
+                .or_else(|| Checksum::new(HashAlgo::Md5, input)),
+        }
+    }
+
+    /// 伺服器宣告的雜湊：Repr-Digest / Digest 的 sha-256 優先，其次 md5、Content-MD5
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let mut found = Vec::new();
+        for name in ["repr-digest", "digest"] {
+            for value in headers.get_all(name).iter().filter_map(|v| v.to_str().ok()) {
+                for item in value.split(',') {
+                    let Some((algo, encoded)) = item.trim().split_once('=') else {
+                        continue;
+                    };
+                    let algo = match algo.trim().to_ascii_lowercase().as_str() {
+                        "sha-256" => HashAlgo::Sha256,
+                        "md5" => HashAlgo::Md5,
+                        _ => continue,
+                    };
+                    found.extend(Checksum::from_base64(algo, encoded));
+                }
+            }
+        }
+        if let Some(value) = headers.get("content-md5").and_then(|v| v.to_str().ok()) {
+            found.extend(Checksum::from_base64(HashAlgo::Md5, value));
+        }
+        found.sort_by_key(|c| c.algo != HashAlgo::Sha256);
+        found.into_iter().next()
+    }
+
+    /// `.sha256` 檔內容：`<hex>  檔名` 一行一筆（sha256sum 格式），或只有 hex。
+    /// 多筆時取檔名相符的那筆
+    pub fn from_sidecar(text: &str, file_name: &str) -> Option<Self> {
+        let entries: Vec<(&str, Option<&str>)> = text
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let hex = parts.next()?;
+                let name = parts.next().map(|n| n.trim_start_matches('*'));
+                Some((hex, name))
+            })
+            .collect();
+        let (hex, _) = match entries.as_slice() {
+            [only] => *only,
+            _ => *entries.iter().find(|(_, name)| *name == Some(file_name))?,
+        };
+        Checksum::new(HashAlgo::Sha256, hex)
+    }
+}
+
+/// 算整個檔案的雜湊（hex 小寫）
+pub fn hash_file(path: &Path, algo: HashAlgo) -> std::io::Result<String> {
+    fn run<D: Digest>(path: &Path) -> std::io::Result<String> {
+        let mut file = std::fs::File::open(path)?;
+        let mut hasher = D::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = file.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+        }
+        Ok(hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect())
+    }
+    match algo {
+        HashAlgo::Sha256 => run::<Sha256>(path),
+        HashAlgo::Md5 => run::<Md5>(path),
+    }
+}
+
+/// 檔案雜湊須與預期相符
+pub fn verify_checksum(path: &Path, expected: &Checksum) -> Result<(), DownloadError> {
+    let actual = hash_file(path, expected.algo)?;
+    if actual != expected.hex {
+        return Err(DownloadError::ChecksumMismatch(format!(
+            "{} 應為 {}，實際為 {}",
+            expected.algo.label(),
+            expected.hex,
+            actual
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    const ABC_SHA256: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    const ABC_MD5: &str = "900150983cd24fb0d6963f7d28e17f72";
+
+    #[test]
+    fn parses_user_input_and_sidecars() {
+        assert_eq!(Checksum::parse(ABC_MD5).unwrap().algo, HashAlgo::Md5);
+        let upper = format!("SHA-256:{}", ABC_SHA256.to_uppercase());
+        assert_eq!(Checksum::parse(&upper).unwrap().hex, ABC_SHA256);
+        assert!(Checksum::parse("sha256:abc").is_none());
+
+        let listing = format!("{ABC_MD5:0>64}  other.bin\n{ABC_SHA256} *abc.bin\n");
+        assert_eq!(
+            Checksum::from_sidecar(&listing, "abc.bin").unwrap().hex,
+            ABC_SHA256
+        );
+        assert!(Checksum::from_sidecar(&listing, "missing.bin").is_none());
+        assert!(Checksum::from_sidecar(ABC_SHA256, "whatever").is_some());
+    }
+
+    #[test]
+    fn reads_digest_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "content-md5",
+            HeaderValue::from_static("kAFQmDzST7DWlj99KOF/cg=="),
+        );
+        assert_eq!(Checksum::from_headers(&headers).unwrap().hex, ABC_MD5);
+        headers.insert(
+            "digest",
+            HeaderValue::from_static(
+                "unixsum=30637, sha-256=ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=",
+            ),
+        );
+        assert_eq!(Checksum::from_headers(&headers).unwrap().hex, ABC_SHA256);
+    }
+
+    #[test]
+    fn verifies_file_hash() {
+        let dir = std::env::temp_dir().join(format!("dl-checksum-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("abc.bin");
+        std::fs::write(&path, b"abc").unwrap();
+        verify_checksum(&path, &Checksum::parse(ABC_MD5).unwrap()).unwrap();
+        let wrong = Checksum::parse(&"0".repeat(64)).unwrap();
+        assert!(matches!(
+            verify_checksum(&path, &wrong),
+            Err(DownloadError::ChecksumMismatch(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
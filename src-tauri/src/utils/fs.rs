@@ -1,14 +1,136 @@
+use regex::Regex;
 use sanitize_filename::sanitize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-pub fn get_unique_save_path(dir: PathBuf, title: &str) -> PathBuf {
-    let base_name = sanitize(title);
+/// 下載中的暫存檔：`{save_path}.part`，完成後改名成 save_path
+pub fn part_path(save_path: &Path) -> PathBuf {
+    let mut name = save_path.as_os_str().to_owned();
+    name.push(".part");
+    PathBuf::from(name)
+}
+
+/// 刪除暫存：一般是 .part 檔，逐頁打包的站台則是 .part 資料夾
+pub fn remove_part(save_path: &Path) -> std::io::Result<()> {
+    let part = part_path(save_path);
+    if part.is_dir() {
+        std::fs::remove_dir_all(part)
+    } else {
+        std::fs::remove_file(part)
+    }
+}
+
+/// 檔名樣板的替換值
+pub struct NameVars<'a> {
+    pub title: &'a str,
+    pub site: &'a str,
+    pub id: &'a str,
+    pub date: &'a str,
+}
+
+/// 套用檔名樣板（不含副檔名）：`{title}` `{site}` `{id}` `{date}`，未知的 `{...}` 原樣保留。
+/// 結果過 sanitize；樣板展開後為空則退回標題
+pub fn render_file_stem(template: &str, vars: &NameVars) -> String {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"\{(title|site|id|date)\}").unwrap());
+    let rendered = re.replace_all(template, |c: &regex::Captures| match &c[1] {
+        "title" => vars.title.to_string(),
+        "site" => vars.site.to_string(),
+        "id" => vars.id.to_string(),
+        _ => vars.date.to_string(),
+    });
+    let stem = sanitize(rendered.trim());
+    if stem.trim().is_empty() {
+        sanitize(vars.title)
+    } else {
+        stem
+    }
+}
+
+/// dir 下是否已有 `{stem}.zip` 或 `{stem}_N.zip`（get_unique_save_path 產生的變體）
+pub fn has_saved_copy(dir: &Path, stem: &str) -> bool {
+    let prefix = sanitize(stem);
+    let exact = format!("{}.zip", prefix);
+    // 精確比對 {prefix}_N.zip，避免誤擋標題為彼此前綴的不同作品
+    let numbered = Regex::new(&format!(r"^{}_\d+\.zip$", regex::escape(&prefix))).ok();
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries.filter_map(|e| e.ok()).any(|e| {
+                let name = e.file_name();
+                let name = name.to_string_lossy();
+                name == exact.as_str() || numbered.as_ref().is_some_and(|re| re.is_match(&name))
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// `{dir}/{stem}.zip`，已存在（或有下載中的 .part）時加 `_N` 後綴
+pub fn get_unique_save_path(dir: PathBuf, stem: &str) -> PathBuf {
+    let base_name = sanitize(stem);
     let mut path = dir.join(format!("{}.zip", base_name));
     let mut counter = 1;
 
-    while path.exists() {
+    // 其他任務下載中的 .part 也算佔用，避免同名作品寫進同一個暫存檔
+    while path.exists() || part_path(&path).exists() {
         path.set_file_name(format!("{}_{}.zip", base_name, counter));
         counter += 1;
     }
     path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part_path_appends_suffix() {
+        assert_eq!(
+            part_path(Path::new("dl/title.zip")),
+            PathBuf::from("dl/title.zip.part")
+        );
+    }
+
+    #[test]
+    fn unique_save_path_skips_in_progress_part() {
+        let dir = std::env::temp_dir().join(format!("dl-fs-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("title.zip.part"), b"").unwrap();
+
+        assert_eq!(
+            get_unique_save_path(dir.clone(), "title"),
+            dir.join("title_1.zip")
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn render_file_stem_fills_placeholders() {
+        let vars = NameVars {
+            title: "a/b",
+            site: "nhentai",
+            id: "123",
+            date: "2026-10-18",
+        };
+        assert_eq!(render_file_stem("{title}", &vars), "ab");
+        assert_eq!(
+            render_file_stem("[{site}-{id}] {title} {date}", &vars),
+            "[nhentai-123] ab 2026-10-18"
+        );
+        assert_eq!(render_file_stem("{other} {title}", &vars), "{other} ab");
+        assert_eq!(render_file_stem("  ", &vars), "ab");
+    }
+
+    #[test]
+    fn has_saved_copy_matches_numbered_variants_only() {
+        let dir = std::env::temp_dir().join(format!("dl-fs-copy-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("title_2.zip"), b"").unwrap();
+
+        assert!(has_saved_copy(&dir, "title"));
+        assert!(!has_saved_copy(&dir, "tit"));
+        assert!(!has_saved_copy(&dir.join("missing"), "title"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
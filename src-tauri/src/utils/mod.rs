@@ -1 +1,5 @@
+pub mod checksum;
 pub mod fs;
+#[cfg(test)]
+pub mod test_server;
+pub mod verify;
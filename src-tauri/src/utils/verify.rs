@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use crate::error::DownloadError;
+
+/// 完成後的 ZIP 驗證：讀得到中央目錄，且每個檔案解壓後 CRC 相符
+pub fn verify_zip(path: &Path) -> Result<(), DownloadError> {
+    let mut archive = zip::ZipArchive::new(std::fs::File::open(path)?)
+        .map_err(|e| DownloadError::Integrity(format!("ZIP 目錄損毀（{}）", e)))?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| {
+            DownloadError::Integrity(format!("ZIP 第 {} 個檔案無法讀取（{}）", i + 1, e))
+        })?;
+        // zip 讀到結尾時比對 CRC，不符回 io 錯誤
+        std::io::copy(&mut entry, &mut std::io::sink())
+            .map_err(|e| DownloadError::Integrity(format!("{} 內容損毀（{}）", entry.name(), e)))?;
+    }
+    Ok(())
+}
+
+/// 檔案大小須與伺服器宣告的一致
+pub fn verify_size(path: &Path, expected: u64) -> Result<(), DownloadError> {
+    let actual = std::fs::metadata(path)?.len();
+    if actual != expected {
+        return Err(DownloadError::Integrity(format!(
+            "檔案大小不符（{} / {} bytes）",
+            actual, expected
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn detects_truncated_and_corrupted_zip() {
+        let dir = std::env::temp_dir().join(format!("dl-verify-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("a.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("001.jpg", options).unwrap();
+        zip.write_all(b"0123456789").unwrap();
+        zip.finish().unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        verify_zip(&path).unwrap();
+        verify_size(&path, bytes.len() as u64).unwrap();
+
+        // 內容被改一個 byte → CRC 不符
+        let mut corrupted = bytes.clone();
+        let at = bytes.windows(10).position(|w| w == b"0123456789").unwrap();
+        corrupted[at] = b'X';
+        std::fs::write(&path, &corrupted).unwrap();
+        assert!(matches!(
+            verify_zip(&path),
+            Err(DownloadError::Integrity(_))
+        ));
+
+        // 截斷 → 找不到中央目錄
+        std::fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+        assert!(matches!(
+            verify_zip(&path),
+            Err(DownloadError::Integrity(_))
+        ));
+        assert!(matches!(
+            verify_size(&path, bytes.len() as u64),
+            Err(DownloadError::Integrity(_))
+        ));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
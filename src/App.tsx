@@ -3,7 +3,7 @@
 import React, { useCallback, useEffect, useState } from "react";
 import "./App.css";
 
-import { getAppSettings, updateAppSettings } from './lib/settingsApi';
+import { ArchiveMetadata, getAppSettings, updateAppSettings } from './lib/settingsApi';
 
 import { useTaskManager } from './hooks/useTaskManager';
 import { useClipboardMonitor } from './hooks/useClipboardMonitor';
@@ -11,11 +11,19 @@ import { useUrlDrop } from './hooks/useUrlDrop';
 import { useDownloadTasks } from './hooks/useDownloadTasks';
 import { useTorrentStats } from './hooks/useTorrentStats';
 import { useHttpStats } from './hooks/useHttpStats';
+import { useLibraryHits } from './hooks/useLibraryHits';
 import { Toolbar } from './components/Toolbar';
 import { TaskListView } from './components/TaskListView';
 import { BtView } from './components/bt/BtView';
 import { HttpView } from './components/http/HttpView';
 import { JinView } from './components/jin/JinView';
+import { SiteSettingsDialog } from './components/SiteSettingsDialog';
+import { BandwidthDialog } from './components/BandwidthDialog';
+import { ScheduleDialog } from './components/ScheduleDialog';
+import { HostLimitsDialog } from './components/HostLimitsDialog';
+import { CookiesDialog } from './components/CookiesDialog';
+import { ProxyDialog } from './components/ProxyDialog';
+import { useScheduleProfile } from './hooks/useScheduleProfile';
 
 type Tab = 'web' | 'bt' | 'http' | 'jin';
 type Theme = 'light' | 'dark';
@@ -35,17 +43,19 @@ function App() {
   const {
     tasks: downloadTasks,
     handleDownload,
+    handlePause,
     handleClearDone,
-    handleDownloadAllSequentially,
+    handleDownloadAll,
     stopBatchDownload,
     isBatchDownloading,
-    batchProgress,
+    queueState,
     reorderTasks,
   } = useDownloadTasks(tasks, removeTask);
 
   // BT / 直鏈 stats 訂閱掛 App 層，切分頁不中斷;剪貼簿 magnet 加入時播 ding
   const { stats: btStats, toasts: btToasts } = useTorrentStats(playDing);
   const { stats: httpStats, toasts: httpToasts } = useHttpStats();
+  const { toasts: libraryToasts } = useLibraryHits();
 
   const [tab, setTab] = useState<Tab>(() =>
     (localStorage.getItem("activeTab") as Tab) || "web"
@@ -73,6 +83,15 @@ function App() {
 
   // 頻寬限制持久化在 app_settings.json;後端啟動已自行套用,mount 只同步 UI
   const [bandwidthKbps, setBandwidthKbps] = useState<number>(0);
+  const [siteConcurrency, setSiteConcurrency] = useState<number>(2);
+  const [showSiteSettings, setShowSiteSettings] = useState(false);
+  const [showBandwidth, setShowBandwidth] = useState(false);
+  const [showSchedule, setShowSchedule] = useState(false);
+  const [showHostLimits, setShowHostLimits] = useState(false);
+  const [showCookies, setShowCookies] = useState(false);
+  const [showProxy, setShowProxy] = useState(false);
+  const scheduleProfile = useScheduleProfile();
+  const [archiveMetadata, setArchiveMetadata] = useState<ArchiveMetadata>("none");
   useEffect(() => {
     (async () => {
       try {
@@ -87,6 +106,8 @@ function App() {
           }
         }
         setBandwidthKbps(s.bandwidth_limit_kbps);
+        setSiteConcurrency(s.site_concurrency);
+        setArchiveMetadata(s.archive_metadata);
       } catch {}
     })();
   }, []);
@@ -102,6 +123,17 @@ function App() {
     await updateAppSettings(s => ({ ...s, bandwidth_limit_kbps: kbps }));
   }, []);
 
+  const handleConcurrencyChange = useCallback(async (n: number) => {
+    setSiteConcurrency(n);
+    // save_app_settings 會即時補滿佇列空槽
+    await updateAppSettings(s => ({ ...s, site_concurrency: n }));
+  }, []);
+
+  const handleArchiveMetadataChange = useCallback(async (mode: ArchiveMetadata) => {
+    setArchiveMetadata(mode);
+    await updateAppSettings(s => ({ ...s, archive_metadata: mode }));
+  }, []);
+
   const handleMonitorChange = useCallback(async (e: React.ChangeEvent<HTMLInputElement>) => {
     await setMonitorClipboard(e.target.checked);
   }, [setMonitorClipboard]);
@@ -189,10 +221,10 @@ function App() {
           <Toolbar
             onRemoveAll={removeAllTasks}
             onClearDone={handleClearDone}
-            onDownloadAll={handleDownloadAllSequentially}
+            onDownloadAll={handleDownloadAll}
             onStopDownload={stopBatchDownload}
             isBatchDownloading={isBatchDownloading}
-            batchProgress={batchProgress}
+            queueState={queueState}
             totalCount={downloadTasks.length}
             doneCount={doneCount}
             pendingCount={pendingCount}
@@ -200,14 +232,25 @@ function App() {
             hasDoneTasks={downloadTasks.some(t => t.status === "done")}
             bandwidthKbps={bandwidthKbps}
             onBandwidthChange={handleBandwidthChange}
+            siteConcurrency={siteConcurrency}
+            onConcurrencyChange={handleConcurrencyChange}
+            archiveMetadata={archiveMetadata}
+            onArchiveMetadataChange={handleArchiveMetadataChange}
+            onOpenSettings={() => setShowSiteSettings(true)}
+            onOpenBandwidth={() => setShowBandwidth(true)}
+            onOpenSchedule={() => setShowSchedule(true)}
+            onOpenHostLimits={() => setShowHostLimits(true)}
+            onOpenCookies={() => setShowCookies(true)}
+            onOpenProxy={() => setShowProxy(true)}
+            scheduleName={scheduleProfile?.name ?? null}
           />
           <main className="main-content">
             <TaskListView
               tasks={downloadTasks}
               onRemoveTask={removeTask}
               onDownload={handleDownload}
+              onPause={handlePause}
               onReorder={reorderTasks}
-              isBatchDownloading={isBatchDownloading}
             />
           </main>
         </>
@@ -218,8 +261,14 @@ function App() {
       ) : (
         <JinView />
       )}
+      {showSiteSettings && <SiteSettingsDialog onClose={() => setShowSiteSettings(false)} />}
+      {showBandwidth && <BandwidthDialog onClose={() => setShowBandwidth(false)} />}
+      {showSchedule && <ScheduleDialog onClose={() => setShowSchedule(false)} />}
+      {showHostLimits && <HostLimitsDialog onClose={() => setShowHostLimits(false)} />}
+      {showCookies && <CookiesDialog onClose={() => setShowCookies(false)} />}
+      {showProxy && <ProxyDialog onClose={() => setShowProxy(false)} />}
       <div className="toast-container">
-        {[...btToasts, ...httpToasts].map(t => (
+        {[...btToasts, ...httpToasts, ...libraryToasts].map(t => (
           <div key={t.key} className="toast">
             {t.text}
           </div>
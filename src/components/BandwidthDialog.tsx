@@ -0,0 +1,104 @@
+import { useEffect, useState } from "react";
+import { getAppSettings, updateAppSettings, type BandwidthShares } from "../lib/settingsApi";
+import { errorText } from "../lib/errors";
+
+interface Props {
+  onClose: () => void;
+}
+
+const ENGINES: { key: keyof BandwidthShares; label: string }[] = [
+  { key: "site", label: "網站下載" },
+  { key: "http", label: "直鏈下載" },
+  { key: "bt", label: "BT 下載" },
+];
+
+/** 總頻寬在各引擎間的分配比例 */
+export function BandwidthDialog({ onClose }: Props) {
+  const [shares, setShares] = useState<BandwidthShares | null>(null);
+  const [segments, setSegments] = useState(4);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    getAppSettings()
+      .then((s) => {
+        setShares(s.bandwidth_shares);
+        setSegments(s.download_segments);
+      })
+      .catch((e) => setError(errorText(e)));
+  }, []);
+
+  if (!shares) {
+    return (
+      <div className="modal-backdrop" onClick={onClose}>
+        <div className="modal" onClick={(e) => e.stopPropagation()}>
+          {error ? <p className="error-text">{error}</p> : <p>載入中…</p>}
+        </div>
+      </div>
+    );
+  }
+
+  async function save() {
+    setError(null);
+    try {
+      await updateAppSettings((s) => ({
+        ...s,
+        bandwidth_shares: shares!,
+        download_segments: segments,
+      }));
+      onClose();
+    } catch (e) {
+      setError(errorText(e));
+    }
+  }
+
+  return (
+    <div className="modal-backdrop" onClick={onClose}>
+      <div className="modal" onClick={(e) => e.stopPropagation()}>
+        <h2>頻寬分配</h2>
+        {ENGINES.map(({ key, label }) => (
+          <label key={key}>
+            {label}最多可用總頻寬的 %
+            <input
+              type="number"
+              min={1}
+              max={100}
+              value={shares[key]}
+              onChange={(e) => {
+                const n = parseInt(e.target.value, 10);
+                setShares({ ...shares, [key]: Number.isFinite(n) ? Math.min(100, Math.max(1, n)) : 100 });
+              }}
+            />
+          </label>
+        ))}
+        <p className="hint">
+          總頻寬限制由網站、直鏈、BT 共用,各引擎另受此比例限制;總頻寬無限制時不作用。存檔後即時生效。
+        </p>
+        <label>
+          單一檔案並行連線數(1–16)
+          <input
+            type="number"
+            min={1}
+            max={16}
+            value={segments}
+            onChange={(e) => {
+              const n = parseInt(e.target.value, 10);
+              setSegments(Number.isFinite(n) ? Math.min(16, Math.max(1, n)) : 4);
+            }}
+          />
+        </label>
+        <p className="hint">
+          伺服器支援續傳且檔案夠大時依大小切段;先做完的連線會分走剩最多那段的一半。直鏈任務可在新增時另外指定,新開跑的下載生效。
+        </p>
+        {error && <p className="error-text">{error}</p>}
+        <div className="modal-actions">
+          <button type="button" onClick={onClose}>
+            取消
+          </button>
+          <button type="button" className="btn-primary" onClick={save}>
+            儲存
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,97 @@
+import { useEffect, useState } from "react";
+import { open } from "@tauri-apps/plugin-dialog";
+import { clearCookies, importCookies, listCookies, type CookieDomain } from "../lib/settingsApi";
+import { errorText } from "../lib/errors";
+
+interface Props {
+  onClose: () => void;
+}
+
+/** 網站與直鏈下載共用的登入 cookie:匯入 cookies.txt、依網域檢視與清除 */
+export function CookiesDialog({ onClose }: Props) {
+  const [domains, setDomains] = useState<CookieDomain[] | null>(null);
+  const [message, setMessage] = useState<string | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  async function refresh() {
+    try {
+      setDomains(await listCookies());
+    } catch (e) {
+      setError(errorText(e));
+    }
+  }
+
+  useEffect(() => {
+    refresh();
+  }, []);
+
+  async function run(action: () => Promise<string>) {
+    setError(null);
+    setMessage(null);
+    try {
+      setMessage(await action());
+      await refresh();
+    } catch (e) {
+      setError(errorText(e));
+    }
+  }
+
+  async function onImport() {
+    const path = await open({ filters: [{ name: "cookies.txt", extensions: ["txt"] }] });
+    if (typeof path !== "string") return;
+    run(async () => `已匯入 ${await importCookies(path)} 筆 cookie`);
+  }
+
+  function onClear(domain?: string) {
+    const target = domain ?? "所有網域";
+    if (!window.confirm(`清除 ${target} 的 cookie?`)) return;
+    run(async () => `已清除 ${await clearCookies(domain)} 筆 cookie`);
+  }
+
+  return (
+    <div className="modal-backdrop" onClick={onClose}>
+      <div className="modal" onClick={(e) => e.stopPropagation()}>
+        <h2>登入 Cookie</h2>
+        {domains === null ? (
+          <p>載入中…</p>
+        ) : domains.length === 0 ? (
+          <p className="hint">尚未匯入任何 cookie。</p>
+        ) : (
+          domains.map((d) => (
+            <fieldset key={d.domain} className="schedule-rule">
+              <span title={d.names.join("\n")}>
+                {d.domain}({d.names.length} 筆)
+              </span>
+              <button type="button" className="btn-sm btn-danger" onClick={() => onClear(d.domain)}>
+                清除
+              </button>
+            </fieldset>
+          ))
+        )}
+        <button type="button" className="btn-sm" onClick={onImport}>
+          匯入 cookies.txt…
+        </button>
+        <p className="hint">
+          用瀏覽器擴充功能匯出 Netscape 格式的 cookies.txt 後匯入;網站下載與直鏈下載依網域自動帶上,伺服器更新的
+          cookie 也會記下。值只存在 app 資料夾,不會顯示也不寫進 log。直鏈任務或 Header 設定檔自己設定 Cookie
+          時以那個為準。
+        </p>
+        {message && <p className="hint">{message}</p>}
+        {error && <p className="error-text">{error}</p>}
+        <div className="modal-actions">
+          <button
+            type="button"
+            className="btn-danger"
+            disabled={!domains || domains.length === 0}
+            onClick={() => onClear()}
+          >
+            全部清除
+          </button>
+          <button type="button" onClick={onClose}>
+            關閉
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,145 @@
+import { useEffect, useState } from "react";
+import { getAppSettings, updateAppSettings, type HostLimits, type HostRule } from "../lib/settingsApi";
+import { errorText } from "../lib/errors";
+
+interface Props {
+  onClose: () => void;
+}
+
+const newRule = (): HostRule => ({ pattern: "", max_connections: 2, min_interval_ms: 0 });
+
+const toCount = (v: string) => {
+  const n = parseInt(v, 10);
+  return Number.isFinite(n) && n >= 0 ? n : 0;
+};
+
+/** 每主機的同時連線數、請求間隔與 429 暫停秒數 */
+export function HostLimitsDialog({ onClose }: Props) {
+  const [limits, setLimits] = useState<HostLimits | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    getAppSettings()
+      .then((s) => setLimits(s.host_limits))
+      .catch((e) => setError(errorText(e)));
+  }, []);
+
+  if (!limits) {
+    return (
+      <div className="modal-backdrop" onClick={onClose}>
+        <div className="modal" onClick={(e) => e.stopPropagation()}>
+          {error ? <p className="error-text">{error}</p> : <p>載入中…</p>}
+        </div>
+      </div>
+    );
+  }
+
+  const setRule = (i: number, patch: Partial<HostRule>) =>
+    setLimits({
+      ...limits,
+      rules: limits.rules.map((r, j) => (j === i ? { ...r, ...patch } : r)),
+    });
+
+  async function save() {
+    setError(null);
+    try {
+      const rules = limits!.rules.filter((r) => r.pattern.trim());
+      await updateAppSettings((s) => ({ ...s, host_limits: { ...limits!, rules } }));
+      onClose();
+    } catch (e) {
+      setError(errorText(e));
+    }
+  }
+
+  return (
+    <div className="modal-backdrop" onClick={onClose}>
+      <div className="modal" onClick={(e) => e.stopPropagation()}>
+        <h2>主機連線限制</h2>
+        <label>
+          預設每主機同時連線數(0 = 不限)
+          <input
+            type="number"
+            min={0}
+            value={limits.max_connections}
+            onChange={(e) => setLimits({ ...limits, max_connections: toCount(e.target.value) })}
+          />
+        </label>
+        <label>
+          預設請求間隔 ms
+          <input
+            type="number"
+            min={0}
+            value={limits.min_interval_ms}
+            onChange={(e) => setLimits({ ...limits, min_interval_ms: toCount(e.target.value) })}
+          />
+        </label>
+        <label>
+          429 未附 Retry-After 時暫停秒數
+          <input
+            type="number"
+            min={0}
+            value={limits.backoff_secs}
+            onChange={(e) => setLimits({ ...limits, backoff_secs: toCount(e.target.value) })}
+          />
+        </label>
+        {limits.rules.map((rule, i) => (
+          <fieldset key={i} className="schedule-rule">
+            <label>
+              主機
+              <input
+                type="text"
+                value={rule.pattern}
+                placeholder="例:*.example.com"
+                onChange={(e) => setRule(i, { pattern: e.target.value })}
+              />
+            </label>
+            <label>
+              同時連線數(0 = 不限)
+              <input
+                type="number"
+                min={0}
+                value={rule.max_connections}
+                onChange={(e) => setRule(i, { max_connections: toCount(e.target.value) })}
+              />
+            </label>
+            <label>
+              請求間隔 ms
+              <input
+                type="number"
+                min={0}
+                value={rule.min_interval_ms}
+                onChange={(e) => setRule(i, { min_interval_ms: toCount(e.target.value) })}
+              />
+            </label>
+            <button
+              type="button"
+              className="btn-sm btn-danger"
+              onClick={() => setLimits({ ...limits, rules: limits.rules.filter((_, j) => j !== i) })}
+            >
+              刪除
+            </button>
+          </fieldset>
+        ))}
+        <button
+          type="button"
+          className="btn-sm"
+          onClick={() => setLimits({ ...limits, rules: [...limits.rules, newRule()] })}
+        >
+          新增主機
+        </button>
+        <p className="hint">
+          直鏈與網站整包下載共用;由上而下比對,第一個符合的主機規則生效。主機回 429 時只暫停連到該主機的分段,其他主機照常下載。存檔後即時生效。
+        </p>
+        {error && <p className="error-text">{error}</p>}
+        <div className="modal-actions">
+          <button type="button" onClick={onClose}>
+            取消
+          </button>
+          <button type="button" className="btn-primary" onClick={save}>
+            儲存
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,134 @@
+import { useEffect, useState } from "react";
+import { getAppSettings, updateAppSettings, type ProxyKind, type ProxySettings } from "../lib/settingsApi";
+import { errorText } from "../lib/errors";
+
+interface Props {
+  onClose: () => void;
+}
+
+/** 代理伺服器、帳密、各引擎是否走代理與直連主機 */
+export function ProxyDialog({ onClose }: Props) {
+  const [proxy, setProxy] = useState<ProxySettings | null>(null);
+  const [bypass, setBypass] = useState("");
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    getAppSettings()
+      .then((s) => {
+        setProxy(s.proxy);
+        setBypass(s.proxy.bypass.join("\n"));
+      })
+      .catch((e) => setError(errorText(e)));
+  }, []);
+
+  if (!proxy) {
+    return (
+      <div className="modal-backdrop" onClick={onClose}>
+        <div className="modal" onClick={(e) => e.stopPropagation()}>
+          {error ? <p className="error-text">{error}</p> : <p>載入中…</p>}
+        </div>
+      </div>
+    );
+  }
+
+  const set = (patch: Partial<ProxySettings>) => setProxy({ ...proxy, ...patch });
+
+  async function save() {
+    setError(null);
+    try {
+      const next: ProxySettings = {
+        ...proxy!,
+        host: proxy!.host.trim(),
+        bypass: bypass
+          .split(/[\s,]+/)
+          .map((h) => h.trim())
+          .filter(Boolean),
+      };
+      await updateAppSettings((s) => ({ ...s, proxy: next }));
+      onClose();
+    } catch (e) {
+      setError(errorText(e));
+    }
+  }
+
+  return (
+    <div className="modal-backdrop" onClick={onClose}>
+      <div className="modal" onClick={(e) => e.stopPropagation()}>
+        <h2>代理</h2>
+        <label className="checkbox-label">
+          <input type="checkbox" checked={proxy.enabled} onChange={(e) => set({ enabled: e.target.checked })} />
+          啟用代理
+        </label>
+        <label>
+          類型
+          <select value={proxy.kind} onChange={(e) => set({ kind: e.target.value as ProxyKind })}>
+            <option value="http">HTTP</option>
+            <option value="https">HTTPS</option>
+            <option value="socks5">SOCKS5</option>
+          </select>
+        </label>
+        <label>
+          主機
+          <input
+            type="text"
+            value={proxy.host}
+            placeholder="例:proxy.office.local"
+            onChange={(e) => set({ host: e.target.value })}
+          />
+        </label>
+        <label>
+          Port
+          <input
+            type="number"
+            min={1}
+            max={65535}
+            value={proxy.port}
+            onChange={(e) => {
+              const n = parseInt(e.target.value, 10);
+              set({ port: Number.isFinite(n) && n > 0 && n <= 65535 ? n : proxy.port });
+            }}
+          />
+        </label>
+        <label>
+          帳號(留空 = 不需驗證)
+          <input type="text" value={proxy.username} onChange={(e) => set({ username: e.target.value })} />
+        </label>
+        <label>
+          密碼
+          <input type="password" value={proxy.password} onChange={(e) => set({ password: e.target.value })} />
+        </label>
+        <div className="schedule-days">
+          <label className="checkbox-label">
+            <input type="checkbox" checked={proxy.site} onChange={(e) => set({ site: e.target.checked })} />
+            網站下載
+          </label>
+          <label className="checkbox-label">
+            <input type="checkbox" checked={proxy.http} onChange={(e) => set({ http: e.target.checked })} />
+            直鏈下載
+          </label>
+          <label className="checkbox-label">
+            <input type="checkbox" checked={proxy.bt} onChange={(e) => set({ bt: e.target.checked })} />
+            BT(僅 SOCKS5)
+          </label>
+        </div>
+        <label>
+          直連主機(一行一個,例:*.intra.net)
+          <textarea rows={3} value={bypass} onChange={(e) => setBypass(e.target.value)} />
+        </label>
+        <p className="hint">
+          網站與直鏈下載存檔後即時生效,直連主機不經代理。BT 只支援 SOCKS5(tracker 與 peer 連線,DHT 不經代理),
+          變更時 BT 引擎會重新啟動,解析中的 magnet 需重新加入。
+        </p>
+        {error && <p className="error-text">{error}</p>}
+        <div className="modal-actions">
+          <button type="button" onClick={onClose}>
+            取消
+          </button>
+          <button type="button" className="btn-primary" onClick={save}>
+            儲存
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
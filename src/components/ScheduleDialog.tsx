@@ -0,0 +1,164 @@
+import { useEffect, useState } from "react";
+import {
+  getAppSettings,
+  updateAppSettings,
+  type Schedule,
+  type ScheduleAction,
+  type ScheduleRule,
+} from "../lib/settingsApi";
+import { errorText } from "../lib/errors";
+
+interface Props {
+  onClose: () => void;
+}
+
+const DAYS = ["一", "二", "三", "四", "五", "六", "日"];
+
+const newRule = (): ScheduleRule => ({
+  name: "",
+  days: [],
+  start: "01:00",
+  end: "08:00",
+  bandwidth_limit_kbps: null,
+  action: "none",
+});
+
+/** 時段排程:依時間切換總頻寬、暫停或開跑所有引擎 */
+export function ScheduleDialog({ onClose }: Props) {
+  const [schedule, setSchedule] = useState<Schedule | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    getAppSettings()
+      .then((s) => setSchedule(s.schedule))
+      .catch((e) => setError(errorText(e)));
+  }, []);
+
+  if (!schedule) {
+    return (
+      <div className="modal-backdrop" onClick={onClose}>
+        <div className="modal" onClick={(e) => e.stopPropagation()}>
+          {error ? <p className="error-text">{error}</p> : <p>載入中…</p>}
+        </div>
+      </div>
+    );
+  }
+
+  const setRule = (i: number, patch: Partial<ScheduleRule>) =>
+    setSchedule({
+      ...schedule,
+      rules: schedule.rules.map((r, j) => (j === i ? { ...r, ...patch } : r)),
+    });
+
+  const toggleDay = (i: number, day: number) => {
+    const days = schedule.rules[i].days;
+    setRule(i, {
+      days: days.includes(day) ? days.filter((d) => d !== day) : [...days, day].sort(),
+    });
+  };
+
+  async function save() {
+    setError(null);
+    try {
+      await updateAppSettings((s) => ({ ...s, schedule: schedule! }));
+      onClose();
+    } catch (e) {
+      setError(errorText(e));
+    }
+  }
+
+  return (
+    <div className="modal-backdrop" onClick={onClose}>
+      <div className="modal" onClick={(e) => e.stopPropagation()}>
+        <h2>時段排程</h2>
+        <label className="checkbox-label">
+          <input
+            type="checkbox"
+            checked={schedule.enabled}
+            onChange={(e) => setSchedule({ ...schedule, enabled: e.target.checked })}
+          />
+          啟用排程
+        </label>
+        {schedule.rules.map((rule, i) => (
+          <fieldset key={i} className="schedule-rule">
+            <label>
+              名稱
+              <input
+                type="text"
+                value={rule.name}
+                placeholder="例:夜間不限速"
+                onChange={(e) => setRule(i, { name: e.target.value })}
+              />
+            </label>
+            <div className="schedule-days">
+              {DAYS.map((d, idx) => (
+                <label key={d} className="checkbox-label">
+                  <input
+                    type="checkbox"
+                    checked={rule.days.includes(idx + 1)}
+                    onChange={() => toggleDay(i, idx + 1)}
+                  />
+                  {d}
+                </label>
+              ))}
+            </div>
+            <div className="schedule-times">
+              <input type="time" value={rule.start} onChange={(e) => setRule(i, { start: e.target.value })} />
+              <span>至</span>
+              <input type="time" value={rule.end} onChange={(e) => setRule(i, { end: e.target.value })} />
+            </div>
+            <label>
+              總頻寬 KB/s(0 = 不限,留空 = 沿用一般設定)
+              <input
+                type="number"
+                min={0}
+                value={rule.bandwidth_limit_kbps ?? ""}
+                onChange={(e) => {
+                  const n = parseInt(e.target.value, 10);
+                  setRule(i, { bandwidth_limit_kbps: Number.isFinite(n) && n >= 0 ? n : null });
+                }}
+              />
+            </label>
+            <label>
+              進入時段時
+              <select
+                value={rule.action}
+                onChange={(e) => setRule(i, { action: e.target.value as ScheduleAction })}
+              >
+                <option value="none">只切換頻寬</option>
+                <option value="pause_all">暫停所有下載</option>
+                <option value="start_all">開跑暫停中的下載</option>
+              </select>
+            </label>
+            <button
+              type="button"
+              className="btn-sm btn-danger"
+              onClick={() => setSchedule({ ...schedule, rules: schedule.rules.filter((_, j) => j !== i) })}
+            >
+              刪除
+            </button>
+          </fieldset>
+        ))}
+        <button
+          type="button"
+          className="btn-sm"
+          onClick={() => setSchedule({ ...schedule, rules: [...schedule.rules, newRule()] })}
+        >
+          新增時段
+        </button>
+        <p className="hint">
+          由上而下比對,第一個符合的時段生效;星期全不勾 = 每天。結束早於開始表示跨午夜。暫停/開跑只在進入時段的當下執行一次。
+        </p>
+        {error && <p className="error-text">{error}</p>}
+        <div className="modal-actions">
+          <button type="button" onClick={onClose}>
+            取消
+          </button>
+          <button type="button" className="btn-primary" onClick={save}>
+            儲存
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -0,0 +1,111 @@
+import { useEffect, useState } from "react";
+import { open } from "@tauri-apps/plugin-dialog";
+import { getAppSettings, updateAppSettings } from "../lib/settingsApi";
+import { errorText } from "../lib/errors";
+
+interface Props {
+  onClose: () => void;
+}
+
+interface SiteSaveSettings {
+  site_download_dir: string;
+  site_filename_template: string;
+  site_subfolder_per_site: boolean;
+}
+
+export function SiteSettingsDialog({ onClose }: Props) {
+  const [settings, setSettings] = useState<SiteSaveSettings | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    getAppSettings()
+      .then((s) =>
+        setSettings({
+          site_download_dir: s.site_download_dir,
+          site_filename_template: s.site_filename_template,
+          site_subfolder_per_site: s.site_subfolder_per_site,
+        }),
+      )
+      .catch((e) => setError(errorText(e)));
+  }, []);
+
+  if (!settings) {
+    return (
+      <div className="modal-backdrop" onClick={onClose}>
+        <div className="modal" onClick={(e) => e.stopPropagation()}>
+          {error ? <p className="error-text">{error}</p> : <p>載入中…</p>}
+        </div>
+      </div>
+    );
+  }
+
+  async function pickFolder() {
+    const dir = await open({ directory: true, defaultPath: settings!.site_download_dir || undefined });
+    if (typeof dir === "string") setSettings({ ...settings!, site_download_dir: dir });
+  }
+
+  async function save() {
+    setError(null);
+    try {
+      await updateAppSettings((s) => ({
+        ...s,
+        ...settings!,
+        site_download_dir: settings!.site_download_dir.trim(),
+        site_filename_template: settings!.site_filename_template.trim() || "{title}",
+      }));
+      onClose();
+    } catch (e) {
+      setError(errorText(e));
+    }
+  }
+
+  return (
+    <div className="modal-backdrop" onClick={onClose}>
+      <div className="modal" onClick={(e) => e.stopPropagation()}>
+        <h2>網站下載存放位置</h2>
+        <label>
+          下載目錄(留空 = 系統下載資料夾)
+          <div className="dir-picker">
+            <input
+              type="text"
+              value={settings.site_download_dir}
+              onChange={(e) => setSettings({ ...settings, site_download_dir: e.target.value })}
+            />
+            <button type="button" onClick={pickFolder}>
+              瀏覽…
+            </button>
+          </div>
+        </label>
+        <label>
+          檔名樣板
+          <input
+            type="text"
+            value={settings.site_filename_template}
+            placeholder="{title}"
+            onChange={(e) => setSettings({ ...settings, site_filename_template: e.target.value })}
+          />
+        </label>
+        <label className="checkbox-group">
+          <input
+            type="checkbox"
+            checked={settings.site_subfolder_per_site}
+            onChange={(e) => setSettings({ ...settings, site_subfolder_per_site: e.target.checked })}
+          />
+          依站台分子資料夾
+        </label>
+        <p className="hint">
+          可用 {"{title}"} {"{site}"} {"{id}"} {"{date}"},副檔名固定 .zip。剪貼簿的重複檢查也依此目錄與檔名判斷。
+        </p>
+        {error && <p className="error-text">{error}</p>}
+        <div className="modal-actions">
+          <button type="button" onClick={onClose}>
+            取消
+          </button>
+          <button type="button" className="btn-primary" onClick={save}>
+            儲存
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
@@ -18,7 +18,7 @@ import {
 import { CSS } from "@dnd-kit/utilities";
 import { DownloadableTask } from "../types";
 import { useColumnResize } from "../hooks/useColumnResize";
-import { formatBytes, formatSpeed } from "../lib/format";
+import { formatAttempts, formatBytes, formatSpeed } from "../lib/format";
 
 const formatTime = (secs: number) => {
     if (!isFinite(secs) || secs <= 0) return "計算中";
@@ -36,10 +36,10 @@ interface SortableRowProps {
     task: DownloadableTask;
     onRemoveTask: (url: string) => void;
     onDownload: (task: DownloadableTask) => void;
-    isBatchDownloading: boolean;
+    onPause: (task: DownloadableTask) => void;
 }
 
-const SortableRow: React.FC<SortableRowProps> = ({ task, onRemoveTask, onDownload, isBatchDownloading }) => {
+const SortableRow: React.FC<SortableRowProps> = ({ task, onRemoveTask, onDownload, onPause }) => {
     const { attributes, listeners, setNodeRef, transform, transition, isDragging } = useSortable({ id: task.url });
 
     const rowStyle: React.CSSProperties = {
@@ -59,7 +59,7 @@ const SortableRow: React.FC<SortableRowProps> = ({ task, onRemoveTask, onDownloa
                     target="_blank"
                     rel="noreferrer"
                     className="task-title-link"
-                    title={task.url}
+                    title={task.tags?.length ? `${task.url}\n${task.tags.join(", ")}` : task.url}
                 >
                     {task.title}
                 </a>
@@ -83,7 +83,12 @@ const SortableRow: React.FC<SortableRowProps> = ({ task, onRemoveTask, onDownloa
                     ? new Date(task.created_at * 1000).toLocaleString()
                     : "-"}
             </td>
-            <td className="meta-text">{formatSize(task.file_size ?? -1)}</td>
+            <td className="meta-text">
+                {/* 逐頁打包的站台事先不知道大小，改顯示頁數 */}
+                {(task.file_size ?? -1) < 0 && task.page_count > 0
+                    ? `${task.page_count} 頁`
+                    : formatSize(task.file_size ?? -1)}
+            </td>
             <td>
                 {task.status === "downloading" ? (
                     <>
@@ -99,15 +104,28 @@ const SortableRow: React.FC<SortableRowProps> = ({ task, onRemoveTask, onDownloa
                             {task.speed != null && task.speed > 0 && <div>{formatSpeed(task.speed)}</div>}
                             {task.timeRemaining != null && task.timeRemaining > 0 && <div>{formatTime(task.timeRemaining)}</div>}
                         </div>
+                        {task.retryNote && (
+                            <div className="status-msg" style={{ color: "var(--danger)" }} title={task.retryNote}>
+                                {task.retryNote}
+                            </div>
+                        )}
                     </>
                 ) : task.status === "done" ? (
-                    <span className="status-badge status-done">完成 ✅</span>
+                    <div>
+                        <span className="status-badge status-done">完成 ✅</span>
+                        {task.rebuilt && (
+                            <div className="status-msg" title="ZIP 連結失效，改以逐頁圖片打包">
+                                圖片重建 🧩
+                            </div>
+                        )}
+                    </div>
                 ) : task.status === "error" ? (
                     <div>
                         <span className="status-badge status-error">錯誤 ❌</span>
                         {task.errorMessage && (
-                            <div className="status-msg" style={{ color: "var(--danger)" }} title={task.errorMessage}>
+                            <div className="status-msg" style={{ color: "var(--danger)" }} title={formatAttempts(task.attempts) ?? task.errorMessage}>
                                 {task.errorMessage}
+                                {(task.attempts?.length ?? 0) > 1 && `（已試 ${task.attempts.length} 次）`}
                             </div>
                         )}
                     </div>
@@ -122,28 +140,31 @@ const SortableRow: React.FC<SortableRowProps> = ({ task, onRemoveTask, onDownloa
                     </div>
                 ) : task.status === "paused" ? (
                     <span className="status-badge status-paused">已暫停 ⏸</span>
+                ) : task.status === "queued" ? (
+                    <span className="status-badge status-idle">排隊中 ⏳</span>
                 ) : (
                     <span className="status-badge status-idle">待下載</span>
                 )}
             </td>
             <td>
                 <div style={{ display: "flex", gap: 6 }}>
-                    {/* 下載中禁止刪除：任務移除後後端串流仍會繼續寫檔且無法單獨取消 */}
+                    {/* 下載中刪除：後端會先取消該任務的串流再移除 */}
                     <button
                         className="btn-sm btn-danger"
                         onClick={() => onRemoveTask(task.url)}
-                        disabled={task.status === "downloading"}
                     >
                         刪除
                     </button>
-                    {/* 批次進行中禁止單筆下載：並行下載共用全域取消旗標會互相干擾 */}
-                    <button
-                        className="btn-sm btn-primary"
-                        onClick={() => onDownload(task)}
-                        disabled={task.status === "downloading" || isBatchDownloading}
-                    >
-                        {task.status === "downloading" ? "下載中..." : "下載"}
-                    </button>
+                    {/* 單筆下載也是排進後端佇列；各任務有自己的取消旗標，暫停只停這一筆 */}
+                    {task.status === "downloading" || task.status === "queued" ? (
+                        <button className="btn-sm" onClick={() => onPause(task)}>
+                            暫停
+                        </button>
+                    ) : (
+                        <button className="btn-sm btn-primary" onClick={() => onDownload(task)}>
+                            下載
+                        </button>
+                    )}
                     {task.status === "done" && task.savePath && (
                         <button className="btn-sm" onClick={() => revealItemInDir(task.savePath!)}>
                             開啟
@@ -159,16 +180,16 @@ interface TaskListViewProps {
     tasks: DownloadableTask[];
     onRemoveTask: (url: string) => void;
     onDownload: (task: DownloadableTask) => void;
+    onPause: (task: DownloadableTask) => void;
     onReorder: (activeUrl: string, overUrl: string) => void;
-    isBatchDownloading: boolean;
 }
 
 export const TaskListView: React.FC<TaskListViewProps> = ({
     tasks,
     onRemoveTask,
     onDownload,
+    onPause,
     onReorder,
-    isBatchDownloading,
 }) => {
     const { colWidths, onMouseDown } = useColumnResize("task-table-col-widths", DEFAULT_WIDTHS);
 
@@ -229,7 +250,7 @@ export const TaskListView: React.FC<TaskListViewProps> = ({
                                         task={task}
                                         onRemoveTask={onRemoveTask}
                                         onDownload={onDownload}
-                                        isBatchDownloading={isBatchDownloading}
+                                        onPause={onPause}
                                     />
                                 ))
                             )}
@@ -1,6 +1,8 @@
 // Toolbar.tsx
 
 import React from "react";
+import { SiteQueueState } from "../hooks/useDownloadTasks";
+import { ArchiveMetadata } from "../lib/settingsApi";
 
 interface ToolbarProps {
     onRemoveAll: () => void;
@@ -8,7 +10,7 @@ interface ToolbarProps {
     onDownloadAll: () => void;
     onStopDownload: () => void;
     isBatchDownloading: boolean;
-    batchProgress: { current: number; total: number };
+    queueState: SiteQueueState;
     totalCount: number;
     doneCount: number;
     pendingCount: number;
@@ -16,6 +18,18 @@ interface ToolbarProps {
     hasDoneTasks: boolean;
     bandwidthKbps: number;
     onBandwidthChange: (kbps: number) => void;
+    siteConcurrency: number;
+    onConcurrencyChange: (n: number) => void;
+    archiveMetadata: ArchiveMetadata;
+    onArchiveMetadataChange: (mode: ArchiveMetadata) => void;
+    onOpenSettings: () => void;
+    onOpenBandwidth: () => void;
+    onOpenSchedule: () => void;
+    onOpenHostLimits: () => void;
+    onOpenCookies: () => void;
+    onOpenProxy: () => void;
+    /** 排程中生效的時段名稱,null = 一般設定 */
+    scheduleName: string | null;
 }
 
 export const Toolbar: React.FC<ToolbarProps> = ({
@@ -24,7 +38,7 @@ export const Toolbar: React.FC<ToolbarProps> = ({
     onDownloadAll,
     onStopDownload,
     isBatchDownloading,
-    batchProgress,
+    queueState,
     totalCount,
     doneCount,
     pendingCount,
@@ -32,6 +46,17 @@ export const Toolbar: React.FC<ToolbarProps> = ({
     hasDoneTasks,
     bandwidthKbps,
     onBandwidthChange,
+    siteConcurrency,
+    onConcurrencyChange,
+    archiveMetadata,
+    onArchiveMetadataChange,
+    onOpenSettings,
+    onOpenBandwidth,
+    onOpenSchedule,
+    onOpenHostLimits,
+    onOpenCookies,
+    onOpenProxy,
+    scheduleName,
 }) => (
     <div className="sticky-toolbar" style={{ flexDirection: "column", alignItems: "flex-start", gap: "6px" }}>
         <div style={{ display: "flex", alignItems: "center", gap: "16px", flexWrap: "wrap" }}>
@@ -50,7 +75,53 @@ export const Toolbar: React.FC<ToolbarProps> = ({
                     style={{ width: "80px" }}
                 />
                 <span>KB/s</span>
+                <button type="button" className="btn-sm" onClick={onOpenBandwidth}>
+                    分配…
+                </button>
+                <button type="button" className="btn-sm" onClick={onOpenSchedule}>
+                    排程…
+                </button>
+                <button type="button" className="btn-sm" onClick={onOpenHostLimits}>
+                    主機…
+                </button>
+                <button type="button" className="btn-sm" onClick={onOpenCookies}>
+                    Cookie…
+                </button>
+                <button type="button" className="btn-sm" onClick={onOpenProxy}>
+                    代理…
+                </button>
+                {scheduleName !== null && <span>時段:{scheduleName || "未命名"}</span>}
             </div>
+            <div className="toolbar-field">
+                <span>同時下載</span>
+                <input
+                    type="number"
+                    min="1"
+                    max="8"
+                    step="1"
+                    value={siteConcurrency}
+                    onChange={e => {
+                        const val = parseInt(e.target.value, 10);
+                        onConcurrencyChange(isNaN(val) || val < 1 ? 1 : val);
+                    }}
+                    style={{ width: "50px" }}
+                />
+            </div>
+            <div className="toolbar-field">
+                <span>作品資訊</span>
+                <select
+                    value={archiveMetadata}
+                    onChange={e => onArchiveMetadataChange(e.target.value as ArchiveMetadata)}
+                    title="下載完成後附上標題、來源網址、標籤、頁數與下載日期"
+                >
+                    <option value="none">不附加</option>
+                    <option value="comic_info">ComicInfo.xml</option>
+                    <option value="sidecar">JSON 檔</option>
+                </select>
+            </div>
+            <button type="button" className="btn-sm" onClick={onOpenSettings}>
+                存放位置…
+            </button>
             {totalCount > 0 && (
                 <div className="toolbar-summary">
                     共 {totalCount} 筆 · {doneCount} 完成 · {pendingCount} 待下載
@@ -62,7 +133,7 @@ export const Toolbar: React.FC<ToolbarProps> = ({
                 onClick={() => {
                     if (window.confirm(`確定刪除全部 ${totalCount} 筆任務？`)) onRemoveAll();
                 }}
-                // 批次下載中清掉清單會讓進行中的下載失去取消入口，先停再刪
+                // 佇列進行中清掉清單會讓進行中的下載失去取消入口，先停再刪
                 disabled={totalCount === 0 || isBatchDownloading}
             >
                 全部刪除
@@ -76,7 +147,7 @@ export const Toolbar: React.FC<ToolbarProps> = ({
                 </button>
             ) : (
                 <button className="btn-danger" onClick={onStopDownload}>
-                    停止下載 ({batchProgress.current} / {batchProgress.total})
+                    停止下載 (下載中 {queueState.running.length} · 排隊 {queueState.queued})
                 </button>
             )}
         </div>
@@ -1,7 +1,9 @@
 import { useEffect, useState } from "react";
 import { open } from "@tauri-apps/plugin-dialog";
 import { addMagnet } from "../../lib/btApi";
+import { alreadyDownloadedText } from "../../lib/libraryApi";
 import { updateAppSettings } from "../../lib/settingsApi";
+import { errorText } from "../../lib/errors";
 
 interface Props {
   defaultDir: string;
@@ -40,6 +42,10 @@ export function AddMagnetDialog({ defaultDir, onClose, onAdded, onDefaultDirSave
     setBusy(true);
     try {
       const result = await addMagnet(link, outDir || undefined, !startNow);
+      if (result.already_downloaded) {
+        setError(alreadyDownloadedText(result.already_downloaded));
+        return;
+      }
       // 存預設目錄失敗不擋加入
       if (saveAsDefault && outDir && outDir !== defaultDir) {
         try {
@@ -53,7 +59,7 @@ export function AddMagnetDialog({ defaultDir, onClose, onAdded, onDefaultDirSave
       onAdded(result.already_exists ? (result.id ?? null) : null);
       onClose();
     } catch (e) {
-      setError(String(e));
+      setError(errorText(e));
     } finally {
       setBusy(false);
     }
@@ -1,6 +1,7 @@
 import { useEffect, useState } from "react";
 import { open } from "@tauri-apps/plugin-dialog";
 import { getAppSettings, updateAppSettings, type BtSettings } from "../../lib/settingsApi";
+import { errorText } from "../../lib/errors";
 
 interface Props {
   onClose: () => void;
@@ -14,7 +15,7 @@ export function BtSettingsDialog({ onClose, onSaved }: Props) {
   useEffect(() => {
     getAppSettings()
       .then((s) => setSettings(s.bt))
-      .catch((e) => setError(String(e)));
+      .catch((e) => setError(errorText(e)));
   }, []);
 
   if (!settings) {
@@ -39,7 +40,7 @@ export function BtSettingsDialog({ onClose, onSaved }: Props) {
       onSaved(settings!);
       onClose();
     } catch (e) {
-      setError(String(e));
+      setError(errorText(e));
     }
   }
 
@@ -98,7 +99,7 @@ export function BtSettingsDialog({ onClose, onSaved }: Props) {
             }
           />
         </label>
-        <p className="hint">port 與限速重啟 app 後生效。剪貼簿偵測到 magnet 會自動下載到預設目錄。</p>
+        <p className="hint">port 重啟 app 後生效,限速即時生效(另受總頻寬的 BT 份額限制)。剪貼簿偵測到 magnet 會自動下載到預設目錄。</p>
         {error && <p className="error-text">{error}</p>}
         <div className="modal-actions">
           <button type="button" onClick={onClose}>
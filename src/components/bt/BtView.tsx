@@ -15,6 +15,7 @@ import { TorrentRow } from "./TorrentRow";
 import { PendingRow } from "./PendingRow";
 import { AddMagnetDialog } from "./AddMagnetDialog";
 import { BtSettingsDialog } from "./BtSettingsDialog";
+import { errorText } from "../../lib/errors";
 
 interface Props {
   stats: TorrentStatsEvent | null;
@@ -77,7 +78,7 @@ export function BtView({ stats }: Props) {
     try {
       await Promise.all(finished.map((t) => deleteTorrent(t.id, false)));
     } catch (e) {
-      setActionError(String(e));
+      setActionError(errorText(e));
     }
   }
 
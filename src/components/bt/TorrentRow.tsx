@@ -8,6 +8,7 @@ import {
   type TorrentStatsItem,
 } from "../../lib/btApi";
 import { formatBytes, formatEta, formatSpeed } from "../../lib/format";
+import { errorText } from "../../lib/errors";
 
 interface Props {
   t: TorrentStatsItem;
@@ -59,7 +60,7 @@ export const TorrentRow = React.memo(function TorrentRow({
     try {
       await action();
     } catch (e) {
-      onActionError(String(e));
+      onActionError(errorText(e));
     }
   }
 
@@ -1,7 +1,9 @@
 import { useEffect, useState } from "react";
 import { open } from "@tauri-apps/plugin-dialog";
-import { addHttpDownload } from "../../lib/httpApi";
+import { addHttpDownload, parseHeaderSet } from "../../lib/httpApi";
+import { alreadyDownloadedText } from "../../lib/libraryApi";
 import { getAppSettings, updateAppSettings } from "../../lib/settingsApi";
+import { errorText } from "../../lib/errors";
 
 interface Props {
   onClose: () => void;
@@ -11,6 +13,12 @@ interface Props {
 export function AddHttpDialog({ onClose, onAdded }: Props) {
   const [link, setLink] = useState("");
   const [outDir, setOutDir] = useState("");
+  const [limitKbps, setLimitKbps] = useState("");
+  const [segments, setSegments] = useState("");
+  const [mirrors, setMirrors] = useState("");
+  const [checksum, setChecksum] = useState("");
+  const [headerLines, setHeaderLines] = useState("");
+  const [cookieLine, setCookieLine] = useState("");
   const [saveAsDefault, setSaveAsDefault] = useState(false);
   const [error, setError] = useState<string | null>(null);
   const [busy, setBusy] = useState(false);
@@ -54,17 +62,34 @@ export function AddHttpDialog({ onClose, onAdded }: Props) {
     setError(null);
     setBusy(true);
     try {
-      const result = await addHttpDownload(link.trim(), outDir || undefined);
+      const kbps = parseInt(limitKbps, 10);
+      const n = parseInt(segments, 10);
+      const result = await addHttpDownload(
+        link.trim(),
+        outDir || undefined,
+        Number.isFinite(kbps) && kbps > 0 ? kbps * 1024 : undefined,
+        Number.isFinite(n) && n > 0 ? n : undefined,
+        mirrors
+          .split(/\s+/)
+          .map((m) => m.trim())
+          .filter(Boolean),
+        checksum.trim() || undefined,
+        parseHeaderSet(headerLines, cookieLine),
+      );
+      if (result.already_downloaded) {
+        setError(alreadyDownloadedText(result.already_downloaded));
+        return;
+      }
       // 勾選時記住目錄;留空 = 清除預設,回到系統下載資料夾。存失敗不擋加入
       if (saveAsDefault) {
         try {
           await updateAppSettings((s) => ({ ...s, http_default_dir: outDir.trim() }));
         } catch {}
       }
-      onAdded(result.already_exists ? result.id : null);
+      onAdded(result.already_exists ? (result.id ?? null) : null);
       onClose();
     } catch (e) {
-      setError(String(e));
+      setError(errorText(e));
     } finally {
       setBusy(false);
     }
@@ -84,6 +109,15 @@ export function AddHttpDialog({ onClose, onAdded }: Props) {
             autoFocus
           />
         </label>
+        <label>
+          鏡像連結(選填,一行一個;同一檔案的其他來源)
+          <textarea
+            rows={2}
+            placeholder="https://mirror.example.com/..."
+            value={mirrors}
+            onChange={(e) => setMirrors(e.target.value)}
+          />
+        </label>
         <label>
           下載到(留空 = 系統下載資料夾)
           <div className="dir-picker">
@@ -98,6 +132,47 @@ export function AddHttpDialog({ onClose, onAdded }: Props) {
             </button>
           </div>
         </label>
+        <label>
+          預期雜湊(選填,SHA-256 或 MD5;留空 = 用伺服器提供的)
+          <input
+            type="text"
+            placeholder="sha256:… 或 md5:…"
+            value={checksum}
+            onChange={(e) => setChecksum(e.target.value)}
+          />
+        </label>
+        <label>
+          自訂 Header(選填,一行一個 Name: value;主機符合的 Header 設定檔會自動套用)
+          <textarea
+            rows={2}
+            placeholder={"Referer: https://example.com/\nAuthorization: Bearer …"}
+            value={headerLines}
+            onChange={(e) => setHeaderLines(e.target.value)}
+          />
+        </label>
+        <label>
+          Cookie(選填,a=1; b=2)
+          <input type="text" value={cookieLine} onChange={(e) => setCookieLine(e.target.value)} />
+        </label>
+        <label>
+          此任務限速 KB/s(留空 = 不限)
+          <input
+            type="number"
+            min={1}
+            value={limitKbps}
+            onChange={(e) => setLimitKbps(e.target.value)}
+          />
+        </label>
+        <label>
+          並行連線數 1–16(留空 = 跟隨全域設定)
+          <input
+            type="number"
+            min={1}
+            max={16}
+            value={segments}
+            onChange={(e) => setSegments(e.target.value)}
+          />
+        </label>
         <label className="checkbox-label">
           <input
             type="checkbox"
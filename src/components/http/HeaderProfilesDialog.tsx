@@ -0,0 +1,134 @@
+import { useEffect, useState } from "react";
+import { getAppSettings, updateAppSettings } from "../../lib/settingsApi";
+import { formatHeaderSet, parseHeaderSet } from "../../lib/httpApi";
+import { errorText } from "../../lib/errors";
+
+interface Props {
+  onClose: () => void;
+}
+
+/** 編輯中的設定檔:header 與 cookie 以文字框呈現,存檔時才轉回 HeaderSet */
+interface Draft {
+  name: string;
+  pattern: string;
+  headerLines: string;
+  cookieLine: string;
+}
+
+const newDraft = (): Draft => ({ name: "", pattern: "", headerLines: "", cookieLine: "" });
+
+/** 依主機自動套用的直鏈 header / cookie 設定檔 */
+export function HeaderProfilesDialog({ onClose }: Props) {
+  const [drafts, setDrafts] = useState<Draft[] | null>(null);
+  const [error, setError] = useState<string | null>(null);
+
+  useEffect(() => {
+    getAppSettings()
+      .then((s) =>
+        setDrafts(
+          s.header_profiles.map((p) => ({ name: p.name, pattern: p.pattern, ...formatHeaderSet(p) })),
+        ),
+      )
+      .catch((e) => setError(errorText(e)));
+  }, []);
+
+  if (!drafts) {
+    return (
+      <div className="modal-backdrop" onClick={onClose}>
+        <div className="modal" onClick={(e) => e.stopPropagation()}>
+          {error ? <p className="error-text">{error}</p> : <p>載入中…</p>}
+        </div>
+      </div>
+    );
+  }
+
+  const setDraft = (i: number, patch: Partial<Draft>) =>
+    setDrafts(drafts.map((d, j) => (j === i ? { ...d, ...patch } : d)));
+
+  async function save() {
+    setError(null);
+    try {
+      const header_profiles = drafts!
+        .filter((d) => d.pattern.trim())
+        .map((d) => ({
+          name: d.name.trim(),
+          pattern: d.pattern.trim(),
+          ...parseHeaderSet(d.headerLines, d.cookieLine),
+        }));
+      await updateAppSettings((s) => ({ ...s, header_profiles }));
+      onClose();
+    } catch (e) {
+      setError(errorText(e));
+    }
+  }
+
+  return (
+    <div className="modal-backdrop" onClick={onClose}>
+      <div className="modal" onClick={(e) => e.stopPropagation()}>
+        <h2>Header 設定檔</h2>
+        {drafts.map((d, i) => (
+          <fieldset key={i} className="schedule-rule">
+            <label>
+              名稱
+              <input
+                type="text"
+                value={d.name}
+                placeholder="例:某某網盤"
+                onChange={(e) => setDraft(i, { name: e.target.value })}
+              />
+            </label>
+            <label>
+              主機
+              <input
+                type="text"
+                value={d.pattern}
+                placeholder="例:*.example.com"
+                onChange={(e) => setDraft(i, { pattern: e.target.value })}
+              />
+            </label>
+            <label>
+              Header(一行一個 Name: value)
+              <textarea
+                rows={3}
+                value={d.headerLines}
+                placeholder={"Referer: https://example.com/\nAuthorization: Bearer …"}
+                onChange={(e) => setDraft(i, { headerLines: e.target.value })}
+              />
+            </label>
+            <label>
+              Cookie(a=1; b=2)
+              <input
+                type="text"
+                value={d.cookieLine}
+                onChange={(e) => setDraft(i, { cookieLine: e.target.value })}
+              />
+            </label>
+            <button
+              type="button"
+              className="btn-sm btn-danger"
+              onClick={() => setDrafts(drafts.filter((_, j) => j !== i))}
+            >
+              刪除
+            </button>
+          </fieldset>
+        ))}
+        <button type="button" className="btn-sm" onClick={() => setDrafts([...drafts, newDraft()])}>
+          新增設定檔
+        </button>
+        <p className="hint">
+          直鏈的探測與每個分段請求,主機符合時自動帶上;多個設定檔符合時依序疊加,任務自己設定的同名 header
+          與 cookie 優先。值存在 app 設定檔,不會寫進 log。存檔後下一輪開跑的任務生效。
+        </p>
+        {error && <p className="error-text">{error}</p>}
+        <div className="modal-actions">
+          <button type="button" onClick={onClose}>
+            取消
+          </button>
+          <button type="button" className="btn-primary" onClick={save}>
+            儲存
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
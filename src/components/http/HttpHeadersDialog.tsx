@@ -0,0 +1,60 @@
+import { useState } from "react";
+import { parseHeaderSet, setHttpHeaders, type HttpTaskItem } from "../../lib/httpApi";
+import { errorText } from "../../lib/errors";
+
+interface Props {
+  t: HttpTaskItem;
+  onClose: () => void;
+}
+
+/** 換掉任務的自訂 header 與 cookie。原本的值不會送到前端,存檔時整組取代 */
+export function HttpHeadersDialog({ t, onClose }: Props) {
+  const [headerLines, setHeaderLines] = useState("");
+  const [cookieLine, setCookieLine] = useState("");
+  const [error, setError] = useState<string | null>(null);
+
+  async function save() {
+    setError(null);
+    try {
+      await setHttpHeaders(t.id, parseHeaderSet(headerLines, cookieLine));
+      onClose();
+    } catch (e) {
+      setError(errorText(e));
+    }
+  }
+
+  return (
+    <div className="modal-backdrop" onClick={onClose}>
+      <div className="modal" onClick={(e) => e.stopPropagation()}>
+        <h2>自訂 Header</h2>
+        <p className="hint">
+          目前:{t.header_names.length > 0 ? t.header_names.join("、") : "無"}。原本的值不會顯示,儲存後整組取代;
+          全部留空 = 清除。下一輪開跑時生效。
+        </p>
+        <label>
+          Header(一行一個 Name: value)
+          <textarea
+            rows={3}
+            value={headerLines}
+            placeholder={"Referer: https://example.com/\nAuthorization: Bearer …"}
+            onChange={(e) => setHeaderLines(e.target.value)}
+            autoFocus
+          />
+        </label>
+        <label>
+          Cookie(a=1; b=2)
+          <input type="text" value={cookieLine} onChange={(e) => setCookieLine(e.target.value)} />
+        </label>
+        {error && <p className="error-text">{error}</p>}
+        <div className="modal-actions">
+          <button type="button" onClick={onClose}>
+            取消
+          </button>
+          <button type="button" className="btn-primary" onClick={save}>
+            儲存
+          </button>
+        </div>
+      </div>
+    </div>
+  );
+}
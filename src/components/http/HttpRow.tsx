@@ -1,12 +1,16 @@
-import React from "react";
+import React, { useState } from "react";
 import {
   deleteHttpDownload,
+  addHttpMirror,
   pauseHttpDownload,
   resumeHttpDownload,
+  setHttpSpeedLimit,
   updateHttpUrl,
   type HttpTaskItem,
 } from "../../lib/httpApi";
-import { formatBytes, formatEta, formatSpeed } from "../../lib/format";
+import { formatAttempts, formatBytes, formatEta, formatSpeed } from "../../lib/format";
+import { errorText, needsNewLink } from "../../lib/errors";
+import { HttpHeadersDialog } from "./HttpHeadersDialog";
 
 interface Props {
   t: HttpTaskItem;
@@ -40,11 +44,13 @@ function badgeClass(t: HttpTaskItem): string {
 }
 
 export const HttpRow = React.memo(function HttpRow({ t, onActionError }: Props) {
+  const [editHeaders, setEditHeaders] = useState(false);
+
   async function run(action: () => Promise<void>) {
     try {
       await action();
     } catch (e) {
-      onActionError(String(e));
+      onActionError(errorText(e));
     }
   }
 
@@ -53,6 +59,19 @@ export const HttpRow = React.memo(function HttpRow({ t, onActionError }: Props)
     if (url?.trim()) run(() => updateHttpUrl(t.id, url.trim()));
   }
 
+  function onAddMirror() {
+    const url = window.prompt("貼上同一檔案的其他下載連結(下一輪開跑時確認大小相符後使用):");
+    if (url?.trim()) run(() => addHttpMirror(t.id, url.trim()));
+  }
+
+  function onSetLimit() {
+    const current = t.speed_limit_bps > 0 ? String(Math.round(t.speed_limit_bps / 1024)) : "";
+    const input = window.prompt("此任務限速 KB/s(留空或 0 = 不限):", current);
+    if (input === null) return;
+    const kbps = parseInt(input, 10);
+    run(() => setHttpSpeedLimit(t.id, Number.isFinite(kbps) && kbps > 0 ? kbps * 1024 : 0));
+  }
+
   function onDelete() {
     const withFiles = window.confirm(
       `刪除「${t.name}」。\n\n按「確定」同時刪除已下載檔案;按「取消」回到列表。`,
@@ -86,11 +105,29 @@ export const HttpRow = React.memo(function HttpRow({ t, onActionError }: Props)
             {t.total_bytes > 0 && ` / ${formatBytes(t.total_bytes)}`}
           </span>
           <span>↓ {formatSpeed(t.down_speed_bps)}</span>
+          {t.speed_limit_bps > 0 && <span>限速 {formatSpeed(t.speed_limit_bps)}</span>}
+          {t.checksum && (
+            <span title={t.checksum.hex}>
+              {t.checksum.algo === "sha256" ? "SHA-256" : "MD5"}
+              {t.state === "finished" && " ✓"}
+            </span>
+          )}
+          {t.header_names.length > 0 && (
+            <span title={t.header_names.join("\n")}>Header {t.header_names.length}</span>
+          )}
+          {t.mirror_hosts.length > 0 && (
+            <span title={t.mirror_hosts.join("\n")}>鏡像 {t.mirror_hosts.length}</span>
+          )}
           {t.state === "running" && t.total_bytes > 0 && (
             <span>剩 {formatEta(t.downloaded_bytes, t.total_bytes, t.down_speed_bps)}</span>
           )}
         </div>
-        {t.error && <div className="row-error">{t.error}</div>}
+        {t.error && (
+          <div className="row-error" title={formatAttempts(t.attempts)}>
+            {t.error.message}
+            {t.attempts.length > 1 && `（已試 ${t.attempts.length} 次）`}
+          </div>
+        )}
       </div>
       <div className="row-actions">
         {t.state === "running" && (
@@ -103,16 +140,35 @@ export const HttpRow = React.memo(function HttpRow({ t, onActionError }: Props)
             恢復
           </button>
         )}
-        {t.state === "error" && t.retryable && (
+        {t.state === "error" &&
+          t.error &&
+          !needsNewLink(t.error) &&
+          t.error.code !== "RANGE_NOT_SUPPORTED" &&
+          t.error.code !== "CHECKSUM_MISMATCH" && (
           <button type="button" className="btn-sm" onClick={() => run(() => resumeHttpDownload(t.id))}>
             重試
           </button>
         )}
-        {t.state === "error" && !t.retryable && (
+        {t.state === "error" && t.error && needsNewLink(t.error) && (
           <button type="button" className="btn-sm" onClick={onUpdateUrl}>
             更新連結
           </button>
         )}
+        {t.state !== "finished" && (
+          <button type="button" className="btn-sm" onClick={onSetLimit}>
+            限速
+          </button>
+        )}
+        {t.state !== "finished" && (
+          <button type="button" className="btn-sm" onClick={onAddMirror}>
+            加鏡像
+          </button>
+        )}
+        {t.state !== "finished" && (
+          <button type="button" className="btn-sm" onClick={() => setEditHeaders(true)}>
+            Header
+          </button>
+        )}
         <button type="button" className="btn-sm" onClick={onRemove}>
           移除
         </button>
@@ -120,6 +176,7 @@ export const HttpRow = React.memo(function HttpRow({ t, onActionError }: Props)
           刪除
         </button>
       </div>
+      {editHeaders && <HttpHeadersDialog t={t} onClose={() => setEditHeaders(false)} />}
     </div>
   );
 });
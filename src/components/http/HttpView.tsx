@@ -1,10 +1,12 @@
-// 直鏈下載分頁 — 工具列(新增、清除完成、總速度) + 任務清單 + 新增 dialog
+// 直鏈下載分頁 — 工具列(新增、清除完成、Header 設定檔、總速度) + 任務清單 + 新增 dialog
 
 import { useEffect, useState } from "react";
 import { deleteHttpDownload, type HttpStatsEvent } from "../../lib/httpApi";
 import { formatSpeed } from "../../lib/format";
 import { HttpRow } from "./HttpRow";
 import { AddHttpDialog } from "./AddHttpDialog";
+import { HeaderProfilesDialog } from "./HeaderProfilesDialog";
+import { errorText } from "../../lib/errors";
 
 interface Props {
   stats: HttpStatsEvent | null;
@@ -12,6 +14,7 @@ interface Props {
 
 export function HttpView({ stats }: Props) {
   const [showAdd, setShowAdd] = useState(false);
+  const [showProfiles, setShowProfiles] = useState(false);
   const [actionError, setActionError] = useState<string | null>(null);
 
   useEffect(() => {
@@ -29,7 +32,7 @@ export function HttpView({ stats }: Props) {
     try {
       await Promise.all(finished.map((t) => deleteHttpDownload(t.id, false)));
     } catch (e) {
-      setActionError(String(e));
+      setActionError(errorText(e));
     }
   }
 
@@ -43,6 +46,9 @@ export function HttpView({ stats }: Props) {
           <button type="button" disabled={finished.length === 0} onClick={clearFinished}>
             清除完成{finished.length > 0 ? ` (${finished.length})` : ""}
           </button>
+          <button type="button" onClick={() => setShowProfiles(true)}>
+            Header 設定檔…
+          </button>
         </div>
         <div className="toolbar-summary">
           {stats && <>↓ {formatSpeed(stats.total_down_bps)}</>}
@@ -66,6 +72,7 @@ export function HttpView({ stats }: Props) {
       </main>
 
       {showAdd && <AddHttpDialog onClose={() => setShowAdd(false)} onAdded={() => {}} />}
+      {showProfiles && <HeaderProfilesDialog onClose={() => setShowProfiles(false)} />}
     </>
   );
 }
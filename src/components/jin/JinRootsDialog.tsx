@@ -3,6 +3,7 @@
 import { useEffect, useState } from "react";
 import { open } from "@tauri-apps/plugin-dialog";
 import { getAppSettings, updateAppSettings } from "../../lib/settingsApi";
+import { errorText } from "../../lib/errors";
 
 interface Props {
   onClose: () => void;
@@ -17,7 +18,7 @@ export function JinRootsDialog({ onClose, onSaved }: Props) {
   useEffect(() => {
     getAppSettings()
       .then((s) => setRoots(s.jin_roots.length > 0 ? s.jin_roots : [""]))
-      .catch((e) => setError(String(e)));
+      .catch((e) => setError(errorText(e)));
   }, []);
 
   function setAt(i: number, val: string) {
@@ -37,7 +38,7 @@ export function JinRootsDialog({ onClose, onSaved }: Props) {
       await updateAppSettings((s) => ({ ...s, jin_roots: cleaned }));
       onSaved();
     } catch (e) {
-      setError(String(e));
+      setError(errorText(e));
     } finally {
       setBusy(false);
     }
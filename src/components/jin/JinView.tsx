@@ -14,6 +14,7 @@ import {
   type JinProgress,
 } from "../../lib/jinApi";
 import { JinRootsDialog } from "./JinRootsDialog";
+import { errorText } from "../../lib/errors";
 
 const ACTION_CLASS: Record<JinAction, string> = {
   add: "status-done",
@@ -94,7 +95,7 @@ export function JinView() {
         setPreview(p);
         setResults(null);
       } catch (e) {
-        setError(String(e));
+        setError(errorText(e));
         setPreview(null);
         setResults(null);
       } finally {
@@ -130,7 +131,7 @@ export function JinView() {
       });
       setResults(r);
     } catch (e) {
-      setError(String(e));
+      setError(errorText(e));
     } finally {
       setBusy(null);
       setProgress(null);
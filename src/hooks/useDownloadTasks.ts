@@ -4,22 +4,30 @@ import { useState, useEffect, useRef, useCallback } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { listen } from "@tauri-apps/api/event";
 import { arrayMove } from "@dnd-kit/sortable";
-import { Task, DownloadableTask } from "../types";
+import { Task, DownloadableTask, Attempt } from "../types";
+import type { AppError } from "../lib/errors";
 
 type UiStatus = NonNullable<DownloadableTask["status"]>;
 
-// download_with_progress 失敗時後端回傳 { code, message }（見 src-tauri/src/error.rs），
-// 比對 code 而非錯誤訊息子字串
-type BackendError = { code?: string; message?: string };
+// 網站下載由後端佇列執行（見 src-tauri/src/queue.rs），前端只 enqueue / 暫停 / 排序。
+// 失敗時 site-task-status 事件帶 { code, message, retryable }（見 src-tauri/src/error.rs）
 
-function errCode(err: unknown): string {
-    const code = (err as BackendError)?.code;
-    return typeof code === "string" ? code : "OTHER";
+interface SiteTaskStatusEvent {
+    url: string;
+    status: string;
+    save_path: string | null;
+    rebuilt: boolean;
+    error: AppError | null;
 }
 
-function errMessage(err: unknown): string {
-    const msg = (err as BackendError)?.message;
-    return typeof msg === "string" ? msg : String(err);
+interface SiteTaskAttemptEvent {
+    url: string;
+    attempt: Attempt;
+}
+
+export interface SiteQueueState {
+    running: string[];
+    queued: number;
 }
 
 function dbStatusToUi(dbStatus: string): UiStatus {
@@ -27,6 +35,9 @@ function dbStatusToUi(dbStatus: string): UiStatus {
         case "not_found": return "not_found";
         case "paused": return "paused";
         case "done": return "done";
+        case "queued": return "queued";
+        case "downloading": return "downloading";
+        case "error": return "error";
         default: return "idle";
     }
 }
@@ -37,17 +48,18 @@ function sortTasks(arr: DownloadableTask[]): DownloadableTask[] {
     );
 }
 
-async function persistStatus(url: string, status: string) {
-    await invoke("update_task_status", { url, status }).catch(() => {});
+function toUiTask(t: Task): DownloadableTask {
+    return {
+        ...t,
+        status: dbStatusToUi(t.db_status),
+        progress: 0,
+        savePath: t.save_path || undefined,
+    };
 }
 
 export function useDownloadTasks(baseTasks: Task[], onRemoveTask: (url: string) => void) {
-    const [tasks, setTasks] = useState<DownloadableTask[]>(() =>
-        sortTasks(baseTasks.map(t => ({ ...t, status: dbStatusToUi(t.db_status), progress: 0 })))
-    );
-    const [isBatchDownloading, setIsBatchDownloading] = useState(false);
-    const [batchProgress, setBatchProgress] = useState({ current: 0, total: 0 });
-    const shouldStop = useRef(false);
+    const [tasks, setTasks] = useState<DownloadableTask[]>(() => sortTasks(baseTasks.map(toUiTask)));
+    const [queueState, setQueueState] = useState<SiteQueueState>({ running: [], queued: 0 });
     const tasksRef = useRef<DownloadableTask[]>([]);
 
     useEffect(() => {
@@ -59,18 +71,18 @@ export function useDownloadTasks(baseTasks: Task[], onRemoveTask: (url: string)
             const keptUrls = new Set(kept.map(t => t.url));
             const added = baseTasks
                 .filter(t => !keptUrls.has(t.url))
-                .map(t => ({ ...t, status: dbStatusToUi(t.db_status), progress: 0 }));
+                .map(toUiTask);
             return sortTasks([...kept, ...added]);
         });
     }, [baseTasks]);
 
     useEffect(() => { tasksRef.current = tasks; }, [tasks]);
 
-    // --- 監聽進度事件 ---
+    // --- 監聽進度與佇列事件 ---
     useEffect(() => {
-        let unlisten: (() => void) | undefined;
+        const unlisteners: Array<() => void> = [];
         const setup = async () => {
-            unlisten = await listen<{
+            unlisteners.push(await listen<{
                 url: string;
                 progress: number;
                 speed_bytes_per_sec: number;
@@ -84,57 +96,57 @@ export function useDownloadTasks(baseTasks: Task[], onRemoveTask: (url: string)
                             : t
                     )
                 );
-            });
+            }));
+            unlisteners.push(await listen<SiteTaskStatusEvent>("site-task-status", (event) => {
+                const { url, status, save_path, rebuilt, error } = event.payload;
+                const uiStatus = dbStatusToUi(status);
+                setTasks(prev => sortTasks(prev.map(t => {
+                    if (t.url !== url) return t;
+                    const next: DownloadableTask = { ...t, status: uiStatus, db_status: status, retryNote: undefined };
+                    if (uiStatus === "downloading" || uiStatus === "queued" || uiStatus === "paused") {
+                        next.progress = 0;
+                    }
+                    if (uiStatus === "done") {
+                        next.progress = 100;
+                        next.savePath = save_path ?? undefined;
+                        next.rebuilt = rebuilt;
+                    }
+                    if (uiStatus === "not_found") {
+                        next.errorMessage = "找不到檔案 (404)";
+                    } else if (error) {
+                        next.errorMessage = error.message;
+                    }
+                    return next;
+                })));
+            }));
+            unlisteners.push(await listen<SiteTaskAttemptEvent>("site-task-attempt", (event) => {
+                const { url, attempt } = event.payload;
+                setTasks(prev => prev.map(t => {
+                    if (t.url !== url) return t;
+                    const retryNote = attempt.retry_in_secs != null
+                        ? `第 ${attempt.attempt} 次失敗，${attempt.retry_in_secs} 秒後重試：${attempt.error}`
+                        : undefined;
+                    return { ...t, attempts: [...(t.attempts ?? []), attempt], retryNote };
+                }));
+            }));
+            unlisteners.push(await listen<SiteQueueState>("site-queue-state", (event) => {
+                setQueueState(event.payload);
+            }));
+            // webview 重載後以後端佇列為準
+            setQueueState(await invoke<SiteQueueState>("get_site_queue_state"));
         };
         setup();
-        return () => unlisten?.();
+        return () => unlisteners.forEach(u => u());
     }, []);
 
-    // --- 處理下載失敗（共用邏輯），回傳是否為使用者取消 ---
-    async function applyResult(taskUrl: string, err: unknown): Promise<boolean> {
-        const code = errCode(err);
-        if (code === "CANCELLED") {
-            setTasks(prev => prev.map(t =>
-                t.url === taskUrl ? { ...t, status: "paused", progress: 0 } : t
-            ));
-            await persistStatus(taskUrl, "paused");
-            return true;
-        }
-        if (code === "NOT_FOUND") {
-            await persistStatus(taskUrl, "not_found");
-            setTasks(prev =>
-                sortTasks(prev.map(t =>
-                    t.url === taskUrl
-                        ? { ...t, status: "not_found", errorMessage: "找不到檔案 (404)" }
-                        : t
-                ))
-            );
-            return false;
-        }
-        setTasks(prev => prev.map(t =>
-            t.url === taskUrl ? { ...t, status: "error", errorMessage: errMessage(err) } : t
-        ));
-        return false;
-    }
-
-    // --- 單一下載 ---
+    // --- 單一下載：排進佇列，由後端依同時下載數開跑 ---
     const handleDownload = async (task: DownloadableTask) => {
-        setTasks(prev => prev.map(t =>
-            t.url === task.url ? { ...t, status: "downloading", progress: 0 } : t
-        ));
-        try {
-            const savePath = await invoke<string>("download_with_progress", {
-                url: task.download_page_href,
-                title: task.title,
-                fileUrl: task.file_url ?? "",
-            });
-            setTasks(prev => prev.map(t =>
-                t.url === task.url ? { ...t, status: "done", progress: 100, savePath } : t
-            ));
-            await persistStatus(task.url, "done");
-        } catch (err) {
-            await applyResult(task.url, err);
-        }
+        await invoke("enqueue_site_tasks", { urls: [task.url] }).catch(() => {});
+    };
+
+    // --- 暫停單一任務（下載中的停下、排隊中的移出佇列），不影響其他任務 ---
+    const handlePause = async (task: DownloadableTask) => {
+        await invoke("pause_download", { url: task.url }).catch(() => {});
     };
 
     // --- 清除已完成 ---
@@ -144,60 +156,21 @@ export function useDownloadTasks(baseTasks: Task[], onRemoveTask: (url: string)
         );
     }, [tasks, onRemoveTask]);
 
-    // --- 批次下載 ---
-    const handleDownloadAllSequentially = async () => {
-        if (isBatchDownloading) return;
-        shouldStop.current = false;
-        setIsBatchDownloading(true);
-        let completed = 0;
-
-        // 每輪批次中每個任務只嘗試一次：失敗任務維持 error 狀態但不再重選，
-        // 否則持久性錯誤（如伺服器一直 500）會無限重試
-        const attempted = new Set<string>();
-        const isPending = (t: DownloadableTask) =>
-            (t.status === "idle" || t.status === "error" || t.status === "paused") &&
-            !attempted.has(t.url);
-
-        while (true) {
-            if (shouldStop.current) break;
-
-            const next = tasksRef.current.find(isPending);
-            if (!next) break;
-            attempted.add(next.url);
-
-            const remaining = tasksRef.current.filter(isPending).length + 1;
-            setBatchProgress({ current: completed, total: completed + remaining });
-
-            setTasks(prev => prev.map(t =>
-                t.url === next.url ? { ...t, status: "downloading", progress: 0 } : t
-            ));
-
-            try {
-                const savePath = await invoke<string>("download_with_progress", {
-                    url: next.download_page_href,
-                    title: next.title,
-                    fileUrl: next.file_url ?? "",
-                });
-                completed++;
-                setBatchProgress(prev => ({ ...prev, current: completed }));
-                setTasks(prev => prev.map(t =>
-                    t.url === next.url ? { ...t, status: "done", progress: 100, savePath } : t
-                ));
-                await persistStatus(next.url, "done");
-            } catch (err) {
-                const cancelled = await applyResult(next.url, err);
-                if (cancelled) break;
-            }
-        }
-
-        setIsBatchDownloading(false);
+    // --- 全部下載：待下載/暫停/失敗的任務依目前順序排入佇列 ---
+    const handleDownloadAll = async () => {
+        const urls = tasksRef.current
+            .filter(t => t.status === "idle" || t.status === "error" || t.status === "paused")
+            .map(t => t.url);
+        if (urls.length === 0) return;
+        await invoke("enqueue_site_tasks", { urls }).catch(() => {});
     };
 
     const stopBatchDownload = () => {
-        shouldStop.current = true;
-        invoke("cancel_download");
+        invoke("pause_site_queue").catch(() => {});
     };
 
+    const isBatchDownloading = queueState.running.length + queueState.queued > 0;
+
     const reorderTasks = useCallback((activeUrl: string, overUrl: string) => {
         // 先算結果再 setState，invoke 不放進 updater（StrictMode 會雙呼 updater）
         const prev = tasksRef.current;
@@ -213,11 +186,12 @@ export function useDownloadTasks(baseTasks: Task[], onRemoveTask: (url: string)
         tasks,
         setTasks,
         handleDownload,
+        handlePause,
         handleClearDone,
-        handleDownloadAllSequentially,
+        handleDownloadAll,
         stopBatchDownload,
         isBatchDownloading,
-        batchProgress,
+        queueState,
         reorderTasks,
     };
 }
@@ -0,0 +1,31 @@
+// 剪貼簿偵測到、直鏈探測後或剛下載完發現是已下載過的作品/檔案時,後端推 "library-hit" — 掛 App 層轉 toast。
+
+import { useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { alreadyDownloadedText, type LibraryEntry } from "../lib/libraryApi";
+import type { Toast } from "./useTorrentStats";
+
+export function useLibraryHits() {
+  const [toasts, setToasts] = useState<Toast[]>([]);
+
+  useEffect(() => {
+    let cancelled = false;
+
+    const unlisten = listen<LibraryEntry>("library-hit", (e) => {
+      if (cancelled) return;
+      const key = Date.now() + Math.random();
+      const text = `${e.payload.title || e.payload.source_url} ${alreadyDownloadedText(e.payload)}`;
+      setToasts((t) => [...t, { key, text }]);
+      setTimeout(() => {
+        setToasts((t) => t.filter((x) => x.key !== key));
+      }, 6000);
+    });
+
+    return () => {
+      cancelled = true;
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  return { toasts };
+}
@@ -0,0 +1,29 @@
+// 時段排程切換時後端推 "schedule-profile" — 掛 App 層,工具列顯示目前時段。
+
+import { useEffect, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { getScheduleProfile, type ActiveProfile } from "../lib/settingsApi";
+
+export function useScheduleProfile() {
+  const [profile, setProfile] = useState<ActiveProfile | null>(null);
+
+  useEffect(() => {
+    let cancelled = false;
+
+    getScheduleProfile()
+      .then((p) => {
+        if (!cancelled && p) setProfile(p);
+      })
+      .catch(() => {});
+    const unlisten = listen<ActiveProfile>("schedule-profile", (e) => {
+      if (!cancelled) setProfile(e.payload);
+    });
+
+    return () => {
+      cancelled = true;
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  return profile;
+}
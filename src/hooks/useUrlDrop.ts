@@ -4,9 +4,17 @@ import { useState, useCallback, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { ClipboardPayload } from '../types';
 import { addMagnet } from '../lib/btApi';
+import { alreadyDownloadedText, type LibraryEntry } from '../lib/libraryApi';
+import { errorText } from '../lib/errors';
 
 type AddTaskFunction = (payload: ClipboardPayload) => Promise<void>;
 
+/** add_url_manually 的結果：新任務的 payload，或已下載過的既有檔案 */
+interface ManualAddResult {
+    payload?: ClipboardPayload;
+    already_downloaded?: LibraryEntry;
+}
+
 interface UseUrlDrop {
     isDragging: boolean;
     dropError: string | null;
@@ -78,7 +86,9 @@ export const useUrlDrop = (addTask: AddTaskFunction, onMagnetAdded?: () => void)
         try {
             if (url.startsWith('magnet:')) {
                 const result = await addMagnet(url);
-                if (result.already_exists) {
+                if (result.already_downloaded) {
+                    flashError(alreadyDownloadedText(result.already_downloaded));
+                } else if (result.already_exists) {
                     flashError('磁力任務已存在');
                 } else {
                     onMagnetAdded?.();
@@ -86,11 +96,15 @@ export const useUrlDrop = (addTask: AddTaskFunction, onMagnetAdded?: () => void)
                 }
                 return;
             }
-            const payload = await invoke<ClipboardPayload>('add_url_manually', { url });
-            await addTask(payload);
-            flashError(null);
+            const result = await invoke<ManualAddResult>('add_url_manually', { url });
+            if (result.already_downloaded) {
+                flashError(alreadyDownloadedText(result.already_downloaded));
+            } else if (result.payload) {
+                await addTask(result.payload);
+                flashError(null);
+            }
         } catch (err) {
-            flashError(String(err));
+            flashError(errorText(err));
         }
     }, [addTask, onMagnetAdded, flashError]);
 
@@ -1,6 +1,7 @@
 // BT 磁力下載 IPC 封裝 + TS 契約（後端 torrent/ 模組）
 
 import { invoke } from "@tauri-apps/api/core";
+import type { LibraryEntry } from "./libraryApi";
 
 // ---- Event 契約（後端 torrent/events.rs） ----
 
@@ -57,6 +58,7 @@ export interface TorrentDetails {
 
 export interface AddMagnetResult {
   already_exists?: boolean;
+  already_downloaded?: LibraryEntry; // 已下載完成過且資料夾還在,不會加入
   pending?: boolean; // add 在背景跑，透過 stats event 的 pending 清單追蹤
   key?: number;
   id?: number | null;
@@ -0,0 +1,42 @@
+// 後端錯誤契約（src-tauri/src/error.rs）：command 失敗時 reject 的值、
+// 網站任務 site-task-status 與直鏈任務的 error 欄位都是這個形狀。
+
+export type ErrorCode =
+  | "NOT_FOUND"
+  | "CANCELLED"
+  | "AUTH_EXPIRED"
+  | "FORBIDDEN"
+  | "RATE_LIMITED"
+  | "SERVER_ERROR"
+  | "NETWORK"
+  | "TIMEOUT"
+  | "DISK_FULL"
+  | "PERMISSION_DENIED"
+  | "RANGE_NOT_SUPPORTED"
+  | "INTEGRITY"
+  | "REMOTE_CHANGED"
+  | "CHECKSUM_MISMATCH"
+  | "OTHER";
+
+export interface AppError {
+  code: ErrorCode;
+  message: string;
+  /** 原樣再試可能成功;否則需使用者處理(換連結、清空間、改權限) */
+  retryable: boolean;
+  /** 429 時伺服器要求的等待秒數 */
+  retry_after_secs?: number;
+}
+
+export function isAppError(e: unknown): e is AppError {
+  return typeof e === "object" && e !== null && "code" in e && "message" in e;
+}
+
+/** catch 到的任何值轉成可顯示的訊息 */
+export function errorText(e: unknown): string {
+  return isAppError(e) ? e.message : String(e);
+}
+
+/** 連結失效類錯誤:要貼新連結才能續傳 */
+export function needsNewLink(e: AppError): boolean {
+  return e.code === "AUTH_EXPIRED" || e.code === "FORBIDDEN" || e.code === "NOT_FOUND";
+}
@@ -1,5 +1,7 @@
 // BT 分頁用的格式化工具（自 magnet-downloader 移植）
 
+import type { Attempt } from "../types";
+
 const UNITS = ["B", "KiB", "MiB", "GiB", "TiB"];
 
 export function formatBytes(bytes: number): string {
@@ -17,6 +19,18 @@ export function formatSpeed(bps: number): string {
   return `${formatBytes(bps)}/s`;
 }
 
+/** 失敗紀錄逐行列出（tooltip 用），沒有紀錄回 undefined */
+export function formatAttempts(attempts: Attempt[] | undefined): string | undefined {
+  if (!attempts || attempts.length === 0) return undefined;
+  return attempts
+    .map((a) => {
+      const time = new Date(a.at * 1000).toLocaleTimeString();
+      const retry = a.retry_in_secs != null ? `，${a.retry_in_secs} 秒後重試` : "";
+      return `#${a.attempt} ${time} ${a.error}${retry}`;
+    })
+    .join("\n");
+}
+
 export function formatEta(downloadedBytes: number, totalBytes: number, bps: number): string {
   if (bps <= 0 || totalBytes <= downloadedBytes) return "—";
   const secs = (totalBytes - downloadedBytes) / bps;
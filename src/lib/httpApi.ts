@@ -1,6 +1,9 @@
 // HTTP 直鏈下載 IPC 封裝 + TS 契約（後端 http_dl/ 模組）
 
 import { invoke } from "@tauri-apps/api/core";
+import type { Attempt } from "../types";
+import type { AppError } from "./errors";
+import type { LibraryEntry } from "./libraryApi";
 
 // ---- Event 契約（後端 http_dl/events.rs） ----
 
@@ -14,8 +17,52 @@ export interface HttpTaskItem {
   downloaded_bytes: number;
   total_bytes: number; // 0 = 未知
   down_speed_bps: number;
-  error: string | null;
-  retryable: boolean; // 網路類錯誤可直接重試；否則需貼新連結
+  speed_limit_bps: number; // 任務自己的上限,0 = 只受全域頻寬限制
+  mirror_hosts: string[]; // 仍可用的鏡像來源(只有主機名)
+  checksum: Checksum | null; // 完成後要驗證的雜湊
+  header_names: string[]; // 自訂 header 名稱(值不送前端);有 cookie 時含 "Cookie(N)"
+  error: AppError | null; // retryable = 可直接重試;連結失效類需貼新連結
+  attempts: Attempt[]; // 失敗紀錄（含自動重試），新的在後
+}
+
+export interface Checksum {
+  algo: "sha256" | "md5";
+  hex: string;
+}
+
+/** 自訂 header 與 cookie(後端 http_dl/headers.rs) */
+export interface HeaderSet {
+  headers: Record<string, string>;
+  cookies: Record<string, string>;
+}
+
+/** "Name: value" 一行一個 + 瀏覽器複製的 "a=1; b=2" cookie 字串 → HeaderSet */
+export function parseHeaderSet(headerLines: string, cookieLine: string): HeaderSet {
+  const headers: Record<string, string> = {};
+  for (const line of headerLines.split("\n")) {
+    const i = line.indexOf(":");
+    if (i <= 0) continue;
+    headers[line.slice(0, i).trim()] = line.slice(i + 1).trim();
+  }
+  const cookies: Record<string, string> = {};
+  for (const pair of cookieLine.split(";")) {
+    const i = pair.indexOf("=");
+    if (i <= 0) continue;
+    cookies[pair.slice(0, i).trim()] = pair.slice(i + 1).trim();
+  }
+  return { headers, cookies };
+}
+
+/** parseHeaderSet 的反向,給編輯框帶入 */
+export function formatHeaderSet(set: HeaderSet): { headerLines: string; cookieLine: string } {
+  return {
+    headerLines: Object.entries(set.headers)
+      .map(([k, v]) => `${k}: ${v}`)
+      .join("\n"),
+    cookieLine: Object.entries(set.cookies)
+      .map(([k, v]) => `${k}=${v}`)
+      .join("; "),
+  };
 }
 
 export interface HttpStatsEvent {
@@ -32,11 +79,43 @@ export interface HttpFinishedEvent {
 
 export interface AddHttpResult {
   already_exists?: boolean;
-  id: number;
+  already_downloaded?: LibraryEntry; // 已下載完成過且檔案還在,不會加入
+  id?: number;
+}
+
+export function addHttpDownload(
+  url: string,
+  outDir?: string,
+  speedLimitBps?: number,
+  segments?: number,
+  mirrors?: string[],
+  checksum?: string,
+  headers?: HeaderSet,
+): Promise<AddHttpResult> {
+  return invoke("add_http_download", {
+    url,
+    outDir: outDir ?? null,
+    speedLimitBps: speedLimitBps ?? null,
+    segments: segments ?? null,
+    mirrors: mirrors ?? null,
+    checksum: checksum ?? null,
+    headers: headers ?? null,
+  });
+}
+
+/** 加一個同一檔案的鏡像連結,下一輪開跑時驗證後使用。 */
+export function addHttpMirror(id: number, url: string): Promise<void> {
+  return invoke("add_http_mirror", { id, url });
+}
+
+/** 換掉任務的自訂 header 與 cookie,下一輪開跑時生效。 */
+export function setHttpHeaders(id: number, headers: HeaderSet): Promise<void> {
+  return invoke("set_http_headers", { id, headers });
 }
 
-export function addHttpDownload(url: string, outDir?: string): Promise<AddHttpResult> {
-  return invoke("add_http_download", { url, outDir: outDir ?? null });
+/** 調整任務速率上限(bytes/s,0 = 不限),下載中即時生效。 */
+export function setHttpSpeedLimit(id: number, speedLimitBps: number): Promise<void> {
+  return invoke("set_http_speed_limit", { id, speedLimitBps });
 }
 
 export function pauseHttpDownload(id: number): Promise<void> {
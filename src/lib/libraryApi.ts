@@ -0,0 +1,20 @@
+// 已完成下載索引 TS 契約（後端 library.rs）
+
+export interface LibraryEntry {
+  id: number;
+  kind: "site" | "http" | "bt";
+  source_url: string;
+  site: string;
+  gallery_id: string;
+  title: string;
+  file_path: string; // BT 為任務資料夾
+  size: number;
+  sha256: string;
+  completed_at: number;
+  etag: string; // 直鏈完成時的 ETag,其他為空
+}
+
+/** 「已經有了」提示文字:含既有檔案位置 */
+export function alreadyDownloadedText(entry: LibraryEntry): string {
+  return `已下載過:${entry.file_path}`;
+}
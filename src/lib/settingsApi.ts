@@ -2,6 +2,7 @@
 // 後端會用到的設定走這裡；純 UI 偏好（theme/activeTab/音量/欄寬）留 localStorage。
 
 import { invoke } from "@tauri-apps/api/core";
+import type { HeaderSet } from "./httpApi";
 
 export interface BtSettings {
   default_download_dir: string;
@@ -10,10 +11,131 @@ export interface BtSettings {
   download_limit_bps: number | null;
 }
 
+/** 網站下載完成後附上作品資訊的方式 */
+export type ArchiveMetadata = "none" | "comic_info" | "sidecar";
+
+/** 可自動重試的錯誤類別 */
+export type RetryClass = "network" | "server" | "rate_limited" | "integrity";
+
+/** 網站/直鏈下載失敗的自動重試策略（後端 retry.rs） */
+export interface RetryPolicy {
+  /** 含第一次在內的總嘗試次數,1 = 不重試 */
+  max_attempts: number;
+  /** 第一次重試前等待秒數,之後每次加倍 */
+  base_delay_secs: number;
+  max_delay_secs: number;
+  /** 抖動比例 0–1 */
+  jitter: number;
+  retry_on: RetryClass[];
+}
+
+/** 各引擎最多可用總頻寬的比例(%),總量不限時不作用 */
+export interface BandwidthShares {
+  site: number;
+  http: number;
+  bt: number;
+}
+
+/** 進入時段時對所有引擎做的事 */
+export type ScheduleAction = "none" | "pause_all" | "start_all";
+
+/** 時段規則(後端 schedule.rs) */
+export interface ScheduleRule {
+  name: string;
+  /** 1 = 週一 … 7 = 週日,空 = 每天 */
+  days: number[];
+  /** "HH:MM",結束早於開始表示跨午夜,相同表示整天 */
+  start: string;
+  end: string;
+  /** 時段內總頻寬 KB/s,0 = 不限,null = 沿用一般設定 */
+  bandwidth_limit_kbps: number | null;
+  action: ScheduleAction;
+}
+
+export interface Schedule {
+  enabled: boolean;
+  /** 依序比對,第一個符合的生效 */
+  rules: ScheduleRule[];
+}
+
+/** 單一主機樣式的連線限制(後端 hosts.rs) */
+export interface HostRule {
+  /** example.com 或 *.example.com(含 example.com 本身) */
+  pattern: string;
+  /** 同時連線數上限,0 = 不限 */
+  max_connections: number;
+  /** 兩次請求至少間隔毫秒 */
+  min_interval_ms: number;
+}
+
+export interface HostLimits {
+  /** 沒有規則符合時的預設值 */
+  max_connections: number;
+  min_interval_ms: number;
+  /** 429/503 沒帶 Retry-After 時暫停該主機的秒數 */
+  backoff_secs: number;
+  /** 依序比對,第一個符合的生效 */
+  rules: HostRule[];
+}
+
+/** 依主機自動套用的直鏈 header 設定檔(後端 http_dl/headers.rs) */
+export interface HeaderProfile extends HeaderSet {
+  name: string;
+  /** example.com 或 *.example.com(含 example.com 本身) */
+  pattern: string;
+}
+
+export type ProxyKind = "http" | "https" | "socks5";
+
+/** 代理設定(後端 proxy.rs) */
+export interface ProxySettings {
+  enabled: boolean;
+  kind: ProxyKind;
+  host: string;
+  port: number;
+  /** 空 = 不需驗證 */
+  username: string;
+  password: string;
+  /** 直連的主機(example.com 或 *.example.com) */
+  bypass: string[];
+  /** 各引擎是否走代理;BT 只支援 SOCKS5 */
+  site: boolean;
+  http: boolean;
+  bt: boolean;
+}
+
+/** 目前生效的時段 */
+export interface ActiveProfile {
+  rule: number | null;
+  name: string | null;
+  bandwidth_limit_kbps: number;
+  action: ScheduleAction;
+}
+
 export interface AppSettings {
   monitor_clipboard: boolean;
+  /** 總頻寬限制 KB/s(網站、直鏈、BT 合計),0 = 不限 */
   bandwidth_limit_kbps: number;
+  bandwidth_shares: BandwidthShares;
+  schedule: Schedule;
+  /** 網站下載佇列同時進行數 */
+  site_concurrency: number;
+  host_limits: HostLimits;
+  /** 單一檔案的並行連線數(直鏈任務未指定時與網站整包下載用) */
+  download_segments: number;
+  /** 直鏈請求依主機自動帶的 header 與 cookie,同名時以任務自己的為準 */
+  header_profiles: HeaderProfile[];
+  proxy: ProxySettings;
   http_default_dir: string;
+  /** 網站下載存放目錄,空 = 系統下載資料夾 */
+  site_download_dir: string;
+  /** 網站下載檔名樣板(不含 .zip):{title} {site} {id} {date} */
+  site_filename_template: string;
+  /** 網站下載依站台分子資料夾 */
+  site_subfolder_per_site: boolean;
+  /** ComicInfo.xml 寫進 ZIP，或 ZIP 旁放同名 .json */
+  archive_metadata: ArchiveMetadata;
+  retry: RetryPolicy;
   bt: BtSettings;
   /** 工作需求遊戲設定分頁掃描的根目錄 */
   jin_roots: string[];
@@ -27,6 +149,30 @@ export function saveAppSettings(settings: AppSettings): Promise<void> {
   return invoke("save_app_settings", { settings });
 }
 
+export function getScheduleProfile(): Promise<ActiveProfile | null> {
+  return invoke("get_schedule_profile");
+}
+
+/** 共用 cookie jar 的某個網域(後端 cookies.rs,只有名稱) */
+export interface CookieDomain {
+  domain: string;
+  names: string[];
+}
+
+/** 匯入 Netscape 格式 cookies.txt,回傳匯入幾筆 */
+export function importCookies(path: string): Promise<number> {
+  return invoke("import_cookies", { path });
+}
+
+export function listCookies(): Promise<CookieDomain[]> {
+  return invoke("list_cookies");
+}
+
+/** 清除某網域的 cookie,不給 = 全部;回傳清掉幾筆 */
+export function clearCookies(domain?: string): Promise<number> {
+  return invoke("clear_cookies", { domain: domain ?? null });
+}
+
 /** get → 改 → 存:各元件不持有設定複本,避免互相蓋掉 */
 export async function updateAppSettings(
   patch: (s: AppSettings) => AppSettings,
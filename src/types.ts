@@ -1,5 +1,13 @@
 // src/types.ts
 
+/** 一次下載失敗（含自動重試）的紀錄，見 src-tauri/src/retry.rs */
+export interface Attempt {
+    attempt: number; // 第幾次嘗試，從 1 起算
+    at: number; // unix 秒
+    error: string;
+    retry_in_secs: number | null; // null = 不再自動重試
+}
+
 export interface Task {
     url: string;
     title: string;
@@ -9,15 +17,22 @@ export interface Task {
     file_size: number; // 位元組，-1 = 未知
     created_at: number;
     db_status: string;
+    save_path: string; // 完成後的存檔路徑，未完成為空字串
+    downloaded_bytes: number; // .part 已下載位元組（續傳起點），完成後歸零
+    page_count: number; // 頁數，0 = 未知
+    tags: string[]; // `{類型}:{名稱}`
+    rebuilt: boolean; // ZIP 連結失效、改以逐頁圖片打包
+    attempts: Attempt[]; // 失敗紀錄，新的在後
 }
 
 export interface DownloadableTask extends Task {
     progress?: number;
     speed?: number;
     timeRemaining?: number;
-    status?: "idle" | "downloading" | "done" | "error" | "paused" | "not_found";
+    status?: "idle" | "queued" | "downloading" | "done" | "error" | "paused" | "not_found";
     savePath?: string;
     errorMessage?: string;
+    retryNote?: string; // 自動重試倒數中的說明
 }
 
 export interface ClipboardPayload {
@@ -29,4 +44,10 @@ export interface ClipboardPayload {
     file_size: number; // 位元組，-1 = 未知
     created_at: number;
     db_status: string;
+    save_path: string;
+    downloaded_bytes: number;
+    page_count: number;
+    tags: string[];
+    rebuilt: boolean;
+    attempts: Attempt[];
 }